@@ -98,6 +98,22 @@ pub mod asm {
             llvm_asm!("msr elr_el3, $0" :  : "r"(spsr) : : "volatile");
         }
     }
+    #[inline(always)]
+    ///Get Thread Pointer of User Mode (thread local storage base)
+    pub fn get_el0_thread_pointer() -> u64 {
+        let value: u64;
+        unsafe {
+            llvm_asm!("mrs $0, tpidr_el0" : "=r"(value) : : : "volatile");
+        }
+        value
+    }
+    #[inline(always)]
+    ///Set Thread Pointer of User Mode (thread local storage base)
+    pub fn set_el0_thread_pointer(tp: u64) {
+        unsafe {
+            llvm_asm!("msr tpidr_el0, $0" : : "r"(tp) : : "volatile");
+        }
+    }
     /// enable usage of physical timer in el1
     #[inline(always)]
     pub fn initialize_timers_el1() {
@@ -13,6 +13,11 @@ pub use num_traits::FromPrimitive;
 
 const BRK_FLAG: u64 = 0b111100;
 const SVC_FLAG: u64 = 0b010101;
+const INSTRUCTION_ABORT_LOWER_EL: u64 = 0b100000;
+const PC_ALIGNMENT_FAULT: u64 = 0b100010;
+const DATA_ABORT_LOWER_EL: u64 = 0b100100;
+const DATA_ABORT_CURRENT_EL: u64 = 0b100101;
+const SP_ALIGNMENT_FAULT: u64 = 0b100110;
 
 fn handle_chcek_el(e: &mut ExceptionContext) {
     e.gpr[0] = match e.spsr_el1 & 0b1111 {
@@ -64,6 +69,42 @@ fn default_exception_handler(context: &mut ExceptionContext, source: &str) {
     panic!("Unknown {} Exception type recived.", source);
 }
 
+/// Data abort caused by write to page without write permission
+fn is_write_permission_fault(esr: u64) -> bool {
+    const WRITE_NOT_READ: u64 = 1 << 6;
+    let fault_status = esr & 0b111111;
+    esr & WRITE_NOT_READ != 0 && (0b001100..=0b001111).contains(&fault_status)
+}
+
+/// Resolves write to page shared after fork, returns false if it wasn't such write
+fn handle_copy_on_write(context: &ExceptionContext) -> bool {
+    is_write_permission_fault(context.esr_el1)
+        && scheduler::handle_copy_on_write(context.far_el1 as usize)
+}
+
+/// Turns exception caused by user task into signal instead of kernel panic
+fn handle_task_fault(context: &mut ExceptionContext, exception_type: u64) {
+    use scheduler::signals::Signal;
+    if exception_type == DATA_ABORT_LOWER_EL && handle_copy_on_write(context) {
+        return;
+    }
+    let signal = match exception_type {
+        INSTRUCTION_ABORT_LOWER_EL | DATA_ABORT_LOWER_EL => Signal::SegmentationFault,
+        PC_ALIGNMENT_FAULT | SP_ALIGNMENT_FAULT => Signal::BusError,
+        _ => Signal::IllegalInstruction,
+    };
+    crate::println!(
+        "[Task Fault]\n\tTask:                {}\n\tSignal:              {:?}\n\tReason:              '{:#018x}'\n\tProgram location:    '{:#018x}'\n\tAddress:             '{:#018x}'",
+        scheduler::get_current_task_pid(),
+        signal,
+        context.esr_el1,
+        context.elr_el1,
+        context.far_el1,
+    );
+    let task = unsafe { &mut *scheduler::get_current_task_context() };
+    task.signals.force(signal);
+}
+
 //------------------------------------------------------------------------------
 // Current, EL0
 //------------------------------------------------------------------------------
@@ -111,6 +152,7 @@ unsafe extern "C" fn current_elx_synchronous(e: &mut ExceptionContext) {
         e.spsr_el1 = 0b0;
         e.elr_el1 = e.gpr[2] & (!crate::KERNEL_OFFSET) as u64;
     } else if exception_type == SVC_FLAG {
+        scheduler::account_syscall();
         let syscall_type = Syscalls::from_u64(e.gpr[8])
             .unwrap_or_else(|| panic!("Unknown syscall type {}", e.gpr[8]));
         match syscall_type {
@@ -143,7 +185,61 @@ unsafe extern "C" fn current_elx_synchronous(e: &mut ExceptionContext) {
                 }
             }
             Syscalls::SetPipeReadOnPID => syscall::files::handle_set_pipe_read_on_pid(e),
+            Syscalls::Wait => syscall::wait::handle_wait(e),
+            Syscalls::Kill => syscall::signals::handle_kill(e),
+            Syscalls::SignalAction => syscall::signals::handle_signal_action(e),
+            Syscalls::SignalMask => syscall::signals::handle_signal_mask(e),
+            Syscalls::SignalReturn => syscall::signals::handle_signal_return(e),
+            Syscalls::Exec => syscall::exec::handle_exec(e),
+            Syscalls::Fork => syscall::fork::handle_fork(e),
+            Syscalls::CreateThread => syscall::threads::handle_create_thread(e),
+            Syscalls::JoinThread => syscall::threads::handle_join_thread(e),
+            Syscalls::DetachThread => syscall::threads::handle_detach_thread(e),
+            Syscalls::DuplicateFd => syscall::files::dup::handle_dup(e),
+            Syscalls::DuplicateFdTo => syscall::files::dup::handle_dup2(e),
+            Syscalls::SetCloseOnExec => syscall::files::dup::handle_set_close_on_exec(e),
+            Syscalls::CreatePipe => syscall::files::pipe::handle_pipe(e),
+            Syscalls::PipeCapacity => syscall::files::pipe::handle_pipe_capacity(e),
+            Syscalls::Poll => syscall::files::poll::handle_poll(e),
+            Syscalls::GetEnvironmentVariable => syscall::environment::handle_getenv(e),
+            Syscalls::SetEnvironmentVariable => syscall::environment::handle_setenv(e),
+            Syscalls::RemoveEnvironmentVariable => syscall::environment::handle_unsetenv(e),
+            Syscalls::ChangeDirectory => syscall::files::directory::handle_chdir(e),
+            Syscalls::GetCurrentDirectory => syscall::files::directory::handle_getcwd(e),
+            Syscalls::GetTime => syscall::clock::handle_get_time(e),
+            Syscalls::SetTime => syscall::clock::handle_set_time(e),
+            Syscalls::GetResourceUsage => syscall::rusage::handle_getrusage(e),
+            Syscalls::GetResourceLimit => syscall::rlimit::handle_getrlimit(e),
+            Syscalls::SetResourceLimit => syscall::rlimit::handle_setrlimit(e),
+            Syscalls::Sbrk => syscall::memory::handle_sbrk(e),
+            Syscalls::MapMemory => syscall::memory::handle_mmap(e),
+            Syscalls::UnmapMemory => syscall::memory::handle_munmap(e),
+            Syscalls::CreateSharedMemory => syscall::shared_memory::handle_shm_create(e),
+            Syscalls::AttachSharedMemory => syscall::shared_memory::handle_shm_attach(e),
+            Syscalls::DetachSharedMemory => syscall::shared_memory::handle_shm_detach(e),
+            Syscalls::CreatePort => syscall::ports::handle_create_port(e),
+            Syscalls::OpenPort => syscall::ports::handle_open_port(e),
+            Syscalls::SendMessage => syscall::ports::handle_send_message(e),
+            Syscalls::ReceiveMessage => syscall::ports::handle_receive_message(e),
+            Syscalls::ReplyMessage => syscall::ports::handle_reply_message(e),
+            Syscalls::FutexWait => syscall::futex::handle_futex_wait(e),
+            Syscalls::FutexWake => syscall::futex::handle_futex_wake(e),
+            Syscalls::GetAsyncOverflowCount => {
+                syscall::asynchronous::handle_get_overflow_count::handle_get_overflow_count(e)
+            }
+            Syscalls::AsyncDoorbell => {
+                syscall::asynchronous::async_worker::handle_async_doorbell(e)
+            }
+            Syscalls::AsyncEnter => syscall::asynchronous::async_enter::handle_async_enter(e),
+            Syscalls::RegisterAsyncBuffer => {
+                syscall::asynchronous::registered_buffers::handle_register_buffer(e)
+            }
+            Syscalls::UnregisterAsyncBuffer => {
+                syscall::asynchronous::registered_buffers::handle_unregister_buffer(e)
+            }
         }
+    } else if exception_type == DATA_ABORT_CURRENT_EL && handle_copy_on_write(e) {
+        // kernel wrote to user memory shared after fork, retry the write
     } else {
         default_exception_handler(e, "current_elx_synchronous");
     }
@@ -178,7 +274,8 @@ unsafe extern "C" fn current_elx_irq(_e: &mut ExceptionContext) {
         return;
     }
     IS_SCHEDULING.store(true, core::sync::atomic::Ordering::Relaxed);
-    crate::syscall::asynchronous::handle_async_syscalls::handle_async_syscalls();
+    crate::syscall::asynchronous::async_worker::wake_async_worker_if_needed();
+    scheduler::wake_expired_pollers(timer.get_time());
 
     scheduler::switch_task();
     IS_SCHEDULING.store(false, core::sync::atomic::Ordering::Relaxed);
@@ -201,6 +298,7 @@ unsafe extern "C" fn current_elx_serror(e: &mut ExceptionContext) {
 #[no_mangle]
 unsafe extern "C" fn lower_aarch64_synchronous(e: &mut ExceptionContext) {
     interupts::disable_irqs();
+    scheduler::account_user_time();
 
     // crate::print!("l");
 
@@ -220,6 +318,7 @@ unsafe extern "C" fn lower_aarch64_synchronous(e: &mut ExceptionContext) {
     if exception_type == BRK_FLAG {
         e.elr_el1 = e.gpr[2] | crate::KERNEL_OFFSET as u64;
     } else if exception_type == SVC_FLAG {
+        scheduler::account_syscall();
         let syscall_type = Syscalls::from_u64(e.gpr[8])
             .unwrap_or_else(|| panic!("Unknown syscall type {}", e.gpr[8]));
         match syscall_type {
@@ -252,17 +351,72 @@ unsafe extern "C" fn lower_aarch64_synchronous(e: &mut ExceptionContext) {
                 }
             }
             Syscalls::SetPipeReadOnPID => syscall::files::handle_set_pipe_read_on_pid(e),
+            Syscalls::Wait => syscall::wait::handle_wait(e),
+            Syscalls::Kill => syscall::signals::handle_kill(e),
+            Syscalls::SignalAction => syscall::signals::handle_signal_action(e),
+            Syscalls::SignalMask => syscall::signals::handle_signal_mask(e),
+            Syscalls::SignalReturn => syscall::signals::handle_signal_return(e),
+            Syscalls::Exec => syscall::exec::handle_exec(e),
+            Syscalls::Fork => syscall::fork::handle_fork(e),
+            Syscalls::CreateThread => syscall::threads::handle_create_thread(e),
+            Syscalls::JoinThread => syscall::threads::handle_join_thread(e),
+            Syscalls::DetachThread => syscall::threads::handle_detach_thread(e),
+            Syscalls::DuplicateFd => syscall::files::dup::handle_dup(e),
+            Syscalls::DuplicateFdTo => syscall::files::dup::handle_dup2(e),
+            Syscalls::SetCloseOnExec => syscall::files::dup::handle_set_close_on_exec(e),
+            Syscalls::CreatePipe => syscall::files::pipe::handle_pipe(e),
+            Syscalls::PipeCapacity => syscall::files::pipe::handle_pipe_capacity(e),
+            Syscalls::Poll => syscall::files::poll::handle_poll(e),
+            Syscalls::GetEnvironmentVariable => syscall::environment::handle_getenv(e),
+            Syscalls::SetEnvironmentVariable => syscall::environment::handle_setenv(e),
+            Syscalls::RemoveEnvironmentVariable => syscall::environment::handle_unsetenv(e),
+            Syscalls::ChangeDirectory => syscall::files::directory::handle_chdir(e),
+            Syscalls::GetCurrentDirectory => syscall::files::directory::handle_getcwd(e),
+            Syscalls::GetTime => syscall::clock::handle_get_time(e),
+            Syscalls::SetTime => syscall::clock::handle_set_time(e),
+            Syscalls::GetResourceUsage => syscall::rusage::handle_getrusage(e),
+            Syscalls::GetResourceLimit => syscall::rlimit::handle_getrlimit(e),
+            Syscalls::SetResourceLimit => syscall::rlimit::handle_setrlimit(e),
+            Syscalls::Sbrk => syscall::memory::handle_sbrk(e),
+            Syscalls::MapMemory => syscall::memory::handle_mmap(e),
+            Syscalls::UnmapMemory => syscall::memory::handle_munmap(e),
+            Syscalls::CreateSharedMemory => syscall::shared_memory::handle_shm_create(e),
+            Syscalls::AttachSharedMemory => syscall::shared_memory::handle_shm_attach(e),
+            Syscalls::DetachSharedMemory => syscall::shared_memory::handle_shm_detach(e),
+            Syscalls::CreatePort => syscall::ports::handle_create_port(e),
+            Syscalls::OpenPort => syscall::ports::handle_open_port(e),
+            Syscalls::SendMessage => syscall::ports::handle_send_message(e),
+            Syscalls::ReceiveMessage => syscall::ports::handle_receive_message(e),
+            Syscalls::ReplyMessage => syscall::ports::handle_reply_message(e),
+            Syscalls::FutexWait => syscall::futex::handle_futex_wait(e),
+            Syscalls::FutexWake => syscall::futex::handle_futex_wake(e),
+            Syscalls::GetAsyncOverflowCount => {
+                syscall::asynchronous::handle_get_overflow_count::handle_get_overflow_count(e)
+            }
+            Syscalls::AsyncDoorbell => {
+                syscall::asynchronous::async_worker::handle_async_doorbell(e)
+            }
+            Syscalls::AsyncEnter => syscall::asynchronous::async_enter::handle_async_enter(e),
+            Syscalls::RegisterAsyncBuffer => {
+                syscall::asynchronous::registered_buffers::handle_register_buffer(e)
+            }
+            Syscalls::UnregisterAsyncBuffer => {
+                syscall::asynchronous::registered_buffers::handle_unregister_buffer(e)
+            }
         }
     } else {
-        default_exception_handler(e, "lower_aarch64_synchronous");
+        handle_task_fault(e, exception_type);
     }
+    scheduler::signals::deliver_pending_signals(e);
+    scheduler::account_system_time();
 
     // interupts::enable_irqs();
 }
 
 #[no_mangle]
-unsafe extern "C" fn lower_aarch64_irq(_e: &mut ExceptionContext) {
+unsafe extern "C" fn lower_aarch64_irq(e: &mut ExceptionContext) {
     interupts::disable_irqs();
+    scheduler::account_user_time();
 
     crate::io::input_to_buffer();
     use crate::drivers::rpi3_interrupt_controller::IRQType;
@@ -271,6 +425,7 @@ unsafe extern "C" fn lower_aarch64_irq(_e: &mut ExceptionContext) {
     let mut controler = crate::drivers::INTERRUPT_CONTROLLER.lock();
     if controler.is_pending_irq(IRQType::Uart) {
         crate::eprintln!("UART");
+        scheduler::account_system_time();
         return;
     }
 
@@ -279,14 +434,18 @@ unsafe extern "C" fn lower_aarch64_irq(_e: &mut ExceptionContext) {
     timer.enable();
 
     if IS_SCHEDULING.load(core::sync::atomic::Ordering::Relaxed) {
+        scheduler::account_system_time();
         return;
     }
     IS_SCHEDULING.store(true, core::sync::atomic::Ordering::Relaxed);
 
-    crate::syscall::asynchronous::handle_async_syscalls::handle_async_syscalls();
+    crate::syscall::asynchronous::async_worker::wake_async_worker_if_needed();
+    scheduler::wake_expired_pollers(timer.get_time());
 
     scheduler::switch_task();
     IS_SCHEDULING.store(false, core::sync::atomic::Ordering::Relaxed);
+    scheduler::signals::deliver_pending_signals(e);
+    scheduler::account_system_time();
 }
 
 #[no_mangle]
@@ -63,9 +63,13 @@ pub fn input_to_buffer() {
     // crate::println!("AAA");
     // crate::println!("Buffer: {:x}", &*buffer as *const VecDeque<u8> as u64);
 
+    let previous_len = buffer.len();
     while let Some(b) = uart.try_getb() {
         buffer.push_back(b);
     }
+    if buffer.len() > previous_len {
+        crate::scheduler::wake_pollers(crate::syscall::files::poll::PollObject::Stdin);
+    }
 }
 
 pub fn read_input(my_buffer: &mut [u8]) -> usize {
@@ -108,11 +108,32 @@ unsafe fn jump_to_kernel_space(f: fn() -> !) -> ! {
 fn echo() -> ! {
     // config::set_debug_alloc(true);
     // config::set_debug_mmu(true);
-    let shell = scheduler::task_context::TaskContext::new(userspace::ushell, &[], false)
-        .expect("Error creating task 1 context");
+    let mut environment = scheduler::environment::Environment::new();
+    environment.set("USER", "default").ok();
+    environment.set("HOME", "/").ok();
+    let limits = scheduler::resource_limits::ResourceLimits::new();
+    let shell = scheduler::task_context::TaskContext::new(
+        userspace::ushell,
+        &[],
+        &environment,
+        &limits,
+        false,
+    )
+    .expect("Error creating task 1 context");
 
     scheduler::add_task(shell).expect("Error adding task 1");
 
+    let async_worker = scheduler::task_context::TaskContext::new(
+        syscall::asynchronous::async_worker::async_worker,
+        &[],
+        &environment,
+        &limits,
+        true,
+    )
+    .expect("Error creating async worker context");
+
+    scheduler::add_task(async_worker).expect("Error adding async worker");
+
     unsafe {
         interupts::init_exceptions(
             utils::binary_info::BinaryInfo::get().exception_vector | KERNEL_OFFSET,
@@ -32,7 +32,7 @@ unsafe impl GlobalAlloc for ChooseAllocator {
                 allocator.alloc(layout)
             }
             ExceptionLevel::User => {
-                let allocator = kernel_allocator::ALLOCATOR.lock();
+                let allocator = user_allocator::USER_ALLOCATOR.lock();
                 allocator.alloc(layout)
             }
             _ => null_mut(),
@@ -46,7 +46,7 @@ unsafe impl GlobalAlloc for ChooseAllocator {
                 allocator.dealloc(ptr, layout)
             }
             ExceptionLevel::User => {
-                let allocator = kernel_allocator::ALLOCATOR.lock();
+                let allocator = user_allocator::USER_ALLOCATOR.lock();
                 allocator.dealloc(ptr, layout)
             }
             _ => panic!("Global Allocator in invalid context!"),
@@ -70,5 +70,10 @@ pub fn kernel_heap_range() -> Range<usize> {
 #[alloc_error_handler]
 pub fn bad_alloc(layout: Layout) -> ! {
     crate::println!("bad_alloc: {:?}", layout);
+    if let ExceptionLevel::User = unsafe { get_level() } {
+        // only the task is aborted, signal is delivered on return from kill
+        let pid = crate::syscall::get_pid() as u64;
+        crate::syscall::signals::kill(pid, crate::scheduler::signals::Signal::Abort).ok();
+    }
     crate::aarch64::halt()
 }
@@ -1,18 +1,137 @@
 use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::ptr::null_mut;
 
-pub struct UserAllocator;
+use crate::sync::mutex::Mutex;
+use crate::syscall::memory::sbrk;
 
-unsafe impl GlobalAlloc for UserAllocator {
-    unsafe fn alloc(&self, _layout: Layout) -> *mut u8 {
-        unimplemented!();
+/// Every block is multiple of this size and aligned to it, so free blocks can hold their header
+const BLOCK_ALIGN: usize = 16;
+/// Minimal amount by which heap is grown
+const GROW_SIZE: usize = 0x4000;
+
+/// Header stored at the beginning of free block
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+/// Allocator of EL0 tasks, managing heap grown with sbrk. Free blocks are kept in list sorted by
+/// address and merged with neighbours, allocated blocks have no header as layout is passed
+/// on dealloc.
+pub struct UserAllocator {
+    free_list: UnsafeCell<*mut FreeBlock>,
+}
+
+unsafe impl Sync for UserAllocator {}
+
+fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+fn block_size(layout: Layout) -> usize {
+    round_up(core::cmp::max(layout.size(), 1), BLOCK_ALIGN)
+}
+
+impl UserAllocator {
+    /// Allocator with empty heap, all zeroes, so it is valid in zeroed task local memory
+    pub const fn new() -> Self {
+        UserAllocator {
+            free_list: UnsafeCell::new(null_mut()),
+        }
     }
-    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
-        unimplemented!();
+
+    /// Carves block of given size and alignment out of first free block that can hold it
+    unsafe fn take_free(&self, size: usize, align: usize) -> *mut u8 {
+        let mut previous: *mut FreeBlock = null_mut();
+        let mut block = *self.free_list.get();
+        while !block.is_null() {
+            let block_start = block as usize;
+            let block_end = block_start + (*block).size;
+            let start = round_up(block_start, align);
+            if start + size <= block_end {
+                let mut rest = (*block).next;
+                if start + size < block_end {
+                    let tail = (start + size) as *mut FreeBlock;
+                    *tail = FreeBlock {
+                        size: block_end - start - size,
+                        next: rest,
+                    };
+                    rest = tail;
+                }
+                if start > block_start {
+                    // padding before allocated block stays free
+                    (*block).size = start - block_start;
+                    (*block).next = rest;
+                } else if previous.is_null() {
+                    *self.free_list.get() = rest;
+                } else {
+                    (*previous).next = rest;
+                }
+                return start as *mut u8;
+            }
+            previous = block;
+            block = (*block).next;
+        }
+        null_mut()
+    }
+
+    /// Returns block to free list, merging it with adjacent free blocks
+    unsafe fn give_free(&self, address: usize, size: usize) {
+        let mut previous: *mut FreeBlock = null_mut();
+        let mut next = *self.free_list.get();
+        while !next.is_null() && (next as usize) < address {
+            previous = next;
+            next = (*next).next;
+        }
+
+        let block = address as *mut FreeBlock;
+        *block = FreeBlock { size, next };
+        if !next.is_null() && address + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+        if previous.is_null() {
+            *self.free_list.get() = block;
+        } else if previous as usize + (*previous).size == address {
+            (*previous).size += (*block).size;
+            (*previous).next = (*block).next;
+        } else {
+            (*previous).next = block;
+        }
+    }
+
+    /// Grows heap so that block of given size and alignment fits in new memory
+    unsafe fn grow(&self, size: usize, align: usize) -> bool {
+        let increment = round_up(core::cmp::max(size + align, GROW_SIZE), GROW_SIZE);
+        match sbrk(increment as isize) {
+            Ok(old_break) => {
+                // task may have moved break by itself, so it is not always aligned
+                let start = round_up(old_break, BLOCK_ALIGN);
+                let end = (old_break + increment) & !(BLOCK_ALIGN - 1);
+                self.give_free(start, end - start);
+                true
+            }
+            Err(_) => false,
+        }
     }
 }
-impl UserAllocator {
-    #[allow(dead_code)]
-    pub const fn get() -> Self {
-        UserAllocator {}
+
+unsafe impl GlobalAlloc for UserAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let size = block_size(layout);
+        let align = core::cmp::max(layout.align(), BLOCK_ALIGN);
+        let address = self.take_free(size, align);
+        if !address.is_null() || !self.grow(size, align) {
+            return address;
+        }
+        self.take_free(size, align)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.give_free(ptr as usize, block_size(layout));
     }
 }
+
+/// Each task has its own copy, placed in its task local memory
+#[link_section = ".task_local"]
+pub static USER_ALLOCATOR: Mutex<UserAllocator> = Mutex::new(UserAllocator::new());
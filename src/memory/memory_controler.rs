@@ -128,6 +128,11 @@ pub const USER_RW_: AttributeFields = AttributeFields::new(
     AccessPermissions::UserReadWrite,
     false,
 );
+pub const USER_R__: AttributeFields = AttributeFields::new(
+    MemAttributes::CacheableDRAM,
+    AccessPermissions::UserReadOnly,
+    false,
+);
 pub const USER_R_X: AttributeFields = AttributeFields::new(
     MemAttributes::CacheableDRAM,
     AccessPermissions::UserReadOnly,
@@ -1,12 +1,25 @@
+pub mod environment;
+pub mod resource_limits;
+pub mod resource_usage;
+pub mod shared_memory;
+pub mod signals;
 pub mod special_return_vals;
 pub mod task_context;
 pub mod task_memory_manager;
 pub mod task_stack;
 use crate::device_driver;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
 use crate::interupts::ExceptionContext;
-use crate::syscall::asynchronous::handle_async_syscalls::handle_async_syscalls;
+use crate::sync::nulllock::NullLock;
+use crate::syscall::files::poll::PollObject;
+use alloc::sync::Arc;
 use alloc::{vec::Vec, collections::VecDeque};
+use core::ops::Range;
 use core::time::Duration;
+use resource_limits::Resource;
+use resource_usage::ResourceUsage;
+use signals::Signal;
 use task_context::*;
 
 pub const MAX_TASK_COUNT: usize = 2048;
@@ -66,11 +79,208 @@ pub fn finish_current_task(return_value: u32) {
     scheduler.finish_current_task(return_value);
 }
 
+pub fn stop_current_task() {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.stop_current_task();
+}
+
+pub fn send_signal(pid: usize, signal: Signal) -> Result<(), TaskError> {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.send_signal(pid, signal)
+}
+
+/// Copies page shared after fork, that current task tries to write
+pub fn handle_copy_on_write(address: usize) -> bool {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler
+        .get_current_task()
+        .memory_manager
+        .lock()
+        .handle_copy_on_write(address)
+}
+
+pub fn detach_thread(pid: usize) -> Result<(), TaskError> {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.detach_thread(pid)
+}
+
 pub fn get_current_task_pid() -> usize {
     let scheduler = TASK_MANAGER.lock();
     scheduler.get_current_task_pid()
 }
 
+/// Blocking syscall was interrupted to run handler of signal sent to the task
+#[derive(Debug)]
+pub struct Interrupted;
+
+/// Suspends current task until it is woken on the condition. Fails if it was resumed to run
+/// signal handler instead, syscall suspended in should fail then.
+pub fn suspend_current_task(condition: WaitCondition) -> Result<(), Interrupted> {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.suspend_current_task(condition)
+}
+
+/// Makes current kernel task act on behalf of task with given pid and in its address space,
+/// so syscall handlers it calls treat that task as current. None makes it act for itself.
+pub fn act_for_task(pid: Option<usize>) {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.act_for_task(pid);
+}
+
+/// Some task, the async worker, acts for task with given pid right now
+pub fn is_acted_for(pid: usize) -> bool {
+    let scheduler = TASK_MANAGER.lock();
+    scheduler.tasks.iter().any(|task| {
+        task.acting_for
+            .as_ref()
+            .map_or(false, |(acted_for, _)| *acted_for == pid)
+    })
+}
+
+/// Some running task using given address space has buffer registered for async syscalls
+/// overlapping the range, so the range has to stay mapped
+pub fn is_registered_buffer(
+    memory_manager: &task_memory_manager::SharedMemoryManager,
+    range: &Range<usize>,
+) -> bool {
+    let scheduler = TASK_MANAGER.lock();
+    scheduler.tasks.iter().any(|task| {
+        let finished = match task.state {
+            TaskStates::Zombie | TaskStates::Dead => true,
+            _ => false,
+        };
+        !finished
+            && Arc::ptr_eq(&task.memory_manager, memory_manager)
+            && task.registered_buffers.overlaps(range)
+    })
+}
+
+/// Pids of tasks whose async syscalls should be handled
+pub fn tasks_with_async_work() -> Vec<usize> {
+    let scheduler = TASK_MANAGER.lock();
+    scheduler
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| task.has_async_work())
+        .map(|(pid, _)| pid)
+        .collect()
+}
+
+/// Some task submitted async syscalls that were not read yet
+pub fn has_async_submissions() -> bool {
+    let scheduler = TASK_MANAGER.lock();
+    scheduler
+        .tasks
+        .iter()
+        .any(|task| task.has_async_submissions())
+}
+
+/// Some task has async syscalls that were not read or did not complete yet
+pub fn has_async_work() -> bool {
+    let scheduler = TASK_MANAGER.lock();
+    scheduler.tasks.iter().any(|task| task.has_async_work())
+}
+
+pub fn wake_task_if<P>(pid: usize, predicate: P)
+where
+    P: Fn(&WaitCondition) -> bool,
+{
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.wake_task_if(pid, predicate);
+}
+
+pub fn wake_all_if<P>(predicate: P)
+where
+    P: Fn(&WaitCondition) -> bool,
+{
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.wake_all_if(predicate);
+}
+
+/// Resumes tasks waiting in poll on descriptors of given object, so they can recheck their
+/// readiness
+pub fn wake_pollers(object: PollObject) {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.wake_pollers(object);
+}
+
+/// Resumes tasks waiting in poll or for async completions, whose timeout has passed
+pub fn wake_expired_pollers(now: Duration) {
+    wake_all_if(|condition| match condition {
+        WaitCondition::Poll(Some(deadline)) => *deadline <= now,
+        WaitCondition::AsyncCompletions {
+            deadline: Some(deadline),
+            ..
+        } => *deadline <= now,
+        _ => false,
+    });
+}
+
+/// Resource usage of current task and of its reaped children, must be called from EL1
+pub fn get_current_task_usage() -> (ResourceUsage, ResourceUsage) {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.charge_time(false);
+    let task = scheduler.get_current_task();
+    let peak_page_count = task.memory_manager.lock().peak_page_count() as u64;
+    task.usage.max_pages_mapped = core::cmp::max(task.usage.max_pages_mapped, peak_page_count);
+    (task.usage, task.children_usage)
+}
+
+/// Charges time since last accounting as EL0 time of current task, called on entry from EL0
+pub fn account_user_time() {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.charge_time(true);
+}
+
+/// Charges time since last accounting as EL1 time of current task, called on return to EL0
+pub fn account_system_time() {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.charge_time(false);
+}
+
+pub fn account_syscall() {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.get_current_task().usage.syscalls += 1;
+}
+
+pub fn account_pipe_read(bytes: usize) {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.get_current_task().usage.pipe_bytes_read += bytes as u64;
+}
+
+pub fn account_pipe_write(bytes: usize) {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.get_current_task().usage.pipe_bytes_written += bytes as u64;
+}
+
+/// Fails if current task reached its limit of unreaped children
+pub fn check_children_limit() -> Result<(), TaskError> {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.check_children_limit()
+}
+
+/// Children that wait reaps are processes, join reaps threads
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChildKind {
+    Process,
+    Thread,
+}
+
+impl ChildKind {
+    fn matches(self, task: &TaskContext) -> bool {
+        task.is_thread() == (self == ChildKind::Thread)
+    }
+}
+
+pub fn reap_child_task(
+    pid: Option<usize>,
+    kind: ChildKind,
+) -> Result<Option<(usize, u32)>, TaskError> {
+    let mut scheduler = TASK_MANAGER.lock();
+    scheduler.reap_child_task(pid, kind)
+}
+
 pub fn get_child_task_return_val(pid: usize) -> Option<u32> {
     let mut scheduler = TASK_MANAGER.lock();
     let ret = scheduler.get_child_task_return_val(pid);
@@ -83,6 +293,21 @@ pub struct TaskManager {
     current_task: usize,
     started: bool,
     time_quant: Duration,
+    /// Time when CPU time was last charged to current task
+    last_accounting_time: Duration,
+}
+
+/// Resumes task if it is suspended on condition matching the predicate
+fn resume_if<P>(task: &mut TaskContext, predicate: &P)
+where
+    P: Fn(&WaitCondition) -> bool,
+{
+    if let TaskStates::Suspended = task.state {
+        if task.wait_condition.as_ref().map_or(false, predicate) {
+            task.wait_condition = None;
+            task.state = TaskStates::Running;
+        }
+    }
 }
 
 impl TaskManager {
@@ -92,6 +317,7 @@ impl TaskManager {
             current_task: 0,
             started: false,
             time_quant,
+            last_accounting_time: Duration::from_secs(0),
         }
     }
 
@@ -122,17 +348,280 @@ impl TaskManager {
     }
 
     pub fn get_current_task(&mut self) -> &mut TaskContext {
-        &mut self.tasks[self.current_task]
+        let caller = self.caller_pid();
+        &mut self.tasks[caller]
+    }
+
+    /// Pid of task that syscalls are handled for, differs from current task when kernel task
+    /// acts on behalf of other one
+    fn caller_pid(&self) -> usize {
+        self.tasks[self.current_task]
+            .acting_for
+            .as_ref()
+            .map_or(self.current_task, |(pid, _)| *pid)
+    }
+
+    pub fn act_for_task(&mut self, pid: Option<usize>) {
+        let current_task = self.current_task;
+        if let Some((_, own_memory_manager)) = self.tasks[current_task].acting_for.take() {
+            self.tasks[current_task].memory_manager = own_memory_manager;
+        }
+        if let Some(pid) = pid {
+            let memory_manager = self.tasks[pid].memory_manager.clone();
+            let task = &mut self.tasks[current_task];
+            let own_memory_manager = core::mem::replace(&mut task.memory_manager, memory_manager);
+            task.acting_for = Some((pid, own_memory_manager));
+        }
+        // memory manager is also switched to on each return to this task
+        unsafe { self.tasks[current_task].memory_manager.lock().activate() };
     }
 
     pub fn get_child_task_return_val(&mut self, pid: usize) -> Option<u32> {
-        let val = self.tasks[self.current_task]
+        let caller = self.caller_pid();
+        let val = self.tasks[caller]
             .children_return_vals
             .remove(&pid);
         self.tasks[pid].was_returned_value_read = true; 
+        if val.is_some() {
+            self.account_reaped_child(pid);
+        }
         val
     }
 
+    /// Takes return value of finished child of current task.
+    /// Returns Ok(None) if matching child exists but has not finished yet.
+    pub fn reap_child_task(
+        &mut self,
+        pid: Option<usize>,
+        kind: ChildKind,
+    ) -> Result<Option<(usize, u32)>, TaskError> {
+        let current_task = self.caller_pid();
+        let tasks = &self.tasks;
+        let child_pid = match pid {
+            Some(pid) => Some(pid),
+            None => tasks[current_task]
+                .children_return_vals
+                .keys()
+                .copied()
+                .find(|child_pid| kind.matches(&tasks[*child_pid])),
+        }
+        .filter(|child_pid| {
+            tasks
+                .get(*child_pid)
+                .map_or(false, |child| kind.matches(child))
+        });
+        if let Some(child_pid) = child_pid {
+            let children_return_vals = &mut self.tasks[current_task].children_return_vals;
+            if let Some(return_value) = children_return_vals.remove(&child_pid) {
+                self.account_reaped_child(child_pid);
+                let child = &mut self.tasks[child_pid];
+                child.was_returned_value_read = true;
+                child.update_zombie();
+                return Ok(Some((child_pid, return_value)));
+            }
+        }
+
+        let has_unfinished_child = self.tasks.iter().enumerate().any(|(child_pid, task)| {
+            if task.ppid != Some(current_task)
+                || task.detached
+                || !kind.matches(task)
+                || pid.map_or(false, |pid| pid != child_pid)
+            {
+                return false;
+            }
+            match task.state {
+                TaskStates::Zombie | TaskStates::Dead => false,
+                _ => true,
+            }
+        });
+        if has_unfinished_child {
+            Ok(None)
+        } else {
+            Err(TaskError::InvalidTaskReference)
+        }
+    }
+
+    /// Adds resource usage of finished child to current task
+    fn account_reaped_child(&mut self, child_pid: usize) {
+        let child = &self.tasks[child_pid];
+        let (usage, children_usage) = (child.usage, child.children_usage);
+        let caller = self.caller_pid();
+        let parent = &mut self.tasks[caller];
+        parent.children_usage.accumulate(&usage);
+        parent.children_usage.accumulate(&children_usage);
+    }
+
+    pub fn check_children_limit(&mut self) -> Result<(), TaskError> {
+        let current_task = self.caller_pid();
+        let children = self
+            .tasks
+            .iter()
+            .filter(|task| task.ppid == Some(current_task))
+            .filter(|task| {
+                if let TaskStates::Dead = task.state {
+                    false
+                } else {
+                    true
+                }
+            })
+            .count();
+        if children as u64 >= self.tasks[current_task].limits.soft(Resource::Children) {
+            Err(TaskError::TaskLimitReached)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Adds time since last accounting to EL0 or EL1 time of current task
+    fn charge_time(&mut self, user: bool) {
+        if !self.started {
+            return;
+        }
+        let now = ArmTimer.get_time();
+        let elapsed = now - self.last_accounting_time;
+        self.last_accounting_time = now;
+        let task = &mut self.tasks[self.current_task];
+        let seconds_before = task.usage.cpu_time().as_secs();
+        if user {
+            task.usage.user_time += elapsed;
+        } else {
+            task.usage.system_time += elapsed;
+        }
+        let seconds = task.usage.cpu_time().as_secs();
+        // signals are raised once, when limit is crossed
+        let limit = task.limits.get(Resource::CpuSeconds);
+        if seconds_before < limit.hard && seconds >= limit.hard {
+            task.signals.raise(Signal::Kill);
+        } else if seconds_before < limit.soft && seconds >= limit.soft {
+            task.signals.raise(Signal::CpuTimeLimit);
+        }
+    }
+
+    /// Makes thread created by current task release its resources on exit instead of
+    /// waiting to be joined
+    pub fn detach_thread(&mut self, pid: usize) -> Result<(), TaskError> {
+        let current_task = self.current_task;
+        let thread = self.get_task(pid)?;
+        if thread.ppid != Some(current_task) || thread.thread_stack.is_none() || thread.detached {
+            return Err(TaskError::InvalidTaskReference);
+        }
+        thread.detached = true;
+        if self.tasks[current_task]
+            .children_return_vals
+            .remove(&pid)
+            .is_some()
+        {
+            let thread = &mut self.tasks[pid];
+            thread.was_returned_value_read = true;
+            thread.update_zombie();
+        }
+        Ok(())
+    }
+
+    pub fn suspend_current_task(&mut self, condition: WaitCondition) -> Result<(), Interrupted> {
+        let task = &mut self.tasks[self.current_task];
+        task.state = TaskStates::Suspended;
+        task.wait_condition = Some(condition);
+        self.switch_task();
+        if self.tasks[self.current_task].signals.interrupts_syscall() {
+            Err(Interrupted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resumes task if it is suspended on condition matching the predicate. Kernel task acting
+    /// for it blocks in its place, so it is resumed too.
+    pub fn wake_task_if<P>(&mut self, pid: usize, predicate: P)
+    where
+        P: Fn(&WaitCondition) -> bool,
+    {
+        for (task_pid, task) in self.tasks.iter_mut().enumerate() {
+            let acts_for_pid = task
+                .acting_for
+                .as_ref()
+                .map_or(false, |(acted_for, _)| *acted_for == pid);
+            if task_pid != pid && !acts_for_pid {
+                continue;
+            }
+            resume_if(task, &predicate);
+        }
+    }
+
+    /// Resumes all suspended tasks waiting on condition matching the predicate
+    pub fn wake_all_if<P>(&mut self, predicate: P)
+    where
+        P: Fn(&WaitCondition) -> bool,
+    {
+        for task in self.tasks.iter_mut() {
+            resume_if(task, &predicate);
+        }
+    }
+
+    fn wake_pollers(&mut self, object: PollObject) {
+        for pid in 0..self.tasks.len() {
+            if self.tasks[pid].polled.contains(&object) {
+                self.wake_task_if(pid, |condition| {
+                    if let WaitCondition::Poll(_) = condition {
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+        }
+    }
+
+    pub fn stop_current_task(&mut self) {
+        self.tasks[self.current_task].state = TaskStates::Stopped;
+        self.switch_task();
+    }
+
+    /// Marks signal as pending for the task. Stop, continue and termination of tasks that are
+    /// not running are handled immediately, other signals are delivered on task's return to EL0.
+    pub fn send_signal(&mut self, pid: usize, signal: Signal) -> Result<(), TaskError> {
+        let current_task = self.current_task;
+        let task = self.get_task(pid)?;
+        // kernel tasks, like the async worker, cannot be stopped or killed
+        if task.is_kernel() {
+            return Err(TaskError::InvalidTaskReference);
+        }
+        if let TaskStates::Zombie | TaskStates::Dead | TaskStates::NotStarted = task.state {
+            return Err(TaskError::InvalidTaskReference);
+        }
+        task.signals.raise(signal);
+        if signal == Signal::Continue {
+            if let TaskStates::Stopped = task.state {
+                // blocking syscalls recheck their condition after wake up
+                task.wait_condition = None;
+                task.state = TaskStates::Running;
+            }
+        }
+        if pid == current_task {
+            return Ok(());
+        }
+        if signal == Signal::Stop {
+            task.signals.discard(Signal::Stop);
+            task.state = TaskStates::Stopped;
+            return Ok(());
+        }
+        if let TaskStates::Suspended | TaskStates::Stopped = task.state {
+            if let Some(signal) = task.signals.terminating_signal() {
+                self.terminate_task(special_return_vals::KILLED_BY_SIGNAL | signal as u32, pid);
+                return Ok(());
+            }
+        }
+        // handler runs when blocking syscall of the task fails with Interrupted
+        let task = &mut self.tasks[pid];
+        if let TaskStates::Suspended = task.state {
+            if task.signals.interrupts_syscall() {
+                task.wait_condition = None;
+                task.state = TaskStates::Running;
+            }
+        }
+        Ok(())
+    }
+
     fn get_two_tasks(
         &mut self,
         first_task_pid: usize,
@@ -174,6 +663,9 @@ impl TaskManager {
         if !self.started {
             return;
         }
+        // tasks are switched in EL1
+        self.charge_time(false);
+
         let previous_task_pid = self.current_task;
         let mut next_task_pid = self.current_task + 1;
 
@@ -181,17 +673,21 @@ impl TaskManager {
             if next_task_pid >= self.tasks.len() {
                 next_task_pid = 0;
             }
-            match self.tasks[next_task_pid].state {
-                TaskStates::Zombie => handle_async_syscalls(),
-                TaskStates::Running => break,
-                _ => (),
-            };
+            if let TaskStates::Running = self.tasks[next_task_pid].state {
+                break;
+            }
             next_task_pid += 1;
         }
 
         if self.current_task == next_task_pid {
             return;
         }
+        let previous_task = &mut self.tasks[previous_task_pid];
+        if let TaskStates::Running = previous_task.state {
+            previous_task.usage.involuntary_switches += 1;
+        } else {
+            previous_task.usage.voluntary_switches += 1;
+        }
 
         self.current_task = next_task_pid;
 
@@ -205,14 +701,23 @@ impl TaskManager {
             cpu_switch_to(
                 current_task as *const _ as u64,
                 next_task as *const _ as u64,
-                current_task.memory_manager.additional_table_hack.as_mut() as *mut _ as u64,
-                next_task.memory_manager.additional_table_hack.as_mut() as *mut _ as u64,
+                current_task
+                    .memory_manager
+                    .lock()
+                    .additional_table_hack
+                    .as_mut() as *mut _ as u64,
+                next_task
+                    .memory_manager
+                    .lock()
+                    .additional_table_hack
+                    .as_mut() as *mut _ as u64,
             );
         }
     }
 
     pub fn start(&mut self) {
         self.started = true;
+        self.last_accounting_time = ArmTimer.get_time();
         let task = self
             .tasks
             .get_mut(0)
@@ -221,13 +726,58 @@ impl TaskManager {
         unsafe {
             cpu_switch_to_first(
                 &task.gpr as *const _ as u64,
-                task.memory_manager.additional_table_hack.as_mut() as *mut _ as u64,
+                task.memory_manager.lock().additional_table_hack.as_mut() as *mut _ as u64,
             );
         }
     }
 
     pub fn finish_task(&mut self, return_value: u32, task_pid: usize) {
-        self.tasks[task_pid].state = TaskStates::Zombie;
+        self.terminate_task(return_value, task_pid);
+        self.switch_task()
+    }
+
+    /// Marks task as finished and passes return value to its parent without switching tasks
+    fn terminate_task(&mut self, return_value: u32, task_pid: usize) {
+        let task = &mut self.tasks[task_pid];
+        task.state = TaskStates::Zombie;
+        if let Some(stack) = &task.thread_stack {
+            let name = alloc::format!("Thread Stack {:#x}", stack.end);
+            task.memory_manager.lock().unmap_memory(&name).ok();
+        }
+        if let Some(writer_pid) = task.pipe_from {
+            self.close_pipe_queue_reader(writer_pid);
+        }
+        let task = &mut self.tasks[task_pid];
+        let peak_page_count = task.memory_manager.lock().peak_page_count() as u64;
+        task.usage.max_pages_mapped = core::cmp::max(task.usage.max_pages_mapped, peak_page_count);
+        // senders waiting for replies from dropped ports are woken after ports are released
+        crate::syscall::ports::collect_failed_replies();
+        // descriptors and shared memory are released by the last thread using them
+        if Arc::strong_count(&task.file_descriptor_table) == 1 {
+            for mut file in task.file_descriptor_table.lock().close_all() {
+                crate::vfs::close(&mut file).ok();
+            }
+        }
+        if Arc::strong_count(&task.memory_manager) == 1 {
+            task.memory_manager.lock().unmap_all_shared();
+        }
+        let mut failed_replies = crate::syscall::ports::take_failed_replies();
+        failed_replies.extend(crate::syscall::ports::fail_replies_of(task_pid));
+        crate::syscall::futex::forget_waiter(task_pid);
+        if task.detached {
+            task.was_returned_value_read = true;
+            task.update_zombie();
+        }
+        for (id, sender) in failed_replies {
+            self.wake_task_if(sender, |condition| {
+                crate::syscall::ports::waits_for_reply(condition, id)
+            });
+        }
+        // pipe queue of finished task, or one it was reading, may be closed now
+        self.wake_pollers(PollObject::PipeQueue(task_pid));
+        if let Some(writer_pid) = self.tasks[task_pid].pipe_from {
+            self.wake_pollers(PollObject::PipeQueue(writer_pid));
+        }
         let mut stack : VecDeque<(u32,usize)> = VecDeque::new();
         stack.push_back((return_value,task_pid));
         while !stack.is_empty() {
@@ -240,13 +790,46 @@ impl TaskManager {
                     }
                 }
             }
+            if self.tasks[t_pid].detached {
+                continue;
+            }
             if let Some(ppid) = self.tasks[t_pid].ppid {
                 self.tasks[ppid]
                     .children_return_vals
                     .insert(t_pid, ret_val);
+                self.wake_task_if(ppid, |condition| match condition {
+                    WaitCondition::Child(None) => true,
+                    WaitCondition::Child(Some(pid)) => *pid == t_pid,
+                    _ => false,
+                });
             };
         }
-        self.switch_task()
+    }
+
+    /// Lets writer know that its pipe queue will not be drained, when no running task reads it
+    fn close_pipe_queue_reader(&mut self, writer_pid: usize) {
+        let has_reader = self.tasks.iter().any(|task| {
+            if task.pipe_from != Some(writer_pid) {
+                return false;
+            }
+            match task.state {
+                TaskStates::Zombie | TaskStates::Dead => false,
+                _ => true,
+            }
+        });
+        if has_reader {
+            return;
+        }
+        if let Some(writer) = self.tasks.get_mut(writer_pid) {
+            writer.pipe_reader_closed = true;
+        }
+        self.wake_task_if(writer_pid, |condition| {
+            if let WaitCondition::PipeQueue = condition {
+                true
+            } else {
+                false
+            }
+        });
     }
 
     pub fn finish_current_task(&mut self, return_value: u32) {
@@ -254,7 +837,7 @@ impl TaskManager {
     }
 
     pub fn get_current_task_pid(&self) -> usize {
-        self.current_task
+        self.caller_pid()
     }
 }
 extern "C" {
@@ -283,27 +866,52 @@ pub fn handle_new_task_syscall(e: &mut ExceptionContext) {
     let function = unsafe {
         core::mem::transmute::<usize, extern "C" fn(usize, *const &[u8]) -> u32>(function_address)
     };
-    let mut task = TaskContext::new(function, args, false).expect("Failed to create new task");
+    let stdin_pipe_from = if stdin_to_pipe != !0usize {
+        Some(stdin_to_pipe)
+    } else {
+        None
+    };
+    e.gpr[0] = match spawn_task(function, args, stdout_to_pipe, stdin_pipe_from) {
+        Ok(pid) => pid,
+        Err(TaskError::TaskLimitReached) => !0u64,
+        Err(error) => {
+            crate::println!("Error when creating new task: {:?}", error);
+            !0u64
+        }
+    };
+}
 
+/// Starts function as child of current task, with copy of its descriptors. Stdout of child
+/// can be redirected to its pipe queue and stdin to pipe queue of given task.
+pub fn spawn_task(
+    function: extern "C" fn(usize, *const &[u8]) -> u32,
+    args: &[&[u8]],
+    stdout_to_pipe: bool,
+    stdin_pipe_from: Option<usize>,
+) -> Result<u64, TaskError> {
+    use crate::syscall::files::file_descriptor_map::FileDescription;
     use crate::syscall::files::*;
+    check_children_limit()?;
+    let parent = unsafe { &*get_current_task_context() };
+    let mut task = TaskContext::new(function, args, &parent.environment, &parent.limits, false)?;
+    task.current_directory = parent.current_directory.clone();
+
+    // new program gets copy of parent's descriptors, so redirections made by parent apply to it
+    let mut file_descriptor_table = parent.file_descriptor_table.lock().inherit();
     if stdout_to_pipe {
-        task.mapped_fds.insert(STDOUT, PIPEOUT);
+        file_descriptor_table.set_description(STDOUT, FileDescription::PipeOut);
     }
 
-    if stdin_to_pipe != !0usize {
-        task.mapped_fds.insert(STDIN, PIPEIN);
-        task.pipe_from = Some(stdin_to_pipe);
+    if let Some(writer_pid) = stdin_pipe_from {
+        file_descriptor_table.set_description(STDIN, FileDescription::PipeIn);
+        task.pipe_from = Some(writer_pid);
     }
+    task.file_descriptor_table = Arc::new(NullLock::new(file_descriptor_table));
 
     task.ppid = Some(get_current_task_pid());
+    task.signals = parent.signals.inherit();
 
-    e.gpr[0] = match add_task(task) {
-        Ok(pid) => pid,
-        Err(error) => {
-            crate::println!("Error when creating new task: {:?}", error);
-            !0u64
-        }
-    };
+    add_task(task)
 }
 
 #[no_mangle]
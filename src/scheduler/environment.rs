@@ -0,0 +1,46 @@
+use crate::syscall::environment::EnvironmentError;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Variables of task, inherited by its children and placed next to argv on stack of new program
+#[derive(Clone, Default)]
+pub struct Environment {
+    variables: BTreeMap<String, String>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            variables: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(|value| value.as_str())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), EnvironmentError> {
+        if name.is_empty() || name.contains('=') {
+            return Err(EnvironmentError::InvalidName);
+        }
+        self.variables
+            .insert(String::from(name), String::from(value));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), EnvironmentError> {
+        self.variables
+            .remove(name)
+            .map(|_| ())
+            .ok_or(EnvironmentError::NotFound)
+    }
+
+    /// Variables in `NAME=value` form, as they are placed on stack
+    pub fn entries(&self) -> Vec<Vec<u8>> {
+        self.variables
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value).into_bytes())
+            .collect()
+    }
+}
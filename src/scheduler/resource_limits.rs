@@ -0,0 +1,93 @@
+use super::task_context::TASK_STACK_SIZE;
+use super::task_memory_manager::PAGE_SIZE;
+use super::MAX_TASK_COUNT;
+use crate::syscall::rlimit::RlimitError;
+
+/// Limit value meaning that resource is not limited
+pub const RLIMIT_INFINITY: u64 = u64::MAX;
+
+/// Largest EL0 stack that can be requested with StackSize limit
+const MAX_STACK_SIZE: u64 = 0x10_0000;
+
+const RESOURCE_COUNT: usize = 5;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy)]
+pub enum Resource {
+    /// Children and threads of task that were not reaped yet
+    Children,
+    /// Opened file descriptors, including standard ones
+    OpenFiles,
+    /// Bytes of heap and anonymous mappings of task
+    HeapBytes,
+    /// Size of EL0 stack of programs and threads created by task
+    StackSize,
+    /// CPU time of task in seconds. Soft limit raises CpuTimeLimit signal, hard one kills task.
+    CpuSeconds,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceLimit {
+    /// Value that is enforced
+    pub soft: u64,
+    /// Ceiling for soft limit, it can only be lowered
+    pub hard: u64,
+}
+
+impl ResourceLimit {
+    pub const fn new(soft: u64, hard: u64) -> Self {
+        ResourceLimit { soft, hard }
+    }
+}
+
+/// Limits of task, inherited by tasks it creates
+#[derive(Clone)]
+pub struct ResourceLimits {
+    limits: [ResourceLimit; RESOURCE_COUNT],
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResourceLimits {
+    pub fn new() -> Self {
+        ResourceLimits {
+            limits: [
+                ResourceLimit::new(64, MAX_TASK_COUNT as u64),
+                ResourceLimit::new(64, 1024),
+                ResourceLimit::new(RLIMIT_INFINITY, RLIMIT_INFINITY),
+                ResourceLimit::new(TASK_STACK_SIZE as u64, MAX_STACK_SIZE),
+                ResourceLimit::new(RLIMIT_INFINITY, RLIMIT_INFINITY),
+            ],
+        }
+    }
+
+    pub fn get(&self, resource: Resource) -> ResourceLimit {
+        self.limits[resource as usize]
+    }
+
+    pub fn soft(&self, resource: Resource) -> u64 {
+        self.get(resource).soft
+    }
+
+    pub fn set(&mut self, resource: Resource, limit: ResourceLimit) -> Result<(), RlimitError> {
+        if limit.soft > limit.hard {
+            return Err(RlimitError::SoftAboveHard);
+        }
+        if limit.hard > self.limits[resource as usize].hard {
+            return Err(RlimitError::HardLimitRaised);
+        }
+        self.limits[resource as usize] = limit;
+        Ok(())
+    }
+
+    /// Size of new EL0 stacks, soft StackSize limit rounded up to whole pages
+    pub fn stack_size(&self) -> usize {
+        let size = self.soft(Resource::StackSize) as usize;
+        (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+    }
+}
@@ -0,0 +1,38 @@
+use core::time::Duration;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+/// Resources used by task, for children it is sum over all reaped descendants
+pub struct ResourceUsage {
+    /// Time spent in EL0
+    pub user_time: Duration,
+    /// Time spent in EL1 handling exceptions of task
+    pub system_time: Duration,
+    /// Switches away from task that was suspended, stopped or finished
+    pub voluntary_switches: u64,
+    /// Switches away from task that could still run, because its time quant ended or it yielded
+    pub involuntary_switches: u64,
+    pub syscalls: u64,
+    /// Largest number of private pages mapped in address space of task
+    pub max_pages_mapped: u64,
+    pub pipe_bytes_read: u64,
+    pub pipe_bytes_written: u64,
+}
+
+impl ResourceUsage {
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+
+    /// Adds usage of reaped child, pages are not summed as children may share them
+    pub fn accumulate(&mut self, other: &ResourceUsage) {
+        self.user_time += other.user_time;
+        self.system_time += other.system_time;
+        self.voluntary_switches += other.voluntary_switches;
+        self.involuntary_switches += other.involuntary_switches;
+        self.syscalls += other.syscalls;
+        self.max_pages_mapped = core::cmp::max(self.max_pages_mapped, other.max_pages_mapped);
+        self.pipe_bytes_read += other.pipe_bytes_read;
+        self.pipe_bytes_written += other.pipe_bytes_written;
+    }
+}
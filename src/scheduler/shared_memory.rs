@@ -0,0 +1,68 @@
+use super::task_memory_manager::{Frame, PAGE_SIZE};
+use crate::device_driver;
+use crate::syscall::shared_memory::SharedMemoryError;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// Largest shared memory object that can be created
+const MAX_SHARED_MEMORY_SIZE: usize = 0x100_0000;
+
+/// Pages mapped in address spaces of many tasks, freed when the last of them unmaps it
+pub struct SharedMemory {
+    frames: Vec<Arc<Frame>>,
+}
+
+impl SharedMemory {
+    fn new(size: usize) -> Option<Self> {
+        let frames = (0..size / PAGE_SIZE)
+            .map(|_| Frame::new().map(Arc::new))
+            .collect::<Option<Vec<_>>>()?;
+        Some(SharedMemory { frames })
+    }
+
+    pub fn size(&self) -> usize {
+        self.frames.len() * PAGE_SIZE
+    }
+
+    pub(super) fn frames(&self) -> &[Arc<Frame>] {
+        &self.frames
+    }
+}
+
+// objects are owned by tasks that attached them, name is forgotten when all of them detach
+device_driver!(
+    unsynchronized SHARED_MEMORY_OBJECTS: BTreeMap<String, Weak<SharedMemory>> = BTreeMap::new()
+);
+
+/// Creates named object of given size, rounded up to whole pages
+pub fn create(name: &str, size: usize) -> Result<Arc<SharedMemory>, SharedMemoryError> {
+    if name.is_empty() {
+        return Err(SharedMemoryError::InvalidName);
+    }
+    if size == 0 || size > MAX_SHARED_MEMORY_SIZE {
+        return Err(SharedMemoryError::InvalidSize);
+    }
+    let mut objects = SHARED_MEMORY_OBJECTS.lock();
+    if objects
+        .get(name)
+        .map_or(false, |object| object.strong_count() > 0)
+    {
+        return Err(SharedMemoryError::AlreadyExists);
+    }
+    let size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+    let memory = Arc::new(SharedMemory::new(size).ok_or(SharedMemoryError::OutOfMemory)?);
+    objects.insert(String::from(name), Arc::downgrade(&memory));
+    Ok(memory)
+}
+
+/// Object with given name, if any task still has it attached
+pub fn find(name: &str) -> Result<Arc<SharedMemory>, SharedMemoryError> {
+    let mut objects = SHARED_MEMORY_OBJECTS.lock();
+    let memory = objects.get(name).and_then(Weak::upgrade);
+    if memory.is_none() {
+        objects.remove(name);
+    }
+    memory.ok_or(SharedMemoryError::NotFound)
+}
@@ -0,0 +1,338 @@
+use super::special_return_vals::KILLED_BY_SIGNAL;
+use super::task_context::TaskContext;
+use crate::interupts::ExceptionContext;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use num_traits::FromPrimitive;
+
+pub const SIGNAL_COUNT: usize = 32;
+
+/// Handler value meaning default action of signal
+pub const SIGNAL_DEFAULT: usize = 0;
+/// Handler value meaning that signal is ignored
+pub const SIGNAL_IGNORE: usize = 1;
+
+/// NZCV bits of SPSR, the only ones task can set when returning from signal handler
+const SPSR_CONDITION_FLAGS: u64 = 0xF000_0000;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Hangup = 1,
+    Interrupt = 2,
+    Quit = 3,
+    IllegalInstruction = 4,
+    Abort = 6,
+    BusError = 7,
+    Kill = 9,
+    User1 = 10,
+    SegmentationFault = 11,
+    User2 = 12,
+    BrokenPipe = 13,
+    Alarm = 14,
+    Terminate = 15,
+    Child = 17,
+    Continue = 18,
+    Stop = 19,
+    /// Task exceeded soft CpuSeconds limit
+    CpuTimeLimit = 24,
+}
+
+/// Action taken when signal without registered handler is delivered
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+impl Signal {
+    pub fn default_action(self) -> DefaultAction {
+        match self {
+            Signal::Child => DefaultAction::Ignore,
+            Signal::Continue => DefaultAction::Continue,
+            Signal::Stop => DefaultAction::Stop,
+            _ => DefaultAction::Terminate,
+        }
+    }
+    /// Kill and Stop can't be handled, ignored or masked
+    pub fn can_be_caught(self) -> bool {
+        match self {
+            Signal::Kill | Signal::Stop => false,
+            _ => true,
+        }
+    }
+    pub fn bit(self) -> u32 {
+        1 << (self as usize)
+    }
+}
+
+const UNMASKABLE_SIGNALS: u32 = 1 << Signal::Kill as usize | 1 << Signal::Stop as usize;
+
+#[derive(Clone, Copy)]
+pub enum SignalHandler {
+    Default,
+    Ignore,
+    /// User function called with signal number, returning through trampoline to SignalReturn
+    Function { address: usize, trampoline: usize },
+}
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy)]
+pub enum MaskOperation {
+    Block,
+    Unblock,
+    Set,
+}
+
+/// Context of interrupted task saved on its stack during signal handler execution
+#[repr(C)]
+struct SignalFrame {
+    context: ExceptionContext,
+    previous_mask: u64,
+    signal: u64,
+}
+
+pub struct SignalState {
+    pending: u32,
+    mask: u32,
+    handlers: [SignalHandler; SIGNAL_COUNT],
+    frames: Vec<usize>,
+}
+
+impl Default for SignalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        SignalState {
+            pending: 0,
+            mask: 0,
+            handlers: [SignalHandler::Default; SIGNAL_COUNT],
+            frames: Vec::new(),
+        }
+    }
+
+    /// Copy of signal dispositions for newly created task, without pending signals
+    pub fn inherit(&self) -> Self {
+        SignalState {
+            pending: 0,
+            mask: self.mask,
+            handlers: self.handlers,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Copy for forked task, which continues in the same signal handlers as its parent
+    pub fn fork(&self) -> Self {
+        SignalState {
+            frames: self.frames.clone(),
+            ..self.inherit()
+        }
+    }
+
+    /// Handlers point to code that is no longer mapped after exec, ignored signals stay ignored
+    pub fn reset_handlers(&mut self) {
+        for handler in self.handlers.iter_mut() {
+            if let SignalHandler::Function { .. } = handler {
+                *handler = SignalHandler::Default;
+            }
+        }
+        self.frames.clear();
+    }
+
+    pub fn raise(&mut self, signal: Signal) {
+        // stop and continue cancel each other
+        match signal {
+            Signal::Stop => self.discard(Signal::Continue),
+            Signal::Continue => self.discard(Signal::Stop),
+            _ => (),
+        }
+        self.pending |= signal.bit();
+    }
+
+    pub fn discard(&mut self, signal: Signal) {
+        self.pending &= !signal.bit();
+    }
+
+    pub fn is_blocked(&self, signal: Signal) -> bool {
+        self.mask & signal.bit() != 0
+    }
+
+    pub fn handler(&self, signal: Signal) -> SignalHandler {
+        self.handlers[signal as usize]
+    }
+
+    pub fn set_handler(&mut self, signal: Signal, handler: SignalHandler) {
+        self.handlers[signal as usize] = handler;
+    }
+
+    /// Changes signal mask, returns previous one
+    pub fn update_mask(&mut self, operation: MaskOperation, set: u32) -> u32 {
+        let previous = self.mask;
+        self.mask = match operation {
+            MaskOperation::Block => self.mask | set,
+            MaskOperation::Unblock => self.mask & !set,
+            MaskOperation::Set => set,
+        } & !UNMASKABLE_SIGNALS;
+        previous
+    }
+
+    /// Makes sure signal caused by task itself (e.g. fault) is not ignored nor blocked
+    pub fn force(&mut self, signal: Signal) {
+        let handled = match self.handler(signal) {
+            SignalHandler::Function { .. } => !self.is_blocked(signal),
+            _ => false,
+        };
+        if !handled {
+            self.set_handler(signal, SignalHandler::Default);
+            self.mask &= !signal.bit();
+        }
+        self.raise(signal);
+    }
+
+    /// Some signal that would be delivered now has handler function, so syscall task is blocked
+    /// in has to fail to let the handler run
+    pub fn interrupts_syscall(&self) -> bool {
+        self.deliverable_signals().any(|signal| {
+            if let SignalHandler::Function { .. } = self.handler(signal) {
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Returns signal that would be terminating the task if delivered now
+    pub fn terminating_signal(&self) -> Option<Signal> {
+        self.deliverable_signals().find(|signal| {
+            if let SignalHandler::Default = self.handler(*signal) {
+                if let DefaultAction::Terminate = signal.default_action() {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    fn deliverable_signals(&self) -> impl Iterator<Item = Signal> + '_ {
+        let deliverable = self.pending & !self.mask;
+        (1..SIGNAL_COUNT)
+            .filter(move |number| deliverable & (1 << number) != 0)
+            .filter_map(Signal::from_usize)
+    }
+
+    fn take_deliverable(&mut self) -> Option<Signal> {
+        let signal = self.deliverable_signals().next();
+        if let Some(signal) = signal {
+            self.discard(signal);
+        }
+        signal
+    }
+}
+
+/// Delivers signals pending for current task. Must be called right before returning to EL0.
+pub fn deliver_pending_signals(context: &mut ExceptionContext) {
+    loop {
+        let task: &mut TaskContext = unsafe { &mut *super::get_current_task_context() };
+        if task.is_kernel() {
+            return;
+        }
+        let signal = match task.signals.take_deliverable() {
+            Some(signal) => signal,
+            None => return,
+        };
+        match task.signals.handler(signal) {
+            SignalHandler::Ignore => (),
+            SignalHandler::Default => match signal.default_action() {
+                DefaultAction::Ignore | DefaultAction::Continue => (),
+                DefaultAction::Stop => super::stop_current_task(),
+                DefaultAction::Terminate => {
+                    super::finish_current_task(KILLED_BY_SIGNAL | signal as u32)
+                }
+            },
+            SignalHandler::Function {
+                address,
+                trampoline,
+            } => {
+                if setup_signal_frame(task, context, signal, address, trampoline) {
+                    return;
+                }
+                // stack can't hold the frame, handler of the fault would fail the same way
+                task.signals
+                    .set_handler(Signal::SegmentationFault, SignalHandler::Default);
+                task.signals.force(Signal::SegmentationFault);
+            }
+        }
+    }
+}
+
+/// Saves context on user stack and makes task return to handler, false if stack of task can't
+/// hold the frame
+fn setup_signal_frame(
+    task: &mut TaskContext,
+    context: &mut ExceptionContext,
+    signal: Signal,
+    address: usize,
+    trampoline: usize,
+) -> bool {
+    let frame_address = match (context.sp_el0 as usize).checked_sub(size_of::<SignalFrame>()) {
+        Some(address) => address & !0b1111,
+        None => return false,
+    };
+    if !is_user_writable(task, frame_address) {
+        return false;
+    }
+    let frame = frame_address as *mut SignalFrame;
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            context as *const ExceptionContext,
+            &mut (*frame).context,
+            1,
+        );
+        (*frame).previous_mask = task.signals.mask as u64;
+        (*frame).signal = signal as u64;
+    }
+    task.signals.frames.push(frame_address);
+    task.signals.mask |= signal.bit();
+
+    context.sp_el0 = frame_address as u64;
+    context.elr_el1 = address as u64;
+    context.lr = trampoline as u64;
+    context.gpr[0] = signal as u64;
+    true
+}
+
+/// Signal frame at given address lies in memory that task can write
+fn is_user_writable(task: &TaskContext, frame_address: usize) -> bool {
+    let frame = frame_address..frame_address + size_of::<SignalFrame>();
+    task.memory_manager.lock().is_writable(&frame)
+}
+
+/// Restores context saved before last signal handler was called
+pub fn return_from_signal(context: &mut ExceptionContext) {
+    let task: &mut TaskContext = unsafe { &mut *super::get_current_task_context() };
+    let frame_address = match task.signals.frames.pop() {
+        Some(address) => address,
+        None => {
+            task.signals.force(Signal::SegmentationFault);
+            return;
+        }
+    };
+    if !is_user_writable(task, frame_address) {
+        task.signals.force(Signal::SegmentationFault);
+        return;
+    }
+    let frame = frame_address as *const SignalFrame;
+    // frame can be changed by task, so only condition flags are restored from it, exception
+    // level and interrupt masks stay the ones kernel returns with
+    let spsr = context.spsr_el1;
+    unsafe {
+        core::ptr::copy_nonoverlapping(&(*frame).context, context as *mut ExceptionContext, 1);
+        task.signals.mask = (*frame).previous_mask as u32 & !UNMASKABLE_SIGNALS;
+    }
+    context.spsr_el1 = (spsr & !SPSR_CONDITION_FLAGS) | (context.spsr_el1 & SPSR_CONDITION_FLAGS);
+}
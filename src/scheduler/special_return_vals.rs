@@ -1,3 +1,5 @@
 const BIG_NUM: u32 = 0x8000;
 pub const PARENT_PROCESS_ENDED: u32 = BIG_NUM | 1;
 pub const WRONG_RETURN_VALUE_PASSED: u32 = BIG_NUM | 2;
+/// Task was terminated by signal, its number is stored in lowest bits
+pub const KILLED_BY_SIGNAL: u32 = BIG_NUM | 0x100;
@@ -1,17 +1,32 @@
-use super::task_memory_manager;
+use super::environment::Environment;
+use super::resource_limits::{Resource, ResourceLimits};
+use super::resource_usage::ResourceUsage;
+use super::signals::SignalState;
+use super::task_memory_manager::{self, FutexKey};
 use super::task_stack;
 use crate::alloc::collections::BTreeMap;
+use crate::interupts::ExceptionContext;
+use crate::sync::nulllock::NullLock;
+use crate::syscall::asynchronous::async_queue::AsyncQueue;
 use crate::syscall::asynchronous::async_returned_values::AsyncReturnedValues;
+use crate::syscall::asynchronous::registered_buffers::RegisteredBuffers;
 use crate::syscall::files::file_descriptor_map::*;
+use crate::syscall::files::poll::PollObject;
 use crate::utils::circullar_buffer::*;
-use alloc::collections::VecDeque;
+use crate::utils::ring_buffer::RingBuffer;
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::ops::Range;
 use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
 /// Stack size of task in bytes
 pub const TASK_STACK_SIZE: usize = 0x8000;
 extern "C" {
     /// Signal end of scheduling, zero x0 - x18 and jump to x19
     fn new_task_func();
+    /// Signal end of scheduling and restore exception context pointed by x19
+    fn ret_from_fork();
 
 }
 
@@ -22,6 +37,10 @@ pub enum TaskError {
     TaskLimitReached,
     /// Stack could not be allocated
     StackAllocationFail,
+    /// Address space could not be created
+    MemoryMappingFail,
+    /// Arguments does not fit on task stack
+    ArgumentsTooLong,
     /// Referenced to task out of bounds of array
     InvalidTaskReference,
     /// Error in changing task
@@ -42,6 +61,34 @@ pub enum TaskStates {
     Zombie = 3,
     /// Task is dead and waiting to clean after it
     Dead = 4,
+    /// Task was stopped by signal and is skipped by scheduler until continued
+    Stopped = 5,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Events that suspended task can wait for
+pub enum WaitCondition {
+    /// Task waits for specific child (or any child if None) to finish
+    Child(Option<usize>),
+    /// Task waits for change of state of pipe with given id
+    Pipe(usize),
+    /// Task waits for readers to drain its pipe queue
+    PipeQueue,
+    /// Task waits for readiness of polled descriptors, until deadline if one is given
+    Poll(Option<Duration>),
+    /// Task waits for message or free space in port with given id
+    Port(usize),
+    /// Task waits for reply to message with given id
+    PortReply(u64),
+    /// Task waits for futex_wake on word with given key
+    Futex(FutexKey),
+    /// Async worker waits for tasks to submit async syscalls
+    AsyncWork,
+    /// Task waits for given count of async completions, until deadline if one is given
+    AsyncCompletions {
+        count: usize,
+        deadline: Option<Duration>,
+    },
 }
 
 #[repr(C)]
@@ -60,6 +107,7 @@ pub struct Gpr {
     pub sp: u64,
     pub lr: u64,
     pub sp_el0: u64,
+    pub tpidr_el0: u64,
 }
 
 impl Default for Gpr {
@@ -72,20 +120,41 @@ impl Default for Gpr {
 pub struct TaskContext {
     pub(super) gpr: Gpr,
     pub(super) state: TaskStates,
-    el0_stack: Option<task_stack::TaskStack>,
+    pub(super) wait_condition: Option<WaitCondition>,
     el1_stack: Option<task_stack::TaskStack>,
     is_kernel: bool,
     pub submission_buffer: CircullarBuffer,
     pub completion_buffer: CircullarBuffer,
-    pub file_descriptor_table: FileDescriptiorMap,
+    pub file_descriptor_table: SharedFileDescriptorMap,
     pub async_returns_map: AsyncReturnedValues,
+    /// Submitted async syscalls that did not complete yet
+    pub async_queue: AsyncQueue,
+    /// Buffers that async syscalls of task can refer to by index
+    pub registered_buffers: RegisteredBuffers,
     pub children_return_vals: BTreeMap<usize, u32>,
     pub was_returned_value_read: bool,
     pub pipe_from: Option<usize>,
-    pub mapped_fds: BTreeMap<usize, usize>,
-    pipe_queue: VecDeque<Vec<u8>>,
-    pub memory_manager: task_memory_manager::TaskMemoryManager,
+    /// Objects of descriptors that task waits on in poll
+    pub polled: Vec<PollObject>,
+    pipe_queue: RingBuffer,
+    /// All tasks reading pipe queue of this task have finished
+    pub(super) pipe_reader_closed: bool,
+    pub memory_manager: task_memory_manager::SharedMemoryManager,
     pub ppid: Option<usize>,
+    pub signals: SignalState,
+    /// EL0 stack of thread, unmapped from shared address space when thread ends
+    pub(super) thread_stack: Option<Range<usize>>,
+    /// Return value of detached thread is not kept for join
+    pub(super) detached: bool,
+    pub environment: Environment,
+    /// Absolute path that relative paths of task are resolved against
+    pub current_directory: String,
+    pub usage: ResourceUsage,
+    /// Usage of reaped children, including their own reaped children
+    pub(super) children_usage: ResourceUsage,
+    pub limits: ResourceLimits,
+    /// Pid of task that kernel task acts on behalf of, with its own memory manager set aside
+    pub(super) acting_for: Option<(usize, task_memory_manager::SharedMemoryManager)>,
 }
 
 // ONLY TEMPORARY SOLUTION
@@ -99,20 +168,34 @@ impl TaskContext {
         TaskContext {
             gpr: Default::default(),
             state: TaskStates::NotStarted,
+            wait_condition: None,
             el1_stack: None,
-            el0_stack: None,
             is_kernel: false,
             submission_buffer: CircullarBuffer::new(),
             completion_buffer: CircullarBuffer::new(),
-            file_descriptor_table: FileDescriptiorMap::new(),
+            file_descriptor_table: Arc::new(NullLock::new(FileDescriptiorMap::new())),
             async_returns_map: AsyncReturnedValues::new(),
+            async_queue: AsyncQueue::new(),
+            registered_buffers: RegisteredBuffers::new(),
             children_return_vals: BTreeMap::<usize, u32>::new(),
             was_returned_value_read: false,
-            mapped_fds: BTreeMap::<usize, usize>::new(),
-            pipe_queue: VecDeque::<Vec<u8>>::new(),
-            memory_manager: Default::default(),
+            pipe_queue: RingBuffer::new(crate::syscall::files::PIPE_CAPACITY),
+            pipe_reader_closed: false,
+            memory_manager: Arc::new(NullLock::new(
+                task_memory_manager::TaskMemoryManager::empty(),
+            )),
             ppid: None,
             pipe_from: None,
+            polled: Vec::new(),
+            signals: SignalState::new(),
+            thread_stack: None,
+            detached: false,
+            environment: Environment::new(),
+            current_directory: String::from("/"),
+            usage: ResourceUsage::default(),
+            children_usage: ResourceUsage::default(),
+            limits: ResourceLimits::new(),
+            acting_for: None,
         }
     }
 
@@ -127,96 +210,101 @@ impl TaskContext {
         }
     }
 
-    pub fn get_item_from_pipe_queue(&mut self) -> Option<Vec<u8>> {
-        let val = self.pipe_queue.pop_front();
+    /// Moves oldest bytes of pipe queue to buffer, returns count of moved bytes
+    pub fn read_from_pipe_queue(&mut self, buffer: &mut [u8]) -> usize {
+        let count = self.pipe_queue.pop_slice(buffer);
         self.update_zombie();
-        val
+        count
     }
 
-    pub fn push_back_item_to_pipe_queue(&mut self, element: Vec<u8>) {
-        self.pipe_queue.push_back(element)
-    }
-
-    pub fn push_front_item_to_pipe_queue(&mut self, element: Vec<u8>) {
-        self.pipe_queue.push_front(element)
+    /// Appends as much of data as fits in pipe queue, returns count of appended bytes
+    pub fn write_to_pipe_queue(&mut self, data: &[u8]) -> usize {
+        self.pipe_queue.push_slice(data)
     }
 
     pub fn is_pipe_queue_empty(&self) -> bool {
         self.pipe_queue.is_empty()
     }
+
+    pub fn is_pipe_queue_full(&self) -> bool {
+        self.pipe_queue.is_full()
+    }
+
+    pub fn pipe_queue_capacity(&self) -> usize {
+        self.pipe_queue.capacity()
+    }
+
+    pub fn is_pipe_reader_closed(&self) -> bool {
+        self.pipe_reader_closed
+    }
+    /// Task has submitted async syscalls that were not read yet
+    pub fn has_async_submissions(&self) -> bool {
+        match self.state {
+            TaskStates::NotStarted | TaskStates::Dead => false,
+            _ => !self.is_kernel && !self.submission_buffer.is_empty(),
+        }
+    }
+
+    /// Task has async syscalls that were not read or did not complete yet
+    pub fn has_async_work(&self) -> bool {
+        match self.state {
+            TaskStates::NotStarted | TaskStates::Dead => false,
+            _ => self.has_async_submissions() || !self.async_queue.is_empty(),
+        }
+    }
+
     pub fn get_state(&self) -> &TaskStates {
         &self.state
     }
+    pub fn is_kernel(&self) -> bool {
+        self.is_kernel
+    }
+    /// Task was created with CreateThread and shares address space with its parent
+    pub fn is_thread(&self) -> bool {
+        self.thread_stack.is_some()
+    }
 
     pub fn new(
         start_function: extern "C" fn(usize, *const &[u8]) -> u32,
         args: &[&[u8]],
+        environment: &Environment,
+        limits: &ResourceLimits,
         is_kernel: bool,
     ) -> Result<Self, TaskError> {
         let mut task: TaskContext = Self::empty();
+        task.environment = environment.clone();
+        task.limits = limits.clone();
+        task.file_descriptor_table
+            .lock()
+            .set_descriptor_limit(limits.soft(Resource::OpenFiles));
+        let entries = environment.entries();
+        let entries: Vec<&[u8]> = entries.iter().map(|entry| &entry[..]).collect();
 
         let user_address =
             |address: usize| ((address & !crate::KERNEL_OFFSET) | 0x1_0000_0000) as u64;
 
         task.is_kernel = is_kernel;
+        let mut memory_manager = task_memory_manager::TaskMemoryManager::default();
 
-        let mut el0_stack = task_stack::TaskStack::new(
-            TASK_STACK_SIZE,
-            Some(NEXT_STATCK_PTR.fetch_add(TASK_STACK_SIZE * 16, Ordering::SeqCst)),
-            false,
-        )
-        .ok_or(TaskError::StackAllocationFail)?;
-
-        let mut el1_stack = task_stack::TaskStack::new(
+        let el1_stack = task_stack::TaskStack::new(
             TASK_STACK_SIZE,
             Some(NEXT_STATCK_PTR.fetch_add(TASK_STACK_SIZE * 16, Ordering::SeqCst)),
             true,
         )
         .ok_or(TaskError::StackAllocationFail)?;
 
-        let target_stack = if task.is_kernel {
-            &mut el1_stack
+        let target_stack_pointer = if task.is_kernel {
+            let stack = el1_stack.top()..el1_stack.base();
+            task_stack::push_args(stack, args, &entries, |address, data| {
+                unsafe {
+                    core::ptr::copy_nonoverlapping(data.as_ptr(), address as *mut u8, data.len())
+                };
+            })
+            .ok_or(TaskError::ArgumentsTooLong)?
         } else {
-            &mut el0_stack
+            setup_user_stack(&mut memory_manager, limits.stack_size(), args, &entries)?
         };
-
-        let mut argv = Vec::<&[u8]>::new();
-        let mut remaining_size = target_stack.size;
-
-        let mut target_stack_pointer = target_stack.base() as *mut u8;
-        //copy the args onto task stack
-        for arg in args.iter() {
-            let arg_len = arg.len();
-            if remaining_size <= arg_len {
-                panic!("Given args does not fit in task stack");
-            }
-            target_stack_pointer = unsafe { target_stack_pointer.sub(arg_len) };
-
-            unsafe {
-                core::ptr::copy_nonoverlapping(
-                    arg.as_ptr() as *const u8,
-                    target_stack_pointer,
-                    arg_len,
-                );
-            }
-            remaining_size -= arg_len;
-            let slice = unsafe { core::slice::from_raw_parts(target_stack_pointer, arg_len) };
-            argv.push(slice);
-        }
-
-        //copy the args vector onto a stack
-        if remaining_size <= argv.len() {
-            panic!("Given args does not fit in task stack");
-        }
-        target_stack_pointer = unsafe { target_stack_pointer.sub(argv.len()) };
-
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                argv[..].as_ptr() as *const u8,
-                target_stack_pointer,
-                argv.len(),
-            );
-        }
+        task.memory_manager = Arc::new(NullLock::new(memory_manager));
 
         task.gpr.lr = new_task_func as *const () as u64;
         task.gpr.sp = if task.is_kernel {
@@ -230,18 +318,142 @@ impl TaskContext {
         } else {
             task.gpr.x19 = crate::scheduler::drop_el0 as *const () as u64;
             task.gpr.x22 = user_address(start_function as *const () as usize);
-            task.gpr.sp_el0 = if task.is_kernel {
-                el0_stack.base() as u64
-            } else {
-                target_stack_pointer as u64
-            };
+            task.gpr.sp_el0 = target_stack_pointer as u64;
+        }
+        task.gpr.x20 = args.len() as u64;
+        task.gpr.x21 = target_stack_pointer as u64;
+
+        task.el1_stack = Some(el1_stack);
+
+        Ok(task)
+    }
+
+    /// Replaces address space of user task with given one and places args and environment on
+    /// its new EL0 stack. Returns new stack pointer, which is also address of argv.
+    pub fn replace_image(
+        &mut self,
+        mut memory_manager: task_memory_manager::TaskMemoryManager,
+        args: &[&[u8]],
+    ) -> Result<usize, TaskError> {
+        let entries = self.environment.entries();
+        let entries: Vec<&[u8]> = entries.iter().map(|entry| &entry[..]).collect();
+        let stack_size = self.limits.stack_size();
+        let stack_pointer = setup_user_stack(&mut memory_manager, stack_size, args, &entries)?;
+
+        unsafe { memory_manager.activate() };
+        // other threads keep running in the old address space
+        self.memory_manager = Arc::new(NullLock::new(memory_manager));
+        self.signals.reset_handlers();
+        // registered buffers and async syscalls point to memory of the old image
+        self.registered_buffers = RegisteredBuffers::new();
+        self.async_queue
+            .cancel_all(&mut self.submission_buffer, &mut self.completion_buffer);
+        Ok(stack_pointer)
+    }
+
+    /// Creates child task, that continues from the same exception as current one with 0 in x0
+    pub fn fork(&mut self, context: &ExceptionContext) -> Result<Self, TaskError> {
+        if self.is_kernel {
+            return Err(TaskError::InvalidTaskReference);
         }
-        task.gpr.x20 = argv.len() as u64;
-        task.gpr.x21 = argv[..].as_ptr() as u64;
+        let mut task: TaskContext = Self::empty();
+        let memory_manager = self
+            .memory_manager
+            .lock()
+            .fork()
+            .map_err(|_| TaskError::MemoryMappingFail)?;
+        task.memory_manager = Arc::new(NullLock::new(memory_manager));
 
-        task.el0_stack = Some(el0_stack);
+        let el1_stack = task_stack::TaskStack::new(
+            TASK_STACK_SIZE,
+            Some(NEXT_STATCK_PTR.fetch_add(TASK_STACK_SIZE * 16, Ordering::SeqCst)),
+            true,
+        )
+        .ok_or(TaskError::StackAllocationFail)?;
+
+        // child starts by returning from copy of the exception placed on its EL1 stack
+        let context_address =
+            (el1_stack.base() - core::mem::size_of::<ExceptionContext>()) & !0b1111;
+        unsafe {
+            let child_context = context_address as *mut ExceptionContext;
+            core::ptr::copy_nonoverlapping(context as *const ExceptionContext, child_context, 1);
+            (*child_context).gpr[0] = 0;
+        }
+        task.gpr.lr = ret_from_fork as *const () as u64;
+        task.gpr.sp = context_address as u64;
+        task.gpr.x19 = context_address as u64;
+        task.gpr.sp_el0 = context.sp_el0;
+        task.gpr.tpidr_el0 = crate::aarch64::asm::get_el0_thread_pointer();
         task.el1_stack = Some(el1_stack);
 
+        let file_descriptor_table = self.file_descriptor_table.lock().fork();
+        task.file_descriptor_table = Arc::new(NullLock::new(file_descriptor_table));
+        task.pipe_from = self.pipe_from;
+        task.signals = self.signals.fork();
+        task.environment = self.environment.clone();
+        task.current_directory = self.current_directory.clone();
+        task.limits = self.limits.clone();
         Ok(task)
     }
+
+    /// Creates thread sharing address space and file descriptors with this task.
+    /// Thread starts at `entry` with x0 = `argument` and thread pointer set to `tls`,
+    /// on its own EL0 stack mapped below stacks of other threads.
+    pub fn new_thread(&self, entry: usize, argument: usize, tls: usize) -> Result<Self, TaskError> {
+        if self.is_kernel {
+            return Err(TaskError::InvalidTaskReference);
+        }
+        let mut task: TaskContext = Self::empty();
+        let stack = self
+            .memory_manager
+            .lock()
+            .map_thread_stack(self.limits.stack_size())
+            .map_err(|_| TaskError::StackAllocationFail)?;
+        task.memory_manager = self.memory_manager.clone();
+        task.thread_stack = Some(stack.clone());
+
+        let el1_stack = task_stack::TaskStack::new(
+            TASK_STACK_SIZE,
+            Some(NEXT_STATCK_PTR.fetch_add(TASK_STACK_SIZE * 16, Ordering::SeqCst)),
+            true,
+        )
+        .ok_or(TaskError::StackAllocationFail)?;
+
+        task.gpr.lr = new_task_func as *const () as u64;
+        task.gpr.sp = el1_stack.base() as u64;
+        task.gpr.x19 = crate::scheduler::drop_el0 as *const () as u64;
+        task.gpr.x20 = argument as u64;
+        task.gpr.x22 = entry as u64;
+        task.gpr.sp_el0 = (stack.end - 16) as u64;
+        task.gpr.tpidr_el0 = tls as u64;
+        task.el1_stack = Some(el1_stack);
+
+        task.file_descriptor_table = self.file_descriptor_table.clone();
+        task.pipe_from = self.pipe_from;
+        task.signals = self.signals.inherit();
+        task.environment = self.environment.clone();
+        task.current_directory = self.current_directory.clone();
+        task.limits = self.limits.clone();
+        Ok(task)
+    }
+}
+
+/// Maps EL0 stack of given size in address space and places args and environment on it,
+/// returns stack pointer
+fn setup_user_stack(
+    memory_manager: &mut task_memory_manager::TaskMemoryManager,
+    stack_size: usize,
+    args: &[&[u8]],
+    environment: &[&[u8]],
+) -> Result<usize, TaskError> {
+    let stack = memory_manager
+        .map_user_stack(stack_size)
+        .map_err(|_| TaskError::StackAllocationFail)?;
+    let stack = stack.start..stack.end - 16;
+    task_stack::push_args(stack, args, environment, |address, data| {
+        memory_manager
+            .write(address, data)
+            .expect("EL0 stack is not mapped");
+    })
+    .ok_or(TaskError::ArgumentsTooLong)
 }
@@ -1,14 +1,137 @@
+use super::shared_memory::SharedMemory;
 use crate::memory::armv8::mmu::*;
 use crate::memory::armv8::translation_tables::*;
 use crate::memory::memory_controler::*;
+use crate::sync::nulllock::NullLock;
 use crate::utils::binary_info::BinaryInfo;
-use alloc::alloc::{alloc_zeroed, Layout};
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
 use core::ops::Range;
-use core::ptr::null_mut;
+
+/// Start of address range that is private for each task
+pub const PROCESS_OFFSET: usize = 0x1_0000_0000;
+/// Range of virtual addresses that can be used by executables loaded into task
+pub const TASK_PRIVATE_RANGE: Range<usize> = PROCESS_OFFSET..PROCESS_OFFSET + (1 << 30);
+/// EL0 stack of task lies at the end of its private range, so it keeps its address after fork
+pub const USER_STACK_TOP: usize = TASK_PRIVATE_RANGE.end;
+/// Range that heap of task can be grown in with sbrk, it starts empty
+pub const USER_HEAP_RANGE: Range<usize> =
+    TASK_PRIVATE_RANGE.start + 0x2000_0000..TASK_PRIVATE_RANGE.start + 0x3000_0000;
+/// Range that anonymous mappings are placed in
+pub const USER_MMAP_RANGE: Range<usize> =
+    USER_HEAP_RANGE.end..TASK_PRIVATE_RANGE.start + 0x3800_0000;
+/// Range that shared memory objects are attached in
+pub const USER_SHARED_RANGE: Range<usize> =
+    USER_MMAP_RANGE.end..TASK_PRIVATE_RANGE.start + 0x3c00_0000;
+
+pub const PAGE_SIZE: usize = 4096;
+
+/// Identity of memory word that futex waiters sleep on, the same for all tasks that share it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FutexKey {
+    /// Address in private page of address space with given id
+    Private(usize, usize),
+    /// Physical address of memory mapped by many address spaces
+    Shared(usize),
+}
+
+#[derive(Debug)]
+pub enum MappingError {
+    /// Range is not page aligned or lies outside of TASK_PRIVATE_RANGE
+    InvalidRange,
+    /// Range overlaps already mapped memory
+    Overlap,
+    OutOfMemory,
+}
+
+/// Single page of physical memory, freed when last task using it drops it
+pub struct Frame {
+    address: *mut u8,
+}
+
+unsafe impl Send for Frame {}
+unsafe impl Sync for Frame {}
+
+impl Frame {
+    fn layout() -> Layout {
+        Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap()
+    }
+    pub(super) fn new() -> Option<Self> {
+        let address = unsafe { alloc_zeroed(Self::layout()) };
+        if address.is_null() {
+            None
+        } else {
+            Some(Frame { address })
+        }
+    }
+    fn copy_of(other: &Frame) -> Option<Self> {
+        let frame = Self::new()?;
+        unsafe { core::ptr::copy_nonoverlapping(other.address, frame.address, PAGE_SIZE) };
+        Some(frame)
+    }
+    pub(super) fn physical_address(&self) -> usize {
+        self.address as usize & !crate::KERNEL_OFFSET
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.address, Self::layout()) };
+    }
+}
+
+/// Page mapped only in address spaces of single task and its forks
+struct PrivatePage {
+    frame: Arc<Frame>,
+    attribute_fields: AttributeFields,
+    /// Page is shared after fork and mapped read only until first write
+    copy_on_write: bool,
+}
+
+impl PrivatePage {
+    fn mapped_attributes(&self) -> AttributeFields {
+        if self.copy_on_write {
+            AttributeFields {
+                acc_perms: AccessPermissions::UserReadOnly,
+                ..self.attribute_fields
+            }
+        } else {
+            self.attribute_fields
+        }
+    }
+    fn is_writable(&self) -> bool {
+        if let AccessPermissions::UserReadWrite = self.attribute_fields.acc_perms {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn flush_tlb() {
+    unsafe { llvm_asm!("tlbi vmalle1" : : : : "volatile") };
+}
+
+/// Address space used by all threads of a task
+pub type SharedMemoryManager = Arc<NullLock<TaskMemoryManager>>;
+
 pub struct TaskMemoryManager {
     pub additional_table_hack: Box<Level1MemoryTable>,
+    /// Mappings of memory shared by all tasks
     memory_descriptors: MemoryMap,
+    private_regions: BTreeMap<String, Range<usize>>,
+    private_pages: BTreeMap<usize, PrivatePage>,
+    /// Largest number of private pages mapped at once
+    peak_page_count: usize,
+    /// Shared memory objects attached by task, by their start address
+    shared_regions: BTreeMap<usize, Arc<SharedMemory>>,
+    /// End of heap, pages are mapped up to it rounded to whole page
+    heap_break: usize,
+    /// Total size of anonymous mappings
+    anonymous_size: usize,
 }
 
 impl Default for TaskMemoryManager {
@@ -16,7 +139,6 @@ impl Default for TaskMemoryManager {
         let mut memory_map = MemoryMap::new();
         let binary_info = BinaryInfo::get();
 
-        const PROCESS_OFFSET: usize = 0x1_0000_0000;
         let make_virtual = |range: &Range<usize>| {
             ((PROCESS_OFFSET | range.start) & (!crate::KERNEL_OFFSET))
                 ..((PROCESS_OFFSET | range.end) & (!crate::KERNEL_OFFSET))
@@ -42,50 +164,45 @@ impl Default for TaskMemoryManager {
                 granule: Granule::Page4KiB,
             },
         );
-        memory_map.insert(
-            "Allocator Page".into(),
-            RangeDescriptor {
-                virtual_range: make_virtual(&binary_info.allocator),
-                translation: get_offset(&binary_info.allocator),
-                attribute_fields: USER_RW_,
-                granule: Granule::Page4KiB,
-            },
-        );
-        memory_map.insert(
-            "Initial Common Heap".into(),
-            RangeDescriptor {
-                virtual_range: make_virtual(&binary_info.heap),
-                translation: get_offset(&binary_info.heap),
-                attribute_fields: USER_RW_,
-                granule: Granule::Page4KiB,
-            },
-        );
-        let task_local_pages = {
-            binary_info.task_local.start
-                ..binary_info.task_local.end
-                    + (binary_info.task_local.end as *const u8).align_offset(4096)
+        // kernel heap is not mapped, tasks allocate from their own heap grown with sbrk
+        let mut my_memory_manager = TaskMemoryManager {
+            memory_descriptors: memory_map,
+            ..TaskMemoryManager::empty()
         };
+        my_memory_manager.map_descriptors();
 
-        let page_address =
-            unsafe { alloc_zeroed(Layout::from_size_align(task_local_pages.len(), 4096).unwrap()) }
-                as usize;
+        let task_local_pages = {
+            let start = binary_info.task_local.start & !crate::KERNEL_OFFSET;
+            let end = binary_info.task_local.end & !crate::KERNEL_OFFSET;
+            PROCESS_OFFSET | start..PROCESS_OFFSET | (end + (PAGE_SIZE - 1)) & !(PAGE_SIZE - 1)
+        };
+        if !task_local_pages.is_empty() {
+            my_memory_manager
+                .map_new_memory("Mutable Task Local Data", task_local_pages, &[], USER_RW_)
+                .expect("Failed to map task local data");
+        }
 
-        memory_map.insert(
-            "Mutable Task Local Data".into(),
-            RangeDescriptor {
-                virtual_range: make_virtual(&binary_info.task_local),
-                translation: Translation::Offset(page_address),
-                attribute_fields: USER_RW_,
-                granule: Granule::Page4KiB,
-            },
-        );
+        my_memory_manager
+    }
+}
 
-        let mut my_memory_manager = TaskMemoryManager {
-            memory_descriptors: memory_map,
+impl TaskMemoryManager {
+    /// Address space without any kernel provided mappings, used for loaded executables
+    pub fn empty() -> Self {
+        TaskMemoryManager {
+            memory_descriptors: MemoryMap::new(),
             additional_table_hack: unsafe { Box::new_zeroed().assume_init() },
-        };
+            private_regions: BTreeMap::new(),
+            private_pages: BTreeMap::new(),
+            peak_page_count: 0,
+            shared_regions: BTreeMap::new(),
+            heap_break: USER_HEAP_RANGE.start,
+            anonymous_size: 0,
+        }
+    }
 
-        for (_, memory) in my_memory_manager.memory_descriptors.iter() {
+    fn map_descriptors(&mut self) {
+        for (_, memory) in self.memory_descriptors.iter() {
             let step = match &memory.granule {
                 Granule::Page4KiB => 1 << 12,
                 Granule::Block2MiB => 1 << 21,
@@ -100,14 +217,471 @@ impl Default for TaskMemoryManager {
             let range = memory.virtual_range.clone();
             for address in range.step_by(step) {
                 unsafe {
-                    my_memory_manager
-                        .additional_table_hack
+                    self.additional_table_hack
                         .map_memory(address, offset, &memory.attribute_fields, memory.granule)
                         .unwrap();
                 }
             }
         }
+    }
 
-        my_memory_manager
+    /// Ranges of all mappings of the address space
+    fn mapped_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let shared = self
+            .shared_regions
+            .iter()
+            .map(|(start, memory)| *start..*start + memory.size());
+        self.memory_descriptors
+            .values()
+            .map(|memory| memory.virtual_range.clone())
+            .chain(self.private_regions.values().cloned())
+            .chain(shared)
+    }
+
+    /// Mapping that overlaps given range, if there is one
+    fn find_overlapping(&self, virtual_range: &Range<usize>) -> Option<Range<usize>> {
+        self.mapped_ranges()
+            .find(|range| range.start < virtual_range.end && virtual_range.start < range.end)
+    }
+
+    fn overlaps(&self, virtual_range: &Range<usize>) -> bool {
+        self.find_overlapping(virtual_range).is_some()
+    }
+
+    /// Lowest address in given range, that region of given size can be mapped at
+    fn find_free_range(&self, within: Range<usize>, size: usize) -> Option<usize> {
+        let mut start = within.start;
+        while start + size <= within.end {
+            match self.find_overlapping(&(start..start + size)) {
+                // continue search after mapping that overlaps the candidate
+                Some(range) => start = range.end,
+                None => return Some(start),
+            }
+        }
+        None
+    }
+
+    fn map_page(&mut self, address: usize, page: PrivatePage) -> Result<(), MappingError> {
+        let offset = page.frame.physical_address().wrapping_sub(address);
+        unsafe {
+            self.additional_table_hack
+                .map_memory(
+                    address,
+                    offset,
+                    &page.mapped_attributes(),
+                    Granule::Page4KiB,
+                )
+                .map_err(|_| MappingError::Overlap)?;
+        }
+        self.private_pages.insert(address, page);
+        self.peak_page_count = core::cmp::max(self.peak_page_count, self.private_pages.len());
+        Ok(())
+    }
+
+    pub fn peak_page_count(&self) -> usize {
+        self.peak_page_count
+    }
+
+    fn remap_page(&mut self, address: usize) {
+        if let Some(page) = self.private_pages.remove(&address) {
+            unsafe {
+                self.additional_table_hack
+                    .unmap_memory(address, Granule::Page4KiB)
+                    .unwrap();
+            }
+            self.map_page(address, page).unwrap();
+        }
+    }
+
+    /// Allocates zeroed pages for given range, fills its beginning with data and maps it
+    pub fn map_new_memory(
+        &mut self,
+        name: &str,
+        virtual_range: Range<usize>,
+        data: &[u8],
+        attribute_fields: AttributeFields,
+    ) -> Result<(), MappingError> {
+        if virtual_range.start % PAGE_SIZE != 0
+            || virtual_range.end % PAGE_SIZE != 0
+            || virtual_range.start >= virtual_range.end
+            || virtual_range.start < TASK_PRIVATE_RANGE.start
+            || virtual_range.end > TASK_PRIVATE_RANGE.end
+            || data.len() > virtual_range.len()
+            || self.private_regions.contains_key(name)
+        {
+            return Err(MappingError::InvalidRange);
+        }
+        if self.overlaps(&virtual_range) {
+            return Err(MappingError::Overlap);
+        }
+
+        self.map_pages(virtual_range.clone(), data, attribute_fields)?;
+        self.private_regions.insert(name.into(), virtual_range);
+        Ok(())
+    }
+
+    /// Maps fresh pages in given range, filling its beginning with data
+    fn map_pages(
+        &mut self,
+        virtual_range: Range<usize>,
+        data: &[u8],
+        attribute_fields: AttributeFields,
+    ) -> Result<(), MappingError> {
+        for (index, address) in virtual_range.step_by(PAGE_SIZE).enumerate() {
+            let frame = Frame::new().ok_or(MappingError::OutOfMemory)?;
+            let chunk_start = core::cmp::min(index * PAGE_SIZE, data.len());
+            let chunk = &data[chunk_start..core::cmp::min(chunk_start + PAGE_SIZE, data.len())];
+            unsafe { core::ptr::copy_nonoverlapping(chunk.as_ptr(), frame.address, chunk.len()) };
+            self.map_page(
+                address,
+                PrivatePage {
+                    frame: Arc::new(frame),
+                    attribute_fields,
+                    copy_on_write: false,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Unmaps pages in given range, they are freed when no forked task uses them
+    fn unmap_pages(&mut self, virtual_range: Range<usize>) {
+        for address in virtual_range.step_by(PAGE_SIZE) {
+            if self.private_pages.remove(&address).is_some() {
+                unsafe {
+                    self.additional_table_hack
+                        .unmap_memory(address, Granule::Page4KiB)
+                        .unwrap();
+                }
+            }
+        }
+        flush_tlb();
+    }
+
+    pub fn heap_break(&self) -> usize {
+        self.heap_break
+    }
+
+    /// Memory allocated by task with sbrk and anonymous mappings
+    pub fn heap_size(&self) -> usize {
+        self.heap_break - USER_HEAP_RANGE.start + self.anonymous_size
+    }
+
+    /// Moves end of heap, mapping or unmapping pages between old and new end
+    pub fn set_heap_break(&mut self, heap_break: usize) -> Result<(), MappingError> {
+        if !(USER_HEAP_RANGE.start..=USER_HEAP_RANGE.end).contains(&heap_break) {
+            return Err(MappingError::InvalidRange);
+        }
+        let page_up = |address: usize| (address + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let old_end = page_up(self.heap_break);
+        let new_end = page_up(heap_break);
+        if new_end > old_end {
+            if self.overlaps(&(old_end..new_end)) {
+                return Err(MappingError::Overlap);
+            }
+            if let Err(error) = self.map_pages(old_end..new_end, &[], USER_RW_) {
+                self.unmap_pages(old_end..new_end);
+                return Err(error);
+            }
+        } else if new_end < old_end {
+            self.unmap_pages(new_end..old_end);
+        }
+        self.private_regions
+            .insert("Heap".into(), USER_HEAP_RANGE.start..new_end);
+        self.heap_break = heap_break;
+        Ok(())
+    }
+
+    /// Maps zeroed read write pages of given size in free part of USER_MMAP_RANGE,
+    /// returns their address
+    pub fn map_anonymous(&mut self, size: usize) -> Result<usize, MappingError> {
+        if size == 0 || size % PAGE_SIZE != 0 {
+            return Err(MappingError::InvalidRange);
+        }
+        let start = self
+            .find_free_range(USER_MMAP_RANGE, size)
+            .ok_or(MappingError::OutOfMemory)?;
+        let name = format!("Anonymous {:#x}", start);
+        self.map_new_memory(&name, start..start + size, &[], USER_RW_)?;
+        self.anonymous_size += size;
+        Ok(start)
+    }
+
+    /// Unmaps whole anonymous mapping starting at given address
+    pub fn unmap_anonymous(&mut self, address: usize, size: usize) -> Result<(), MappingError> {
+        let name = format!("Anonymous {:#x}", address);
+        match self.private_regions.get(&name) {
+            Some(region) if region.len() == size => self.unmap_memory(&name),
+            _ => Err(MappingError::InvalidRange),
+        }?;
+        self.anonymous_size -= size;
+        Ok(())
+    }
+
+    /// Maps pages of shared memory object in free part of USER_SHARED_RANGE, returns their
+    /// address. Pages stay shared after fork.
+    pub fn map_shared(&mut self, memory: Arc<SharedMemory>) -> Result<usize, MappingError> {
+        let start = self
+            .find_free_range(USER_SHARED_RANGE, memory.size())
+            .ok_or(MappingError::OutOfMemory)?;
+        self.map_shared_at(start, memory)?;
+        Ok(start)
+    }
+
+    fn map_shared_at(
+        &mut self,
+        start: usize,
+        memory: Arc<SharedMemory>,
+    ) -> Result<(), MappingError> {
+        for (index, frame) in memory.frames().iter().enumerate() {
+            let address = start + index * PAGE_SIZE;
+            let offset = frame.physical_address().wrapping_sub(address);
+            let mapped = unsafe {
+                self.additional_table_hack
+                    .map_memory(address, offset, &USER_RW_, Granule::Page4KiB)
+            };
+            if mapped.is_err() {
+                // object is attached whole or not at all
+                for page in (start..address).step_by(PAGE_SIZE) {
+                    unsafe {
+                        self.additional_table_hack
+                            .unmap_memory(page, Granule::Page4KiB)
+                            .unwrap();
+                    }
+                }
+                flush_tlb();
+                return Err(MappingError::Overlap);
+            }
+        }
+        self.shared_regions.insert(start, memory);
+        Ok(())
+    }
+
+    /// Addresses of shared memory object attached at given address
+    pub fn shared_range(&self, address: usize) -> Option<Range<usize>> {
+        let memory = self.shared_regions.get(&address)?;
+        Some(address..address + memory.size())
+    }
+
+    /// Unmaps shared memory object attached at given address, object is freed when no task
+    /// has it attached
+    pub fn unmap_shared(&mut self, address: usize) -> Result<(), MappingError> {
+        let memory = self
+            .shared_regions
+            .remove(&address)
+            .ok_or(MappingError::InvalidRange)?;
+        for page in (address..address + memory.size()).step_by(PAGE_SIZE) {
+            unsafe {
+                self.additional_table_hack
+                    .unmap_memory(page, Granule::Page4KiB)
+                    .unwrap();
+            }
+        }
+        flush_tlb();
+        Ok(())
+    }
+
+    /// Unmaps all attached shared memory objects, done when task finishes
+    pub fn unmap_all_shared(&mut self) {
+        let addresses: alloc::vec::Vec<usize> = self.shared_regions.keys().copied().collect();
+        for address in addresses {
+            self.unmap_shared(address).ok();
+        }
+    }
+
+    /// Key identifying word at given address for futex waits, None if address is not mapped
+    pub fn futex_key(&self, address: usize) -> Option<FutexKey> {
+        let page = address & !(PAGE_SIZE - 1);
+        if self.private_pages.contains_key(&page) {
+            // copy on write pages change frames, so they are identified by address space
+            return Some(FutexKey::Private(self as *const Self as usize, address));
+        }
+        if let Some((start, memory)) = self.shared_regions.range(..=address).next_back() {
+            if address < start + memory.size() {
+                let frame = &memory.frames()[(address - start) / PAGE_SIZE];
+                return Some(FutexKey::Shared(
+                    frame.physical_address() + address % PAGE_SIZE,
+                ));
+            }
+        }
+        self.memory_descriptors
+            .values()
+            .find(|memory| memory.virtual_range.contains(&address))
+            .map(|memory| match memory.translation {
+                Translation::Identity => FutexKey::Shared(address),
+                Translation::Offset(value) => {
+                    FutexKey::Shared(value + (address - memory.virtual_range.start))
+                }
+            })
+    }
+
+    /// Whether task can write to every page of given range
+    pub fn is_writable(&self, range: &Range<usize>) -> bool {
+        let mut page = range.start & !(PAGE_SIZE - 1);
+        while page < range.end {
+            if !self.is_page_writable(page) {
+                return false;
+            }
+            page += PAGE_SIZE;
+        }
+        true
+    }
+
+    fn is_page_writable(&self, page: usize) -> bool {
+        if let Some(private_page) = self.private_pages.get(&page) {
+            // copy on write pages are copied when kernel writes to them as well
+            return private_page.is_writable();
+        }
+        if let Some((start, memory)) = self.shared_regions.range(..=page).next_back() {
+            if page < start + memory.size() {
+                return true;
+            }
+        }
+        self.memory_descriptors.values().any(|memory| {
+            memory.virtual_range.contains(&page)
+                && if let AccessPermissions::UserReadWrite = memory.attribute_fields.acc_perms {
+                    true
+                } else {
+                    false
+                }
+        })
+    }
+
+    /// Maps EL0 stack of given size ending at USER_STACK_TOP
+    pub fn map_user_stack(&mut self, size: usize) -> Result<Range<usize>, MappingError> {
+        let range = USER_STACK_TOP - size..USER_STACK_TOP;
+        self.map_new_memory("Stack", range.clone(), &[], USER_RW_)?;
+        Ok(range)
+    }
+
+    /// Maps EL0 stack for additional thread below main stack and stacks of other threads.
+    /// Stacks are separated by unmapped guard page.
+    pub fn map_thread_stack(&mut self, size: usize) -> Result<Range<usize>, MappingError> {
+        let mut end = USER_STACK_TOP - size - PAGE_SIZE;
+        while end - size >= TASK_PRIVATE_RANGE.start {
+            let range = end - size..end;
+            if !self.overlaps(&range) {
+                self.map_new_memory(
+                    &format!("Thread Stack {:#x}", end),
+                    range.clone(),
+                    &[],
+                    USER_RW_,
+                )?;
+                return Ok(range);
+            }
+            end -= size + PAGE_SIZE;
+        }
+        Err(MappingError::OutOfMemory)
+    }
+
+    /// Unmaps private region with given name, its pages are freed when no forked task uses them
+    pub fn unmap_memory(&mut self, name: &str) -> Result<(), MappingError> {
+        let range = self
+            .private_regions
+            .remove(name)
+            .ok_or(MappingError::InvalidRange)?;
+        self.unmap_pages(range);
+        Ok(())
+    }
+
+    /// Writes data to task memory, bypassing page permissions and copy on write sharing.
+    /// Intended for initialization of fresh address spaces.
+    pub fn write(&mut self, address: usize, data: &[u8]) -> Result<(), MappingError> {
+        let mut written = 0;
+        while written < data.len() {
+            let current = address + written;
+            let page = self
+                .private_pages
+                .get(&(current & !(PAGE_SIZE - 1)))
+                .ok_or(MappingError::InvalidRange)?;
+            let page_offset = current % PAGE_SIZE;
+            let count = core::cmp::min(PAGE_SIZE - page_offset, data.len() - written);
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    data[written..].as_ptr(),
+                    page.frame.address.add(page_offset),
+                    count,
+                );
+            }
+            written += count;
+        }
+        Ok(())
+    }
+
+    /// Creates copy of address space. Writable private pages become shared and read only in both
+    /// address spaces, they are copied on first write. Memory common for all tasks stays shared.
+    pub fn fork(&mut self) -> Result<Self, MappingError> {
+        let mut child = TaskMemoryManager::empty();
+        for (name, memory) in self.memory_descriptors.iter() {
+            child.memory_descriptors.insert(
+                name.clone(),
+                RangeDescriptor::new(
+                    memory.virtual_range.clone(),
+                    memory.translation,
+                    memory.attribute_fields,
+                    memory.granule,
+                ),
+            );
+        }
+        child.map_descriptors();
+        child.private_regions = self.private_regions.clone();
+        child.heap_break = self.heap_break;
+        child.anonymous_size = self.anonymous_size;
+
+        let shared_writable: alloc::vec::Vec<usize> = self
+            .private_pages
+            .iter()
+            .filter(|(_, page)| page.is_writable() && !page.copy_on_write)
+            .map(|(address, _)| *address)
+            .collect();
+        for address in shared_writable {
+            if let Some(page) = self.private_pages.get_mut(&address) {
+                page.copy_on_write = true;
+            }
+            self.remap_page(address);
+        }
+        flush_tlb();
+
+        for (address, page) in self.private_pages.iter() {
+            child.map_page(
+                *address,
+                PrivatePage {
+                    frame: page.frame.clone(),
+                    attribute_fields: page.attribute_fields,
+                    copy_on_write: page.copy_on_write,
+                },
+            )?;
+        }
+        for (address, memory) in self.shared_regions.iter() {
+            child.map_shared_at(*address, memory.clone())?;
+        }
+        Ok(child)
+    }
+
+    /// Resolves write to copy on write page, returns false if fault was not caused by sharing
+    pub fn handle_copy_on_write(&mut self, address: usize) -> bool {
+        let page_address = address & !(PAGE_SIZE - 1);
+        let page = match self.private_pages.get_mut(&page_address) {
+            Some(page) if page.copy_on_write => page,
+            _ => return false,
+        };
+        if Arc::strong_count(&page.frame) > 1 {
+            page.frame = match Frame::copy_of(&page.frame) {
+                Some(frame) => Arc::new(frame),
+                None => return false,
+            };
+        }
+        page.copy_on_write = false;
+        self.remap_page(page_address);
+        flush_tlb();
+        true
+    }
+
+    /// Makes this address space the one used by currently running task
+    ///
+    /// # Safety
+    /// Caller must make sure that self belongs to currently running task
+    pub unsafe fn activate(&mut self) {
+        switch_user_space_translation_first(self.additional_table_hack.as_mut());
     }
 }
@@ -1,4 +1,6 @@
 use alloc::alloc::*;
+use alloc::vec::Vec;
+use core::ops::Range;
 
 unsafe impl Sync for TaskStack {}
 
@@ -83,6 +85,40 @@ impl TaskStack {
         self.size
     }
 }
+
+/// Copies args, environment entries and slices describing them below top of the stack using
+/// given writer. Slices of environment follow argv and end with empty slice.
+/// Returns aligned stack pointer, which is also address of argv array.
+pub fn push_args<W>(
+    stack: Range<usize>,
+    args: &[&[u8]],
+    environment: &[&[u8]],
+    mut write: W,
+) -> Option<usize>
+where
+    W: FnMut(usize, &[u8]),
+{
+    let mut stack_pointer = stack.end;
+    let mut argv = Vec::<&[u8]>::with_capacity(args.len() + environment.len() + 1);
+    for arg in args.iter().chain(environment.iter()) {
+        stack_pointer = stack_pointer
+            .checked_sub(arg.len())
+            .filter(|pointer| *pointer > stack.start)?;
+        write(stack_pointer, arg);
+        argv.push(unsafe { core::slice::from_raw_parts(stack_pointer as *const u8, arg.len()) });
+    }
+    argv.push(&[]);
+    let argv_size = argv.len() * core::mem::size_of::<&[u8]>();
+    stack_pointer = stack_pointer
+        .checked_sub(argv_size)
+        .map(|pointer| pointer & !0b1111)
+        .filter(|pointer| *pointer > stack.start)?;
+    write(stack_pointer, unsafe {
+        core::slice::from_raw_parts(argv.as_ptr() as *const u8, argv_size)
+    });
+    Some(stack_pointer)
+}
+
 impl Drop for TaskStack {
     fn drop(&mut self) {
         let layout = Layout::from_size_align(self.size, 16).unwrap();
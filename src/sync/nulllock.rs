@@ -32,6 +32,10 @@ impl<T> NullLock<T> {
             data: UnsafeCell::new(data),
         }
     }
+    /// Consumes the lock, returning protected value
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
     /// Locks the mutex and returns dereferncable lock guard
     /// For most cases prefer using sync method
     ///
@@ -1,7 +1,20 @@
 pub use num_traits::FromPrimitive;
 
+pub mod clock;
+pub mod environment;
+pub mod exec;
 pub mod files;
+pub mod fork;
+pub mod futex;
+pub mod memory;
+pub mod ports;
 pub mod print;
+pub mod rlimit;
+pub mod rusage;
+pub mod shared_memory;
+pub mod signals;
+pub mod threads;
+pub mod wait;
 
 pub mod asynchronous;
 
@@ -30,6 +43,50 @@ pub enum Syscalls {
     CreateFile,
     DeleteFile,
     SetPipeReadOnPID,
+    Wait,
+    Kill,
+    SignalAction,
+    SignalMask,
+    SignalReturn,
+    Exec,
+    Fork,
+    CreateThread,
+    JoinThread,
+    DetachThread,
+    DuplicateFd,
+    DuplicateFdTo,
+    SetCloseOnExec,
+    CreatePipe,
+    PipeCapacity,
+    Poll,
+    GetEnvironmentVariable,
+    SetEnvironmentVariable,
+    RemoveEnvironmentVariable,
+    ChangeDirectory,
+    GetCurrentDirectory,
+    GetTime,
+    SetTime,
+    GetResourceUsage,
+    GetResourceLimit,
+    SetResourceLimit,
+    Sbrk,
+    MapMemory,
+    UnmapMemory,
+    CreateSharedMemory,
+    AttachSharedMemory,
+    DetachSharedMemory,
+    CreatePort,
+    OpenPort,
+    SendMessage,
+    ReceiveMessage,
+    ReplyMessage,
+    FutexWait,
+    FutexWake,
+    GetAsyncOverflowCount,
+    AsyncDoorbell,
+    AsyncEnter,
+    RegisterAsyncBuffer,
+    UnregisterAsyncBuffer,
 }
 
 #[inline(never)]
@@ -175,6 +232,10 @@ pub fn get_async_submission_buffer() -> &'static mut CircullarBuffer {
 pub fn get_async_completion_buffer() -> &'static mut CircullarBuffer {
     unsafe { &mut *(syscall0(Syscalls::GetAsyncCompletionBuffer as usize) as *mut CircullarBuffer) }
 }
+/// How many async completions did not fit in completion buffer and had to wait in kernel
+pub fn get_async_overflow_count() -> usize {
+    unsafe { syscall0(Syscalls::GetAsyncOverflowCount as usize) as usize }
+}
 
 pub fn get_pid() -> usize {
     unsafe { syscall0(Syscalls::GetPID as usize) as usize }
@@ -0,0 +1,45 @@
+use super::async_worker::wake_async_worker;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::WaitCondition;
+use crate::syscall::*;
+use core::time::Duration;
+
+const NO_TIMEOUT: usize = usize::MAX;
+
+/// Lets async worker handle submitted syscalls and suspends task until at least min_complete
+/// completions are in completion buffer, timeout passes or signal handler has to run. Returns
+/// count of completions in completion buffer.
+pub fn async_enter(min_complete: usize, timeout: Option<Duration>) -> usize {
+    let timeout = timeout.map_or(NO_TIMEOUT, |timeout| timeout.as_millis() as usize);
+    unsafe { syscall2(min_complete, timeout, Syscalls::AsyncEnter as usize) }
+}
+
+pub fn handle_async_enter(context: &mut ExceptionContext) {
+    let min_complete = context.gpr[0] as usize;
+    let timeout = context.gpr[1] as usize;
+
+    let deadline = match timeout {
+        NO_TIMEOUT => None,
+        timeout => Some(ArmTimer {}.get_time() + Duration::from_millis(timeout as u64)),
+    };
+
+    wake_async_worker();
+    let mut interrupted = false;
+    loop {
+        let current_task = unsafe { &*scheduler::get_current_task_context() };
+        let available = current_task.completion_buffer.value_count();
+        let timed_out = deadline.map_or(false, |deadline| ArmTimer {}.get_time() >= deadline);
+        if available >= min_complete || timed_out || interrupted {
+            context.gpr[0] = available as u64;
+            return;
+        }
+        interrupted = scheduler::suspend_current_task(WaitCondition::AsyncCompletions {
+            count: min_complete,
+            deadline,
+        })
+        .is_err();
+    }
+}
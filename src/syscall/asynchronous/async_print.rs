@@ -3,12 +3,16 @@ use crate::syscall::print;
 use crate::utils::circullar_buffer::*;
 use async_syscall::*;
 
-pub fn async_print_standalone(msg: &str, id: usize) {
+pub fn async_print_standalone(msg: &str, id: usize) -> Result<(), AsyncSyscallError> {
     let submission_buffer = crate::syscall::get_async_submission_buffer();
-    async_print(msg, id, submission_buffer);
+    async_print(msg, id, submission_buffer)
 }
 
-pub fn async_print(msg: &str, id: usize, submission_buffer: &mut CircullarBuffer) {
+pub fn async_print(
+    msg: &str,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
     let bytes = msg.as_bytes();
 
     let a: AsyncSyscall = AsyncSyscall {
@@ -16,9 +20,10 @@ pub fn async_print(msg: &str, id: usize, submission_buffer: &mut CircullarBuffer
         syscall_type: crate::syscall::asynchronous::async_syscall::AsyncSyscalls::Print,
         data: bytes,
         id,
+        flags: 0,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
+    send_async_syscall(submission_buffer, a)
 }
 
 pub(in crate::syscall::asynchronous) fn handle_async_print(ptr: *const u8, len: usize) -> usize {
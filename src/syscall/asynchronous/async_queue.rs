@@ -0,0 +1,428 @@
+use super::async_syscall::*;
+use super::cancel::{self, CancelError};
+use super::pending::PendingAsyncSyscall;
+use super::timeout::{self, TimeoutError};
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::utils::circullar_buffer::CircullarBuffer;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Syscall runs only after previously submitted syscall succeeds, it is canceled otherwise
+pub const ASYNC_LINK: usize = 1;
+
+/// Returned value of canceled syscalls and of syscalls linked after failed one
+pub const ASYNC_CANCELED: usize = usize::MAX;
+
+type Completions = VecDeque<AsyncSyscallReturnedValue>;
+
+fn complete(completions: &mut Completions, id: usize, value: usize) {
+    completions.push_back(AsyncSyscallReturnedValue { id, value });
+}
+
+/// Completes syscall that will not run, together with its link timeout
+fn complete_canceled(completions: &mut Completions, syscall: QueuedAsyncSyscall) {
+    complete(completions, syscall.id, ASYNC_CANCELED);
+    if let Some((timeout_id, _)) = syscall.link_timeout {
+        complete(completions, timeout_id, ASYNC_CANCELED);
+    }
+}
+
+/// Places returned value of async syscall in completion buffer, fails if it is full
+fn post_completion(
+    completion_buffer: &mut CircullarBuffer,
+    completion: &AsyncSyscallReturnedValue,
+) -> Result<(), ()> {
+    let buffer_frame = completion_buffer
+        .reserve(core::mem::size_of::<AsyncSyscallReturnedValue>())
+        .map_err(|_| ())?;
+    let return_structure: &mut AsyncSyscallReturnedValue =
+        unsafe { crate::utils::struct_to_slice::u8_slice_to_any_mut(buffer_frame.memory) };
+    return_structure.id = completion.id;
+    return_structure.value = completion.value;
+    Ok(())
+}
+
+/// Submitted syscall copied out of submission buffer, waiting for its turn in chain
+pub struct QueuedAsyncSyscall {
+    pub id: usize,
+    pub syscall_type: AsyncSyscalls,
+    // kept in words, so syscall data structures stay aligned
+    data: Vec<usize>,
+    data_size: usize,
+    /// Id and duration of link timeout submitted after this syscall
+    link_timeout: Option<(usize, Duration)>,
+}
+
+impl QueuedAsyncSyscall {
+    pub fn new(request: AsyncSyscallRequest) -> Self {
+        let data_size = request.get_data_size();
+        let word_size = core::mem::size_of::<usize>();
+        let mut data = Vec::new();
+        data.resize((data_size + word_size - 1) / word_size, 0);
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                request.get_syscall_data().as_ptr(),
+                data.as_mut_ptr() as *mut u8,
+                data_size,
+            );
+        }
+        QueuedAsyncSyscall {
+            id: request.id,
+            syscall_type: request.syscall_type,
+            data,
+            data_size,
+            link_timeout: None,
+        }
+    }
+
+    pub fn data_ptr(&self) -> *const u8 {
+        self.data.as_ptr() as *const u8
+    }
+
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+}
+
+/// Syscalls linked with each other, run one after another
+struct AsyncChain {
+    entries: VecDeque<QueuedAsyncSyscall>,
+    /// Syscall of chain that waits for children, time or data
+    in_progress: Option<PendingAsyncSyscall>,
+    /// Id and deadline of link timeout of syscall in progress
+    timeout: Option<(usize, Duration)>,
+    /// Some syscall of chain failed, the rest of it is canceled
+    failed: bool,
+}
+
+impl AsyncChain {
+    fn new(failed: bool) -> Self {
+        AsyncChain {
+            entries: VecDeque::new(),
+            in_progress: None,
+            timeout: None,
+            failed,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.entries.is_empty() && self.in_progress.is_none()
+    }
+
+    fn cancel_entries(&mut self, completions: &mut Completions) {
+        for syscall in self.entries.drain(..) {
+            complete_canceled(completions, syscall);
+        }
+    }
+
+    /// Completes link timeout of syscall in progress with given value, if it has one
+    fn disarm_timeout(&mut self, completions: &mut Completions, value: usize) {
+        if let Some((timeout_id, _)) = self.timeout.take() {
+            complete(completions, timeout_id, value);
+        }
+    }
+}
+
+/// Async syscalls of task that were submitted but did not complete yet. Chains are independent
+/// of each other, so syscall waiting in one of them does not hold back the others.
+pub struct AsyncQueue {
+    chains: Vec<AsyncChain>,
+    /// Whether last submitted syscall failed, None while its chain is still running
+    finished_tail: Option<bool>,
+    /// Completions that did not fit in completion buffer, posted before any newer ones
+    overflow: Completions,
+    /// How many completions had to wait in overflow
+    overflow_count: usize,
+    /// How many completions were produced, timeouts can wait for their count
+    posted: usize,
+}
+
+impl Default for AsyncQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsyncQueue {
+    pub fn new() -> Self {
+        AsyncQueue {
+            chains: Vec::new(),
+            finished_tail: Some(false),
+            overflow: VecDeque::new(),
+            overflow_count: 0,
+            posted: 0,
+        }
+    }
+
+    /// No syscall is waiting, running or has its completion in overflow
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty() && self.overflow.is_empty()
+    }
+
+    pub fn overflow_count(&self) -> usize {
+        self.overflow_count
+    }
+
+    /// Moves overflowed completions to completion buffer, returns whether all of them fit
+    pub fn flush_overflow(&mut self, completion_buffer: &mut CircullarBuffer) -> bool {
+        while let Some(completion) = self.overflow.front() {
+            if post_completion(completion_buffer, completion).is_err() {
+                return false;
+            }
+            self.overflow.pop_front();
+        }
+        true
+    }
+
+    pub fn submit(&mut self, syscall: QueuedAsyncSyscall, flags: usize) {
+        if let AsyncSyscalls::LinkTimeout = syscall.syscall_type {
+            if flags & ASYNC_LINK != 0 && self.attach_link_timeout(&syscall) {
+                return;
+            }
+        }
+        if flags & ASYNC_LINK == 0 {
+            self.chains.push(AsyncChain::new(false));
+        } else if let Some(failed) = self.finished_tail {
+            self.chains.push(AsyncChain::new(failed));
+        }
+        self.finished_tail = None;
+        self.chains
+            .last_mut()
+            .expect("Async queue has no chain to submit to")
+            .entries
+            .push_back(syscall);
+    }
+
+    /// Makes link timeout time last submitted syscall, if it did not complete yet
+    fn attach_link_timeout(&mut self, link_timeout: &QueuedAsyncSyscall) -> bool {
+        if self.finished_tail.is_some() {
+            return false;
+        }
+        let duration =
+            timeout::link_timeout_duration(link_timeout.data_ptr(), link_timeout.data_size());
+        let chain = match self.chains.last_mut() {
+            Some(chain) => chain,
+            None => return false,
+        };
+        if let Some(syscall) = chain.entries.back_mut() {
+            if syscall.link_timeout.is_some() {
+                return false;
+            }
+            syscall.link_timeout = Some((link_timeout.id, duration));
+            return true;
+        }
+        if chain.in_progress.is_some() && chain.timeout.is_none() {
+            chain.timeout = Some((link_timeout.id, ArmTimer {}.get_time() + duration));
+            return true;
+        }
+        false
+    }
+
+    /// Runs syscalls whose predecessors finished and completes waiting syscalls that are ready.
+    /// run returns Err for syscalls that have to wait.
+    pub fn process<F>(&mut self, completion_buffer: &mut CircullarBuffer, mut run: F)
+    where
+        F: FnMut(&QueuedAsyncSyscall) -> Result<usize, PendingAsyncSyscall>,
+    {
+        let mut completions = VecDeque::new();
+        for index in 0..self.chains.len() {
+            self.advance(index, &mut completions, &mut run);
+        }
+        self.post(completion_buffer, completions);
+        if self.finished_tail.is_none() {
+            if let Some(chain) = self.chains.last() {
+                if chain.is_finished() {
+                    self.finished_tail = Some(chain.failed);
+                }
+            }
+        }
+        self.chains.retain(|chain| !chain.is_finished());
+    }
+
+    /// Cancels waiting reads into registered buffer with given index, when it is unregistered
+    pub fn cancel_buffer_users(&mut self, index: usize, completion_buffer: &mut CircullarBuffer) {
+        let targets: Vec<usize> = self
+            .chains
+            .iter()
+            .filter_map(|chain| chain.in_progress.as_ref())
+            .filter(|pending| pending.uses_buffer(index))
+            .map(|pending| pending.id())
+            .collect();
+        let mut completions = VecDeque::new();
+        for target in targets {
+            self.cancel(target, &mut completions).ok();
+        }
+        self.post(completion_buffer, completions);
+    }
+
+    /// Cancels all submitted syscalls, including ones left unread in submission buffer, when
+    /// task replaces its image that they point to
+    pub fn cancel_all(
+        &mut self,
+        submission_buffer: &mut CircullarBuffer,
+        completion_buffer: &mut CircullarBuffer,
+    ) {
+        let mut completions = VecDeque::new();
+        for mut chain in self.chains.drain(..) {
+            if let Some(pending) = chain.in_progress.take() {
+                complete(&mut completions, pending.id(), ASYNC_CANCELED);
+            }
+            chain.disarm_timeout(&mut completions, ASYNC_CANCELED);
+            chain.cancel_entries(&mut completions);
+        }
+        while let Some(request) = read_async_syscall(submission_buffer) {
+            complete(&mut completions, request.id, ASYNC_CANCELED);
+        }
+        self.finished_tail = Some(false);
+        self.post(completion_buffer, completions);
+    }
+
+    /// Places completions in completion buffer, or in overflow when it is full
+    fn post(&mut self, completion_buffer: &mut CircullarBuffer, completions: Completions) {
+        self.posted += completions.len();
+        for completion in completions {
+            if !self.overflow.is_empty() || post_completion(completion_buffer, &completion).is_err()
+            {
+                self.overflow.push_back(completion);
+                self.overflow_count += 1;
+            }
+        }
+    }
+
+    fn advance<F>(&mut self, index: usize, completions: &mut Completions, run: &mut F)
+    where
+        F: FnMut(&QueuedAsyncSyscall) -> Result<usize, PendingAsyncSyscall>,
+    {
+        let posted = self.posted;
+        let polled = self.chains[index]
+            .in_progress
+            .as_mut()
+            .map(|pending| (pending.id(), pending.poll(posted)));
+        match polled {
+            Some((id, None)) => {
+                let chain = &mut self.chains[index];
+                let now = ArmTimer {}.get_time();
+                if !chain.timeout.map_or(false, |(_, deadline)| deadline <= now) {
+                    return;
+                }
+                chain.in_progress = None;
+                chain.failed = true;
+                complete(completions, id, ASYNC_CANCELED);
+                chain.disarm_timeout(
+                    completions,
+                    ONLY_MSB_OF_USIZE | TimeoutError::Expired as usize,
+                );
+            }
+            Some((id, Some(value))) => {
+                self.chains[index].in_progress = None;
+                self.chains[index].disarm_timeout(completions, ASYNC_CANCELED);
+                self.finish(index, id, value, completions);
+            }
+            None => {}
+        }
+        while let Some(syscall) = self.chains[index].entries.pop_front() {
+            if self.chains[index].failed {
+                complete_canceled(completions, syscall);
+                continue;
+            }
+            let ptr = syscall.data_ptr();
+            let length = syscall.data_size();
+            // syscalls operating on the queue itself are handled here
+            let result = match syscall.syscall_type {
+                AsyncSyscalls::Cancel => {
+                    let target = cancel::cancel_target(ptr, length);
+                    Ok(match self.cancel(target, completions) {
+                        Ok(()) => 0,
+                        Err(error) => ONLY_MSB_OF_USIZE | error as usize,
+                    })
+                }
+                AsyncSyscalls::Timeout => {
+                    Err(timeout::start_timeout(ptr, length, syscall.id, posted))
+                }
+                AsyncSyscalls::LinkTimeout => {
+                    Ok(ONLY_MSB_OF_USIZE | TimeoutError::NotLinked as usize)
+                }
+                _ => run(&syscall),
+            };
+            match result {
+                Ok(value) => {
+                    if let Some((timeout_id, _)) = syscall.link_timeout {
+                        complete(completions, timeout_id, ASYNC_CANCELED);
+                    }
+                    self.finish(index, syscall.id, value, completions);
+                }
+                Err(pending) => {
+                    let chain = &mut self.chains[index];
+                    chain.in_progress = Some(pending);
+                    chain.timeout = syscall.link_timeout.map(|(timeout_id, duration)| {
+                        (timeout_id, ArmTimer {}.get_time() + duration)
+                    });
+                    return;
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, index: usize, id: usize, value: usize, completions: &mut Completions) {
+        if value & ONLY_MSB_OF_USIZE > 0 {
+            self.chains[index].failed = true;
+        }
+        complete(completions, id, value);
+    }
+
+    /// Cancels syscall with given id and syscalls linked after it. Canceled link timeout only
+    /// stops timing its syscall.
+    fn cancel(&mut self, target: usize, completions: &mut Completions) -> Result<(), CancelError> {
+        for index in 0..self.chains.len() {
+            let chain = &mut self.chains[index];
+            if chain
+                .timeout
+                .map_or(false, |(timeout_id, _)| timeout_id == target)
+            {
+                chain.disarm_timeout(completions, ASYNC_CANCELED);
+                return Ok(());
+            }
+            if let Some(syscall) = chain.entries.iter_mut().find(|syscall| {
+                syscall
+                    .link_timeout
+                    .map_or(false, |(timeout_id, _)| timeout_id == target)
+            }) {
+                syscall.link_timeout = None;
+                complete(completions, target, ASYNC_CANCELED);
+                return Ok(());
+            }
+            let running = chain
+                .in_progress
+                .as_ref()
+                .map_or(false, |pending| pending.id() == target);
+            if running {
+                chain.in_progress = None;
+                chain.failed = true;
+                complete(completions, target, ASYNC_CANCELED);
+                chain.disarm_timeout(completions, ASYNC_CANCELED);
+                chain.cancel_entries(completions);
+                return Ok(());
+            }
+            if let Some(position) = chain
+                .entries
+                .iter()
+                .position(|syscall| syscall.id == target)
+            {
+                // syscalls before target still run, so only its part of chain fails
+                chain
+                    .entries
+                    .split_off(position)
+                    .into_iter()
+                    .for_each(|syscall| complete_canceled(completions, syscall));
+                if index == self.chains.len() - 1 && self.finished_tail.is_none() {
+                    self.chains.push(AsyncChain::new(true));
+                }
+                return Ok(());
+            }
+        }
+        Err(CancelError::NotFound)
+    }
+}
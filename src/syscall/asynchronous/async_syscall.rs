@@ -10,10 +10,30 @@ pub enum AsyncSyscalls {
     SeekFile,
     WriteFile,
     CloseFile,
+    CreateFile,
+    DeleteFile,
+    StatFile,
+    SpawnTask,
+    WaitChild,
+    Sleep,
+    Cancel,
+    Timeout,
+    LinkTimeout,
+    ReadFixed,
+    WriteFixed,
+}
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum AsyncSyscallError {
+    /// Submission buffer has no space left, syscall was not submitted
+    SubmissionQueueFull,
 }
 
 pub struct AsyncSyscall<'a> {
     pub id: usize,
+    /// ASYNC_LINK or 0
+    pub flags: usize,
     pub data_size: usize,
     pub syscall_type: AsyncSyscalls,
     pub data: &'a [u8],
@@ -21,6 +41,7 @@ pub struct AsyncSyscall<'a> {
 
 pub struct AsyncSyscallRequest<'a> {
     pub id: usize,
+    pub flags: usize,
     pub syscall_type: AsyncSyscalls,
     pub data: ReturnedValue<'a>,
 }
@@ -32,30 +53,42 @@ pub struct AsyncSyscallReturnedValue {
 
 impl<'a> AsyncSyscallRequest<'a> {
     pub fn get_syscall_data(&self) -> &'a [u8] {
-        &self.data.memory[2 * core::mem::size_of::<usize>()..]
+        &self.data.memory[3 * core::mem::size_of::<usize>()..]
     }
     pub fn get_data_size(&self) -> usize {
-        self.data.get_size() - 2 * core::mem::size_of::<usize>()
+        self.data.get_size() - 3 * core::mem::size_of::<usize>()
     }
 }
 
-pub fn send_async_syscall(buffer: &mut CircullarBuffer, syscall: AsyncSyscall) {
+pub fn send_async_syscall(
+    buffer: &mut CircullarBuffer,
+    syscall: AsyncSyscall,
+) -> Result<(), AsyncSyscallError> {
     let usize_size = core::mem::size_of::<usize>();
+    let was_empty = buffer.is_empty();
     let mut buffer_frame = buffer
-        .reserve(2 * usize_size + syscall.data_size)
-        .expect("Error during sending async syscall");
+        .reserve(3 * usize_size + syscall.data_size)
+        .map_err(|_| AsyncSyscallError::SubmissionQueueFull)?;
     unsafe {
         let mut pointer = &mut *buffer_frame as *mut _ as *mut usize;
         *pointer = syscall.syscall_type as usize;
         pointer = pointer.add(1);
         *pointer = syscall.id;
+        pointer = pointer.add(1);
+        *pointer = syscall.flags;
 
         core::ptr::copy_nonoverlapping(
             syscall.data as *const _ as *const u8,
-            (&mut (*buffer_frame) as *mut _ as *mut u8).add(2 * usize_size),
+            (&mut (*buffer_frame) as *mut _ as *mut u8).add(3 * usize_size),
             syscall.data_size,
         );
     }
+    // worker drains whole buffer once woken, so only first submission rings the doorbell
+    drop(buffer_frame);
+    if was_empty {
+        super::async_worker::async_doorbell();
+    }
+    Ok(())
 }
 
 pub fn read_async_syscall(buffer: &mut CircullarBuffer) -> Option<AsyncSyscallRequest> {
@@ -70,11 +103,13 @@ pub fn read_async_syscall(buffer: &mut CircullarBuffer) -> Option<AsyncSyscallRe
         let syscall_type_usize = *pointer;
         let syscall_type = AsyncSyscalls::from_usize(syscall_type_usize).unwrap();
         let syscall_id = *(pointer.add(1));
+        let flags = *(pointer.add(2));
 
         Some(AsyncSyscallRequest {
             syscall_type,
             data: buffer_entry,
             id: syscall_id,
+            flags,
         })
     }
 }
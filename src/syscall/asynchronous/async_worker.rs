@@ -0,0 +1,75 @@
+use super::handle_async_syscalls::handle_async_syscalls;
+use crate::interupts::{self, ExceptionContext};
+use crate::scheduler;
+use crate::scheduler::task_context::WaitCondition;
+use crate::syscall::*;
+
+/// Submissions of one task read in a round, before worker moves on to the next task
+const ASYNC_BATCH: usize = 16;
+
+/// Kernel task handling async syscalls of all tasks. Tasks are served in rounds, so one that
+/// submits a lot does not hold back the others. Syscalls that would block stay pending in
+/// queue of their task instead of suspending the worker.
+pub extern "C" fn async_worker(_argc: usize, _argv: *const &[u8]) -> u32 {
+    loop {
+        let mut unread = false;
+        for pid in scheduler::tasks_with_async_work() {
+            scheduler::act_for_task(Some(pid));
+            unread |= handle_async_syscalls(ASYNC_BATCH);
+            wake_completion_waiter(pid);
+            scheduler::act_for_task(None);
+        }
+        if unread {
+            continue;
+        }
+        // submissions made between the check and suspension would not wake worker
+        interupts::disable_irqs();
+        if !scheduler::has_async_submissions() {
+            // kernel tasks get no signals, so they are never interrupted
+            scheduler::suspend_current_task(WaitCondition::AsyncWork).ok();
+            // worker could be resumed from scheduler running in IRQ of other task
+            interupts::handlers::end_scheduling();
+        }
+        interupts::enable_irqs();
+    }
+}
+
+/// Resumes task acted for if it waits for completions that are now available
+fn wake_completion_waiter(pid: usize) {
+    let task = unsafe { &*scheduler::get_current_task_context() };
+    let available = task.completion_buffer.value_count();
+    scheduler::wake_task_if(pid, |condition| match condition {
+        WaitCondition::AsyncCompletions { count, .. } => available >= *count,
+        _ => false,
+    });
+}
+
+pub(super) fn wake_async_worker() {
+    scheduler::wake_all_if(|condition| {
+        if let WaitCondition::AsyncWork = condition {
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Called on timer interrupt, lets worker retry waiting syscalls and pick up submissions
+/// made without doorbell
+pub fn wake_async_worker_if_needed() {
+    if scheduler::has_async_work() {
+        wake_async_worker();
+    }
+}
+
+/// Lets async worker know that current task submitted async syscalls
+pub fn async_doorbell() {
+    unsafe {
+        syscall0(Syscalls::AsyncDoorbell as usize);
+    }
+}
+
+pub fn handle_async_doorbell(context: &mut ExceptionContext) {
+    wake_async_worker();
+    context.gpr[0] = 0;
+}
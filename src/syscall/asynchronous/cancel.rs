@@ -0,0 +1,50 @@
+use super::async_syscall::*;
+use crate::utils::circullar_buffer::*;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum CancelError {
+    /// No submitted syscall with given id is waiting or running
+    NotFound,
+}
+
+pub struct AsyncCancelSyscallData {
+    pub target: usize,
+}
+
+impl AsyncCancelSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+/// Cancels async syscall with id target. It completes with ASYNC_CANCELED, together with
+/// syscalls linked after it that did not run yet.
+pub fn cancel(
+    target: usize,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncCancelSyscallData { target };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::Cancel,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn cancel_target(ptr: *const u8, len: usize) -> usize {
+    let data: &AsyncCancelSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+    data.target
+}
@@ -1,7 +1,7 @@
 use super::*;
 use crate::syscall::asynchronous::async_returned_values::*;
 use crate::syscall::asynchronous::async_syscall::*;
-use crate::syscall::files::resolve_fd;
+use crate::syscall::files::is_standard_stream_fd;
 use crate::utils::circullar_buffer::*;
 use crate::vfs;
 
@@ -15,7 +15,12 @@ impl AsyncCloseSyscallData {
     }
 }
 
-pub fn close(afd: &AsyncFileDescriptor, id: usize, submission_buffer: &mut CircullarBuffer) {
+pub fn close(
+    afd: &AsyncFileDescriptor,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
     let data = AsyncCloseSyscallData {
         afd: afd.to_usize(),
     };
@@ -25,11 +30,12 @@ pub fn close(afd: &AsyncFileDescriptor, id: usize, submission_buffer: &mut Circu
     let a: AsyncSyscall = AsyncSyscall {
         data: bytes,
         id,
+        flags,
         data_size: bytes.len(),
         syscall_type: AsyncSyscalls::CloseFile,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
+    send_async_syscall(submission_buffer, a)
 }
 
 pub(in crate::syscall::asynchronous) fn handle_async_close(
@@ -60,23 +66,22 @@ pub(in crate::syscall::asynchronous) fn handle_async_close(
         },
     };
 
-    let fd = resolve_fd(fd);
-
-    if fd < 4 {
+    if fd < 4 && is_standard_stream_fd(fd) {
         return ONLY_MSB_OF_USIZE | vfs::FileError::CannotCloseSpecialFile as usize;
     }
 
     let current_task = crate::scheduler::get_current_task_context();
-    let fd_table = unsafe { &mut (*current_task).file_descriptor_table };
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
 
     if !fd_table.exists(fd) {
         return ONLY_MSB_OF_USIZE | vfs::FileError::AttemptToCloseClosedFile as usize;
     }
 
-    let mut opened_file = fd_table.delete_file(fd).unwrap();
-    let ret = vfs::close(&mut opened_file);
-    if ret.is_err() {
-        return ONLY_MSB_OF_USIZE | ret.err().unwrap() as usize;
+    if let Some(mut opened_file) = fd_table.delete_file(fd) {
+        let ret = vfs::close(&mut opened_file);
+        if ret.is_err() {
+            return ONLY_MSB_OF_USIZE | ret.err().unwrap() as usize;
+        }
     }
     0
 }
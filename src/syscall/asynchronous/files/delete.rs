@@ -0,0 +1,47 @@
+use crate::syscall::asynchronous::async_syscall::*;
+use crate::utils::circullar_buffer::*;
+use crate::vfs;
+
+pub struct AsyncDeleteSyscallData {
+    pub filename: &'static str,
+}
+
+impl AsyncDeleteSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+pub fn delete(
+    filename: &'static str,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncDeleteSyscallData { filename };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::DeleteFile,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_delete(ptr: *const u8, len: usize) -> usize {
+    let data: &AsyncDeleteSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+
+    let filename = crate::syscall::files::resolve_path(data.filename);
+    match vfs::delete_file(&filename) {
+        Ok(()) => 0,
+        Err(e) => super::ONLY_MSB_OF_USIZE | (e as usize),
+    }
+}
@@ -1,8 +1,14 @@
 pub mod close;
+pub mod create;
+pub mod delete;
 pub mod open;
 pub mod read;
 pub mod seek;
+pub mod stat;
 pub mod write;
+use crate::syscall::asynchronous::async_queue::ASYNC_LINK;
+use crate::syscall::asynchronous::async_syscall::AsyncSyscallError;
+use crate::syscall::asynchronous::registered_buffers::RegisteredBuffer;
 use crate::utils::circullar_buffer::CircullarBuffer;
 use crate::vfs;
 
@@ -31,20 +37,36 @@ impl AsyncFileDescriptor {
     }
 }
 
+/// Data of syscalls using part of registered buffer
+pub struct AsyncFixedSyscallData {
+    pub afd: usize,
+    pub buffer_index: usize,
+    pub offset: usize,
+    pub length: usize,
+}
+
+impl AsyncFixedSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
 pub struct AsyncOpenedFile {
     pub afd: AsyncFileDescriptor,
 }
 
+/// Operations of chain are linked, so they are canceled when previous one fails
 impl AsyncOpenedFile {
+    /// Buffer has to stay valid until completion arrives, then_read_fixed ensures that
     pub fn then_read(
         &self,
         length: usize,
         buffer: *mut u8,
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> &AsyncOpenedFile {
-        read::read(&self.afd, length, buffer, id, submission_buffer);
-        self
+    ) -> Result<&AsyncOpenedFile, AsyncSyscallError> {
+        read::read(&self.afd, length, buffer, ASYNC_LINK, id, submission_buffer)?;
+        Ok(self)
     }
     pub fn then_seek(
         &self,
@@ -52,20 +74,69 @@ impl AsyncOpenedFile {
         seek_type: vfs::SeekType,
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> &AsyncOpenedFile {
-        seek::seek(&self.afd, value, seek_type, id, submission_buffer);
-        self
+    ) -> Result<&AsyncOpenedFile, AsyncSyscallError> {
+        seek::seek(
+            &self.afd,
+            value,
+            seek_type,
+            ASYNC_LINK,
+            id,
+            submission_buffer,
+        )?;
+        Ok(self)
     }
     pub fn then_write(
         &self,
         message: &'static [u8],
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> &AsyncOpenedFile {
-        write::write(&self.afd, message, id, submission_buffer);
-        self
+    ) -> Result<&AsyncOpenedFile, AsyncSyscallError> {
+        write::write(&self.afd, message, ASYNC_LINK, id, submission_buffer)?;
+        Ok(self)
+    }
+    pub fn then_read_fixed(
+        &self,
+        buffer: &RegisteredBuffer,
+        offset: usize,
+        length: usize,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<&AsyncOpenedFile, AsyncSyscallError> {
+        read::read_fixed(
+            &self.afd,
+            buffer,
+            offset,
+            length,
+            ASYNC_LINK,
+            id,
+            submission_buffer,
+        )?;
+        Ok(self)
     }
-    pub fn then_close(&self, id: usize, submission_buffer: &mut CircullarBuffer) {
-        close::close(&self.afd, id, submission_buffer);
+    pub fn then_write_fixed(
+        &self,
+        buffer: &RegisteredBuffer,
+        offset: usize,
+        length: usize,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<&AsyncOpenedFile, AsyncSyscallError> {
+        write::write_fixed(
+            &self.afd,
+            buffer,
+            offset,
+            length,
+            ASYNC_LINK,
+            id,
+            submission_buffer,
+        )?;
+        Ok(self)
+    }
+    pub fn then_close(
+        &self,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<(), AsyncSyscallError> {
+        close::close(&self.afd, ASYNC_LINK, id, submission_buffer)
     }
 }
@@ -17,9 +17,10 @@ impl AsyncOpenSyscallData {
 pub fn open(
     filename: &'static str,
     with_write: bool,
+    flags: usize,
     id: usize,
     submission_buffer: &mut CircullarBuffer,
-) -> AsyncOpenedFile {
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
     let data = AsyncOpenSyscallData {
         filename,
         with_write,
@@ -30,14 +31,15 @@ pub fn open(
     let a: AsyncSyscall = AsyncSyscall {
         data: bytes,
         id,
+        flags,
         data_size: bytes.len(),
         syscall_type: AsyncSyscalls::OpenFile,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
-    AsyncOpenedFile {
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile {
         afd: AsyncFileDescriptor::AsyncSyscallReturnValue(id),
-    }
+    })
 }
 
 pub(in crate::syscall::asynchronous) fn handle_async_open(ptr: *const u8, len: usize) -> usize {
@@ -46,13 +48,20 @@ pub(in crate::syscall::asynchronous) fn handle_async_open(ptr: *const u8, len: u
         crate::utils::struct_to_slice::u8_slice_to_any(slice)
     };
 
-    let opened_file_res = vfs::open(data.filename, data.with_write);
+    let filename = crate::syscall::files::resolve_path(data.filename);
+    let opened_file_res = vfs::open(&filename, data.with_write);
 
     let current_task = crate::scheduler::get_current_task_context();
 
     match opened_file_res {
         Err(e) => super::ONLY_MSB_OF_USIZE | (e as usize),
 
-        Ok(opened_file) => unsafe { (*current_task).file_descriptor_table.add_file(opened_file) },
+        Ok(opened_file) => unsafe {
+            (*current_task)
+                .file_descriptor_table
+                .lock()
+                .add_file(opened_file)
+                .unwrap_or_else(|e| super::ONLY_MSB_OF_USIZE | (e as usize))
+        },
     }
 }
@@ -1,6 +1,11 @@
 use super::*;
 use crate::syscall::asynchronous::async_returned_values::*;
 use crate::syscall::asynchronous::async_syscall::*;
+use crate::syscall::asynchronous::pending::PendingAsyncSyscall;
+use crate::syscall::asynchronous::registered_buffers::RegisteredBuffer;
+use crate::syscall::files::file_descriptor_map::FileDescription;
+use crate::syscall::files::get_description;
+use crate::syscall::files::poll::poll_events;
 use crate::syscall::files::read::handle_read;
 use crate::utils::circullar_buffer::*;
 use crate::vfs;
@@ -21,9 +26,10 @@ pub fn read(
     afd: &AsyncFileDescriptor,
     length: usize,
     buffer: *mut u8,
+    flags: usize,
     id: usize,
     submission_buffer: &mut CircullarBuffer,
-) -> AsyncOpenedFile {
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
     let data = AsyncReadSyscallData {
         afd: afd.to_usize(),
         length,
@@ -35,41 +41,142 @@ pub fn read(
     let a: AsyncSyscall = AsyncSyscall {
         data: bytes,
         id,
+        flags,
         data_size: bytes.len(),
         syscall_type: AsyncSyscalls::ReadFile,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
-    AsyncOpenedFile { afd: *afd }
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile { afd: *afd })
 }
 
-pub(in crate::syscall::asynchronous) fn handle_async_read(
-    ptr: *const u8,
-    len: usize,
-    returned_values: &mut AsyncReturnedValues,
-) -> usize {
-    let syscall_data: &AsyncReadSyscallData = unsafe {
-        let slice = core::slice::from_raw_parts(ptr, len);
-        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+/// Reads into part of registered buffer, kernel checks that it fits in the buffer
+pub fn read_fixed(
+    afd: &AsyncFileDescriptor,
+    buffer: &RegisteredBuffer,
+    offset: usize,
+    length: usize,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
+    let data = AsyncFixedSyscallData {
+        afd: afd.to_usize(),
+        buffer_index: buffer.index(),
+        offset,
+        length,
     };
 
-    let fd = match AsyncFileDescriptor::from_usize(syscall_data.afd) {
-        AsyncFileDescriptor::FileDescriptor(val) => val,
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::ReadFixed,
+    };
+
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile { afd: *afd })
+}
+
+/// Stdin and pipes without data would complete read with 0 bytes, so it waits for them instead
+pub(in crate::syscall::asynchronous) fn would_block(fd: usize) -> bool {
+    match get_description(fd) {
+        Some(FileDescription::Stdin)
+        | Some(FileDescription::PipeIn)
+        | Some(FileDescription::PipeReader(_)) => poll_events(fd) == 0,
+        _ => false,
+    }
+}
+
+/// Descriptor that afd refers to, or error returned by syscall that should have opened it
+fn read_fd(afd: usize, returned_values: &AsyncReturnedValues) -> Result<usize, usize> {
+    match AsyncFileDescriptor::from_usize(afd) {
+        AsyncFileDescriptor::FileDescriptor(val) => Ok(val),
         AsyncFileDescriptor::AsyncSyscallReturnValue(val) => match returned_values.map.get(&val) {
-            None => return ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize,
+            None => Err(ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize),
             Some((syscall_type, returned_value)) => {
                 if let AsyncSyscalls::OpenFile = syscall_type {
                     if *returned_value & ONLY_MSB_OF_USIZE > 0 {
-                        return *returned_value;
+                        Err(*returned_value)
                     } else {
-                        *returned_value
+                        Ok(*returned_value)
                     }
                 } else {
-                    return ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize;
+                    Err(ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize)
                 }
             }
         },
+    }
+}
+
+fn read_or_wait(
+    fd: usize,
+    length: usize,
+    buffer: *mut u8,
+    id: usize,
+    registered: Option<usize>,
+) -> Result<usize, PendingAsyncSyscall> {
+    if would_block(fd) {
+        return Err(PendingAsyncSyscall::Read {
+            id,
+            fd,
+            length,
+            buffer,
+            registered,
+        });
+    }
+    Ok(handle_read(fd, length, buffer) as usize)
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_read(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+    returned_values: &mut AsyncReturnedValues,
+) -> Result<usize, PendingAsyncSyscall> {
+    let syscall_data: &AsyncReadSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
     };
 
-    handle_read(fd, syscall_data.length, syscall_data.buffer) as usize
+    match read_fd(syscall_data.afd, returned_values) {
+        Ok(fd) => read_or_wait(fd, syscall_data.length, syscall_data.buffer, id, None),
+        Err(value) => Ok(value),
+    }
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_read_fixed(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+    returned_values: &mut AsyncReturnedValues,
+) -> Result<usize, PendingAsyncSyscall> {
+    let syscall_data: &AsyncFixedSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+
+    let fd = match read_fd(syscall_data.afd, returned_values) {
+        Ok(fd) => fd,
+        Err(value) => return Ok(value),
+    };
+    let current_task = unsafe { &*crate::scheduler::get_current_task_context() };
+    let address = match current_task.registered_buffers.resolve(
+        syscall_data.buffer_index,
+        syscall_data.offset,
+        syscall_data.length,
+    ) {
+        Some(address) => address,
+        None => return Ok(ONLY_MSB_OF_USIZE | vfs::FileError::InvalidBuffer as usize),
+    };
+    read_or_wait(
+        fd,
+        syscall_data.length,
+        address as *mut u8,
+        id,
+        Some(syscall_data.buffer_index),
+    )
 }
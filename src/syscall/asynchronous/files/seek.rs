@@ -1,7 +1,7 @@
 use super::*;
 use crate::syscall::asynchronous::async_returned_values::*;
 use crate::syscall::asynchronous::async_syscall::*;
-use crate::syscall::files::resolve_fd;
+use crate::syscall::files::is_special_fd;
 use crate::syscall::files::seek::vfs_seek_handler;
 use crate::utils::circullar_buffer::*;
 use crate::vfs;
@@ -23,9 +23,10 @@ pub fn seek(
     afd: &AsyncFileDescriptor,
     value: isize,
     seek_type: vfs::SeekType,
+    flags: usize,
     id: usize,
     submission_buffer: &mut CircullarBuffer,
-) -> AsyncOpenedFile {
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
     let data = AsyncSeekSyscallData {
         afd: afd.to_usize(),
         value,
@@ -37,12 +38,13 @@ pub fn seek(
     let a: AsyncSyscall = AsyncSyscall {
         data: bytes,
         id,
+        flags,
         data_size: bytes.len(),
         syscall_type: AsyncSyscalls::SeekFile,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
-    AsyncOpenedFile { afd: *afd }
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile { afd: *afd })
 }
 
 pub(in crate::syscall::asynchronous) fn handle_async_seek(
@@ -73,9 +75,7 @@ pub(in crate::syscall::asynchronous) fn handle_async_seek(
         },
     };
 
-    let fd = resolve_fd(fd);
-
-    if fd < 4 {
+    if is_special_fd(fd) {
         return ONLY_MSB_OF_USIZE | vfs::FileError::CannotSeekSpecialFile as usize;
     }
 
@@ -0,0 +1,53 @@
+use crate::syscall::asynchronous::async_syscall::*;
+use crate::utils::circullar_buffer::*;
+use crate::vfs;
+
+pub struct AsyncStatSyscallData {
+    pub filename: &'static str,
+    pub stat: *mut vfs::FileStat,
+}
+
+impl AsyncStatSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+/// Fills stat with information about file, when completion for id arrives
+pub fn stat(
+    filename: &'static str,
+    stat: *mut vfs::FileStat,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncStatSyscallData { filename, stat };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::StatFile,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_stat(ptr: *const u8, len: usize) -> usize {
+    let data: &AsyncStatSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+
+    let filename = crate::syscall::files::resolve_path(data.filename);
+    match vfs::stat(&filename) {
+        Ok(stat) => {
+            unsafe { *data.stat = stat };
+            0
+        }
+        Err(e) => super::ONLY_MSB_OF_USIZE | (e as usize),
+    }
+}
@@ -1,7 +1,9 @@
 use super::*;
 use crate::syscall::asynchronous::async_returned_values::*;
 use crate::syscall::asynchronous::async_syscall::*;
-use crate::syscall::files::write::handle_write;
+use crate::syscall::asynchronous::pending::PendingAsyncSyscall;
+use crate::syscall::asynchronous::registered_buffers::RegisteredBuffer;
+use crate::syscall::files::write::write_available;
 use crate::utils::circullar_buffer::*;
 use crate::vfs;
 
@@ -19,9 +21,10 @@ impl AsyncWriteSyscallData {
 pub fn write(
     afd: &AsyncFileDescriptor,
     message: &'static [u8],
+    flags: usize,
     id: usize,
     submission_buffer: &mut CircullarBuffer,
-) -> AsyncOpenedFile {
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
     let data = AsyncWriteSyscallData {
         afd: afd.to_usize(),
         message,
@@ -32,41 +35,130 @@ pub fn write(
     let a: AsyncSyscall = AsyncSyscall {
         data: bytes,
         id,
+        flags,
         data_size: bytes.len(),
         syscall_type: AsyncSyscalls::WriteFile,
     };
 
-    crate::syscall::asynchronous::async_syscall::send_async_syscall(submission_buffer, a);
-    AsyncOpenedFile { afd: *afd }
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile { afd: *afd })
 }
 
-pub(in crate::syscall::asynchronous) fn handle_async_write(
-    ptr: *const u8,
-    len: usize,
-    returned_values: &mut AsyncReturnedValues,
-) -> usize {
-    let syscall_data: &AsyncWriteSyscallData = unsafe {
-        let slice = core::slice::from_raw_parts(ptr, len);
-        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+/// Writes part of registered buffer, kernel checks that it fits in the buffer
+pub fn write_fixed(
+    afd: &AsyncFileDescriptor,
+    buffer: &RegisteredBuffer,
+    offset: usize,
+    length: usize,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<AsyncOpenedFile, AsyncSyscallError> {
+    let data = AsyncFixedSyscallData {
+        afd: afd.to_usize(),
+        buffer_index: buffer.index(),
+        offset,
+        length,
     };
 
-    let fd = match AsyncFileDescriptor::from_usize(syscall_data.afd) {
-        AsyncFileDescriptor::FileDescriptor(val) => val,
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::WriteFixed,
+    };
+
+    send_async_syscall(submission_buffer, a)?;
+    Ok(AsyncOpenedFile { afd: *afd })
+}
+
+/// Descriptor that afd refers to, or error returned by syscall that should have opened it
+fn write_fd(afd: usize, returned_values: &AsyncReturnedValues) -> Result<usize, usize> {
+    match AsyncFileDescriptor::from_usize(afd) {
+        AsyncFileDescriptor::FileDescriptor(val) => Ok(val),
         AsyncFileDescriptor::AsyncSyscallReturnValue(val) => match returned_values.map.get(&val) {
-            None => return ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize,
+            None => Err(ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize),
             Some((syscall_type, returned_value)) => {
                 if let AsyncSyscalls::OpenFile = syscall_type {
                     if *returned_value & ONLY_MSB_OF_USIZE > 0 {
-                        return *returned_value;
+                        Err(*returned_value)
                     } else {
-                        *returned_value
+                        Ok(*returned_value)
                     }
                 } else {
-                    return ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize;
+                    Err(ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize)
                 }
             }
         },
+    }
+}
+
+/// Full pipes and pipe queues would suspend the async worker, so the rest of data waits for
+/// space instead
+fn write_or_wait(
+    fd: usize,
+    data: &[u8],
+    id: usize,
+    registered: Option<usize>,
+) -> Result<usize, PendingAsyncSyscall> {
+    match write_available(fd, data) {
+        Ok(written) if written < data.len() => Err(PendingAsyncSyscall::Write {
+            id,
+            fd,
+            length: data.len(),
+            buffer: data.as_ptr(),
+            written,
+            registered,
+        }),
+        Ok(_) => Ok(0),
+        Err(value) => Ok(value as usize),
+    }
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_write(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+    returned_values: &mut AsyncReturnedValues,
+) -> Result<usize, PendingAsyncSyscall> {
+    let syscall_data: &AsyncWriteSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+
+    match write_fd(syscall_data.afd, returned_values) {
+        Ok(fd) => write_or_wait(fd, syscall_data.message, id, None),
+        Err(value) => Ok(value),
+    }
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_write_fixed(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+    returned_values: &mut AsyncReturnedValues,
+) -> Result<usize, PendingAsyncSyscall> {
+    let syscall_data: &AsyncFixedSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
     };
 
-    handle_write(fd, syscall_data.message) as usize
+    let fd = match write_fd(syscall_data.afd, returned_values) {
+        Ok(fd) => fd,
+        Err(value) => return Ok(value),
+    };
+    let current_task = unsafe { &*crate::scheduler::get_current_task_context() };
+    let address = match current_task.registered_buffers.resolve(
+        syscall_data.buffer_index,
+        syscall_data.offset,
+        syscall_data.length,
+    ) {
+        Some(address) => address,
+        None => return Ok(ONLY_MSB_OF_USIZE | vfs::FileError::InvalidBuffer as usize),
+    };
+    let data = unsafe { core::slice::from_raw_parts(address as *const u8, syscall_data.length) };
+    write_or_wait(fd, data, id, Some(syscall_data.buffer_index))
 }
@@ -1,53 +1,85 @@
 use super::*;
 use async_print::*;
+use async_queue::QueuedAsyncSyscall;
+use async_returned_values::AsyncReturnedValues;
 use async_syscall::*;
 use files::*;
+use pending::PendingAsyncSyscall;
 
-pub fn handle_async_syscalls() {
-    let current_task = unsafe { &mut *crate::scheduler::get_current_task_context() };
-    while !current_task.submission_buffer.is_empty() {
-        let syscall_ret_opt = crate::syscall::asynchronous::async_syscall::read_async_syscall(
-            &mut current_task.submission_buffer,
-        );
-        if let Some(syscall_ret) = syscall_ret_opt {
-            //ommit the syscall type value that is at the beginning of the data from buffer
-            let data = syscall_ret.get_syscall_data();
-            let ptr = data as *const _ as *const u8;
-            let length = syscall_ret.get_data_size();
-            let returned_value = match syscall_ret.syscall_type {
-                AsyncSyscalls::Print => handle_async_print(ptr, length),
-                AsyncSyscalls::OpenFile => {
-                    let ret = open::handle_async_open(ptr, length);
-                    current_task
-                        .async_returns_map
-                        .map
-                        .insert(syscall_ret.id, (syscall_ret.syscall_type, ret));
-                    ret
-                }
-                AsyncSyscalls::ReadFile => {
-                    read::handle_async_read(ptr, length, &mut current_task.async_returns_map)
-                }
-                AsyncSyscalls::SeekFile => {
-                    seek::handle_async_seek(ptr, length, &mut current_task.async_returns_map)
-                }
-                AsyncSyscalls::WriteFile => {
-                    write::handle_async_write(ptr, length, &mut current_task.async_returns_map)
-                }
-                AsyncSyscalls::CloseFile => {
-                    current_task.async_returns_map.map.remove(&syscall_ret.id);
-                    close::handle_async_close(ptr, length, &mut current_task.async_returns_map)
-                }
-            };
-
-            let buffer_frame = current_task
-                .completion_buffer
-                .reserve(core::mem::size_of::<AsyncSyscallReturnedValue>())
-                .expect("Error during sending async syscall response");
-            let return_structure: &mut AsyncSyscallReturnedValue =
-                unsafe { crate::utils::struct_to_slice::u8_slice_to_any_mut(buffer_frame.memory) };
-            return_structure.id = syscall_ret.id;
-            return_structure.value = returned_value;
+fn run_async_syscall(
+    syscall: &QueuedAsyncSyscall,
+    async_returns_map: &mut AsyncReturnedValues,
+) -> Result<usize, PendingAsyncSyscall> {
+    let ptr = syscall.data_ptr();
+    let length = syscall.data_size();
+    match syscall.syscall_type {
+        AsyncSyscalls::Print => Ok(handle_async_print(ptr, length)),
+        AsyncSyscalls::OpenFile => {
+            let ret = open::handle_async_open(ptr, length);
+            async_returns_map
+                .map
+                .insert(syscall.id, (AsyncSyscalls::OpenFile, ret));
+            Ok(ret)
+        }
+        AsyncSyscalls::ReadFile => {
+            read::handle_async_read(ptr, length, syscall.id, async_returns_map)
+        }
+        AsyncSyscalls::SeekFile => Ok(seek::handle_async_seek(ptr, length, async_returns_map)),
+        AsyncSyscalls::WriteFile => {
+            write::handle_async_write(ptr, length, syscall.id, async_returns_map)
+        }
+        AsyncSyscalls::ReadFixed => {
+            read::handle_async_read_fixed(ptr, length, syscall.id, async_returns_map)
+        }
+        AsyncSyscalls::WriteFixed => {
+            write::handle_async_write_fixed(ptr, length, syscall.id, async_returns_map)
+        }
+        AsyncSyscalls::CloseFile => {
+            async_returns_map.map.remove(&syscall.id);
+            Ok(close::handle_async_close(ptr, length, async_returns_map))
         }
+        AsyncSyscalls::CreateFile => Ok(create::handle_async_create(ptr, length)),
+        AsyncSyscalls::DeleteFile => Ok(delete::handle_async_delete(ptr, length)),
+        AsyncSyscalls::StatFile => Ok(stat::handle_async_stat(ptr, length)),
+        AsyncSyscalls::SpawnTask => Ok(tasks::handle_async_spawn(ptr, length)),
+        AsyncSyscalls::WaitChild => tasks::handle_async_wait(ptr, length, syscall.id),
+        AsyncSyscalls::Sleep => sleep::handle_async_sleep(ptr, length, syscall.id),
+        AsyncSyscalls::Cancel | AsyncSyscalls::Timeout | AsyncSyscalls::LinkTimeout => {
+            unreachable!("Syscalls operating on async queue are handled by it")
+        }
+    }
+}
+
+/// Reads at most budget submissions of current task and advances its submitted syscalls.
+/// Returns whether submissions were left unread because budget ran out.
+pub fn handle_async_syscalls(budget: usize) -> bool {
+    let current_task = unsafe { &mut *crate::scheduler::get_current_task_context() };
+    // submissions are left in their buffer until task makes room for completions
+    if !current_task
+        .async_queue
+        .flush_overflow(&mut current_task.completion_buffer)
+    {
+        current_task.update_zombie();
+        return false;
+    }
+    let mut read = 0;
+    while read < budget {
+        let request = match read_async_syscall(&mut current_task.submission_buffer) {
+            Some(request) => request,
+            None => break,
+        };
+        let flags = request.flags;
+        current_task
+            .async_queue
+            .submit(QueuedAsyncSyscall::new(request), flags);
+        read += 1;
     }
+    let async_returns_map = &mut current_task.async_returns_map;
+    current_task
+        .async_queue
+        .process(&mut current_task.completion_buffer, |syscall| {
+            run_async_syscall(syscall, async_returns_map)
+        });
     current_task.update_zombie();
+    !current_task.submission_buffer.is_empty()
 }
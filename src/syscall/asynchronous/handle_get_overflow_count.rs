@@ -0,0 +1,9 @@
+use crate::interupts::ExceptionContext;
+
+pub fn handle_get_overflow_count(context: &mut ExceptionContext) {
+    unsafe {
+        context.gpr[0] = (*crate::scheduler::get_current_task_context())
+            .async_queue
+            .overflow_count() as u64;
+    }
+}
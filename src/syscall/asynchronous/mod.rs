@@ -1,7 +1,17 @@
+pub mod async_enter;
 pub mod async_print;
+pub mod async_queue;
 pub mod async_returned_values;
 pub mod async_syscall;
+pub mod async_worker;
+pub mod cancel;
 pub mod files;
 pub mod handle_async_syscalls;
 pub mod handle_get_completion_buffer;
+pub mod handle_get_overflow_count;
 pub mod handle_get_submission_buffer;
+pub mod pending;
+pub mod registered_buffers;
+pub mod sleep;
+pub mod tasks;
+pub mod timeout;
@@ -0,0 +1,136 @@
+use super::files::read::would_block;
+use super::tasks::try_wait;
+use super::timeout::TimeoutError;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::syscall::files::read::handle_read;
+use crate::syscall::files::write::write_available;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use core::time::Duration;
+
+/// Async syscall that could not complete when it was handled, it is checked again each time
+/// async syscalls of its task are handled
+pub enum PendingAsyncSyscall {
+    /// Waits for child with given pid, or any child if None
+    WaitChild {
+        id: usize,
+        pid: Option<usize>,
+        status: *mut u32,
+    },
+    /// Completes when ArmTimer reaches deadline
+    Sleep { id: usize, deadline: Duration },
+    /// Waits for data in stdin or pipe
+    Read {
+        id: usize,
+        fd: usize,
+        length: usize,
+        buffer: *mut u8,
+        /// Index of registered buffer that buffer points to
+        registered: Option<usize>,
+    },
+    /// Waits for space in pipe or pipe queue, written is count of bytes already written
+    Write {
+        id: usize,
+        fd: usize,
+        length: usize,
+        buffer: *const u8,
+        written: usize,
+        /// Index of registered buffer that buffer points to
+        registered: Option<usize>,
+    },
+    /// Fails with Expired at deadline, or completes when count of completions posted by task
+    /// reaches given value
+    Timeout {
+        id: usize,
+        deadline: Duration,
+        completions: Option<usize>,
+    },
+}
+
+impl PendingAsyncSyscall {
+    pub fn id(&self) -> usize {
+        match self {
+            PendingAsyncSyscall::WaitChild { id, .. }
+            | PendingAsyncSyscall::Sleep { id, .. }
+            | PendingAsyncSyscall::Read { id, .. }
+            | PendingAsyncSyscall::Write { id, .. }
+            | PendingAsyncSyscall::Timeout { id, .. } => *id,
+        }
+    }
+
+    /// Whether syscall will access registered buffer with given index
+    pub fn uses_buffer(&self, index: usize) -> bool {
+        match self {
+            PendingAsyncSyscall::Read {
+                registered: Some(registered),
+                ..
+            }
+            | PendingAsyncSyscall::Write {
+                registered: Some(registered),
+                ..
+            } => *registered == index,
+            _ => false,
+        }
+    }
+
+    /// Returned value of syscall, if it can complete now. posted is count of completions
+    /// posted by task so far. Partial writes are recorded in the syscall.
+    pub fn poll(&mut self, posted: usize) -> Option<usize> {
+        match self {
+            PendingAsyncSyscall::WaitChild { pid, status, .. } => try_wait(*pid, *status),
+            PendingAsyncSyscall::Sleep { deadline, .. } => {
+                let timer = ArmTimer {};
+                if timer.get_time() >= *deadline {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            PendingAsyncSyscall::Read {
+                fd, length, buffer, ..
+            } => {
+                if would_block(*fd) {
+                    None
+                } else {
+                    Some(handle_read(*fd, *length, *buffer) as usize)
+                }
+            }
+            PendingAsyncSyscall::Write {
+                fd,
+                length,
+                buffer,
+                written,
+                ..
+            } => {
+                let data = unsafe {
+                    core::slice::from_raw_parts(buffer.add(*written), *length - *written)
+                };
+                match write_available(*fd, data) {
+                    Ok(count) => {
+                        *written += count;
+                        if *written == *length {
+                            Some(0)
+                        } else {
+                            None
+                        }
+                    }
+                    Err(value) => Some(value as usize),
+                }
+            }
+            PendingAsyncSyscall::Timeout {
+                deadline,
+                completions,
+                ..
+            } => {
+                let timer = ArmTimer {};
+                if completions.map_or(false, |completions| posted >= completions) {
+                    Some(0)
+                } else if timer.get_time() >= *deadline {
+                    Some(ONLY_MSB_OF_USIZE | TimeoutError::Expired as usize)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
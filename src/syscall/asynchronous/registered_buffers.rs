@@ -0,0 +1,194 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::collections::BTreeMap;
+use core::ops::Range;
+
+/// How many buffers task can have registered at once
+pub const MAX_REGISTERED_BUFFERS: usize = 32;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum BufferError {
+    /// Buffer is empty or task cannot write to whole of it
+    InvalidRange,
+    /// Task has MAX_REGISTERED_BUFFERS buffers registered
+    TooManyBuffers,
+    /// No buffer is registered with given index
+    InvalidIndex,
+    /// Async worker is handling syscalls of task, unregistering has to be retried
+    InUse,
+}
+
+/// Memory of task that async syscalls can read and write after they were submitted. Indices are
+/// not reused, so syscalls submitted for unregistered buffer fail instead of using a new one.
+/// Registered memory can't be unmapped until it is unregistered.
+pub struct RegisteredBuffers {
+    buffers: BTreeMap<usize, Range<usize>>,
+    next_index: usize,
+}
+
+impl Default for RegisteredBuffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegisteredBuffers {
+    pub fn new() -> Self {
+        RegisteredBuffers {
+            buffers: BTreeMap::new(),
+            next_index: 0,
+        }
+    }
+
+    fn register(&mut self, range: Range<usize>) -> Result<usize, BufferError> {
+        if self.buffers.len() >= MAX_REGISTERED_BUFFERS {
+            return Err(BufferError::TooManyBuffers);
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        self.buffers.insert(index, range);
+        Ok(index)
+    }
+
+    fn unregister(&mut self, index: usize) -> Result<(), BufferError> {
+        self.buffers
+            .remove(&index)
+            .map(|_| ())
+            .ok_or(BufferError::InvalidIndex)
+    }
+
+    /// Some registered buffer overlaps the range
+    pub fn overlaps(&self, range: &Range<usize>) -> bool {
+        self.buffers
+            .values()
+            .any(|buffer| buffer.start < range.end && range.start < buffer.end)
+    }
+
+    /// Address of part of registered buffer, None if it is not registered or too small
+    pub fn resolve(&self, index: usize, offset: usize, length: usize) -> Option<usize> {
+        let range = self.buffers.get(&index)?;
+        match offset.checked_add(length) {
+            Some(end) if end <= range.end - range.start => Some(range.start + offset),
+            _ => None,
+        }
+    }
+}
+
+fn to_result(val: usize) -> Result<usize, BufferError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            BufferError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during buffer registration: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+fn to_return_value(result: Result<usize, BufferError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+/// Buffer registered for async syscalls, only with_registered_buffer gives access to it
+pub struct RegisteredBuffer {
+    index: usize,
+    length: usize,
+}
+
+impl RegisteredBuffer {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+/// Registers buffer for the duration of f, so async syscalls submitted by it can refer to the
+/// buffer by index and offset. Buffer is unregistered before returning, which cancels syscalls
+/// still waiting to use it, so kernel never accesses it after it is borrowed again.
+pub fn with_registered_buffer<R, F>(buffer: &mut [u8], f: F) -> Result<R, BufferError>
+where
+    F: FnOnce(&RegisteredBuffer) -> R,
+{
+    let index = to_result(unsafe {
+        syscall2(
+            buffer.as_mut_ptr() as usize,
+            buffer.len(),
+            Syscalls::RegisterAsyncBuffer as usize,
+        )
+    })?;
+    let registered = RegisteredBuffer {
+        index,
+        length: buffer.len(),
+    };
+    let result = f(&registered);
+    unregister_buffer(index);
+    Ok(result)
+}
+
+/// Waiting reads into buffer are canceled, retries while async worker handles syscalls of task.
+/// Buffers of parent are not registered in forked task, so other errors are ignored.
+fn unregister_buffer(index: usize) {
+    while let Err(BufferError::InUse) =
+        to_result(unsafe { syscall1(index, Syscalls::UnregisterAsyncBuffer as usize) })
+    {
+        yield_cpu();
+    }
+}
+
+fn register_impl(context: &mut ExceptionContext) -> Result<usize, BufferError> {
+    let address = context.gpr[0] as usize;
+    let length = context.gpr[1] as usize;
+    let end = address
+        .checked_add(length)
+        .ok_or(BufferError::InvalidRange)?;
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    if length == 0
+        || !current_task
+            .memory_manager
+            .lock()
+            .is_writable(&(address..end))
+    {
+        return Err(BufferError::InvalidRange);
+    }
+    current_task.registered_buffers.register(address..end)
+}
+
+fn unregister_impl(context: &mut ExceptionContext) -> Result<usize, BufferError> {
+    let index = context.gpr[0] as usize;
+    let pid = scheduler::get_current_task_pid();
+    // worker could be preempted while writing to the buffer
+    if scheduler::is_acted_for(pid) {
+        return Err(BufferError::InUse);
+    }
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    current_task.registered_buffers.unregister(index)?;
+    current_task
+        .async_queue
+        .cancel_buffer_users(index, &mut current_task.completion_buffer);
+    Ok(0)
+}
+
+pub fn handle_register_buffer(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(register_impl(context));
+}
+
+pub fn handle_unregister_buffer(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(unregister_impl(context));
+}
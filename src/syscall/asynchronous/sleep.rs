@@ -0,0 +1,57 @@
+use super::async_syscall::*;
+use super::pending::PendingAsyncSyscall;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::utils::circullar_buffer::*;
+use core::time::Duration;
+
+pub struct AsyncSleepSyscallData {
+    pub duration: Duration,
+}
+
+impl AsyncSleepSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+/// Completes with 0 after given time passes
+pub fn sleep(
+    duration: Duration,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncSleepSyscallData { duration };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::Sleep,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_sleep(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+) -> Result<usize, PendingAsyncSyscall> {
+    let data: &AsyncSleepSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+    if data.duration == Duration::from_secs(0) {
+        return Ok(0);
+    }
+    let timer = ArmTimer {};
+    Err(PendingAsyncSyscall::Sleep {
+        id,
+        deadline: timer.get_time() + data.duration,
+    })
+}
@@ -0,0 +1,133 @@
+use super::async_syscall::*;
+use super::pending::PendingAsyncSyscall;
+use crate::scheduler;
+use crate::scheduler::ChildKind;
+use crate::syscall::wait::WaitError;
+use crate::utils::circullar_buffer::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+const ANY_CHILD: usize = !0usize;
+
+pub struct AsyncSpawnSyscallData {
+    pub function: extern "C" fn(usize, *const &[u8]) -> u32,
+    pub args: &'static [&'static str],
+}
+
+impl AsyncSpawnSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+pub struct AsyncWaitSyscallData {
+    pub pid: usize,
+    pub status: *mut u32,
+}
+
+impl AsyncWaitSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+/// Starts function as child task, completion carries its pid
+pub fn spawn(
+    function: extern "C" fn(usize, *const &[u8]) -> u32,
+    args: &'static [&'static str],
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncSpawnSyscallData { function, args };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::SpawnTask,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+/// Waits for child with given pid (or any child if None) to finish. Completion carries pid
+/// of the child and its return value is stored in status.
+pub fn wait(
+    pid: Option<u64>,
+    status: *mut u32,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncWaitSyscallData {
+        pid: pid.map_or(ANY_CHILD, |pid| pid as usize),
+        status,
+    };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::WaitChild,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_spawn(ptr: *const u8, len: usize) -> usize {
+    let data: &AsyncSpawnSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+    // &str and &[u8] have the same layout
+    let args = unsafe { &*(data.args as *const [&str] as *const [&[u8]]) };
+
+    match scheduler::spawn_task(data.function, args, false, None) {
+        Ok(pid) => pid as usize,
+        Err(e) => ONLY_MSB_OF_USIZE | (e as usize),
+    }
+}
+
+/// Reaps finished child, None if matching children are still running
+pub(in crate::syscall::asynchronous) fn try_wait(
+    pid: Option<usize>,
+    status: *mut u32,
+) -> Option<usize> {
+    match scheduler::reap_child_task(pid, ChildKind::Process) {
+        Ok(Some((child_pid, return_value))) => {
+            if !status.is_null() {
+                unsafe { *status = return_value };
+            }
+            Some(child_pid)
+        }
+        Ok(None) => None,
+        Err(_) => Some(ONLY_MSB_OF_USIZE | WaitError::NoSuchChild as usize),
+    }
+}
+
+pub(in crate::syscall::asynchronous) fn handle_async_wait(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+) -> Result<usize, PendingAsyncSyscall> {
+    let data: &AsyncWaitSyscallData = unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    };
+    let pid = match data.pid {
+        ANY_CHILD => None,
+        pid => Some(pid),
+    };
+
+    try_wait(pid, data.status).ok_or(PendingAsyncSyscall::WaitChild {
+        id,
+        pid,
+        status: data.status,
+    })
+}
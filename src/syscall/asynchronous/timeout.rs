@@ -0,0 +1,107 @@
+use super::async_queue::ASYNC_LINK;
+use super::async_syscall::*;
+use super::pending::PendingAsyncSyscall;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::utils::circullar_buffer::*;
+use core::time::Duration;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum TimeoutError {
+    /// Time passed before awaited event
+    Expired,
+    /// Link timeout was not submitted right after syscall it should time
+    NotLinked,
+}
+
+pub struct AsyncTimeoutSyscallData {
+    pub duration: Duration,
+    pub count: usize,
+}
+
+impl AsyncTimeoutSyscallData {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { crate::utils::struct_to_slice::any_as_u8_slice(self) }
+    }
+}
+
+fn timeout_data<'a>(ptr: *const u8, len: usize) -> &'a AsyncTimeoutSyscallData {
+    unsafe {
+        let slice = core::slice::from_raw_parts(ptr, len);
+        crate::utils::struct_to_slice::u8_slice_to_any(slice)
+    }
+}
+
+/// Fails with TimeoutError::Expired after given time, unless count other completions are posted
+/// earlier, then it completes with 0. Count 0 waits only for time. Unlike sleep, expired timeout
+/// cancels syscalls linked after it.
+pub fn timeout(
+    duration: Duration,
+    count: usize,
+    flags: usize,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncTimeoutSyscallData { duration, count };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::Timeout,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+/// Cancels syscall submitted right before, if it does not complete in given time. Link timeout
+/// fails with TimeoutError::Expired when it cancels the syscall and completes with
+/// ASYNC_CANCELED otherwise.
+pub fn link_timeout(
+    duration: Duration,
+    id: usize,
+    submission_buffer: &mut CircullarBuffer,
+) -> Result<(), AsyncSyscallError> {
+    let data = AsyncTimeoutSyscallData { duration, count: 0 };
+
+    let bytes = data.as_bytes();
+
+    let a: AsyncSyscall = AsyncSyscall {
+        data: bytes,
+        id,
+        flags: ASYNC_LINK,
+        data_size: bytes.len(),
+        syscall_type: AsyncSyscalls::LinkTimeout,
+    };
+
+    send_async_syscall(submission_buffer, a)
+}
+
+pub(in crate::syscall::asynchronous) fn start_timeout(
+    ptr: *const u8,
+    len: usize,
+    id: usize,
+    posted: usize,
+) -> PendingAsyncSyscall {
+    let data = timeout_data(ptr, len);
+    PendingAsyncSyscall::Timeout {
+        id,
+        deadline: ArmTimer {}.get_time() + data.duration,
+        completions: if data.count > 0 {
+            Some(posted + data.count)
+        } else {
+            None
+        },
+    }
+}
+
+pub(in crate::syscall::asynchronous) fn link_timeout_duration(
+    ptr: *const u8,
+    len: usize,
+) -> Duration {
+    timeout_data(ptr, len).duration
+}
@@ -0,0 +1,120 @@
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use core::time::Duration;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy)]
+pub enum Clock {
+    /// Time since board reset, never goes back
+    Monotonic,
+    /// Time since Unix epoch, kept across soft reboots
+    Realtime,
+    /// Time that current task has been running
+    TaskCpuTime,
+    /// Time that reaped children of current task have been running
+    ChildrenCpuTime,
+}
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum ClockError {
+    /// There is no clock with given id
+    InvalidClock,
+    /// Only realtime clock can be set
+    NotSettable,
+}
+
+/// Marks that realtime clock in persistent memory was set, and isn't random content of memory
+const REALTIME_CLOCK_MAGIC: u64 = 0x7572_616e_6f73_5254;
+
+#[repr(C)]
+struct RealtimeClock {
+    magic: u64,
+    /// Realtime in nanoseconds when monotonic clock was 0
+    nanos_at_reset: u64,
+}
+
+#[link_section = ".persistent"]
+static mut REALTIME_CLOCK: RealtimeClock = RealtimeClock {
+    magic: 0,
+    nanos_at_reset: 0,
+};
+
+fn realtime_at_reset() -> Duration {
+    unsafe {
+        let clock = &REALTIME_CLOCK as *const RealtimeClock;
+        if core::ptr::read_volatile(&(*clock).magic) == REALTIME_CLOCK_MAGIC {
+            Duration::from_nanos(core::ptr::read_volatile(&(*clock).nanos_at_reset))
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+}
+
+fn set_realtime_at_reset(time: Duration) {
+    unsafe {
+        let clock = &mut REALTIME_CLOCK as *mut RealtimeClock;
+        core::ptr::write_volatile(&mut (*clock).nanos_at_reset, time.as_nanos() as u64);
+        core::ptr::write_volatile(&mut (*clock).magic, REALTIME_CLOCK_MAGIC);
+    }
+}
+
+fn to_result(val: usize) -> Result<usize, ClockError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            ClockError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during clock access: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Reads time of given clock with nanosecond precision
+pub fn clock_gettime(clock: Clock) -> Result<Duration, ClockError> {
+    let nanos = to_result(unsafe { syscall1(clock as usize, Syscalls::GetTime as usize) })?;
+    Ok(Duration::from_nanos(nanos as u64))
+}
+
+pub fn clock_settime(clock: Clock, time: Duration) -> Result<(), ClockError> {
+    let nanos = time.as_nanos() as usize;
+    to_result(unsafe { syscall2(clock as usize, nanos, Syscalls::SetTime as usize) }).map(|_| ())
+}
+
+fn get_time(clock: Clock) -> Duration {
+    let monotonic = ArmTimer.get_time();
+    match clock {
+        Clock::Monotonic => monotonic,
+        Clock::Realtime => realtime_at_reset() + monotonic,
+        Clock::TaskCpuTime => scheduler::get_current_task_usage().0.cpu_time(),
+        Clock::ChildrenCpuTime => scheduler::get_current_task_usage().1.cpu_time(),
+    }
+}
+
+pub fn handle_get_time(context: &mut ExceptionContext) {
+    context.gpr[0] = match Clock::from_u64(context.gpr[0]) {
+        Some(clock) => get_time(clock).as_nanos() as u64,
+        None => (ONLY_MSB_OF_USIZE | ClockError::InvalidClock as usize) as u64,
+    };
+}
+
+pub fn handle_set_time(context: &mut ExceptionContext) {
+    let time = Duration::from_nanos(context.gpr[1]);
+    context.gpr[0] = match Clock::from_u64(context.gpr[0]) {
+        Some(Clock::Realtime) => {
+            // realtime clock is stored as offset from monotonic one, which starts at reset
+            set_realtime_at_reset(time.checked_sub(ArmTimer.get_time()).unwrap_or_default());
+            0
+        }
+        Some(_) => (ONLY_MSB_OF_USIZE | ClockError::NotSettable as usize) as u64,
+        None => (ONLY_MSB_OF_USIZE | ClockError::InvalidClock as usize) as u64,
+    };
+}
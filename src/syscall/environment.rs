@@ -0,0 +1,159 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::from_utf8;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum EnvironmentError {
+    /// Name is empty, contains `=` or is not valid UTF-8
+    InvalidName,
+    /// Value is not valid UTF-8
+    InvalidValue,
+    /// There is no variable with given name
+    NotFound,
+}
+
+fn to_result(val: usize) -> Result<usize, EnvironmentError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            EnvironmentError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during environment access: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Value of variable from environment of current task
+pub fn getenv(name: &str) -> Option<String> {
+    let mut buffer = Vec::<u8>::new();
+    loop {
+        let length = to_result(unsafe {
+            syscall4(
+                name.as_ptr() as usize,
+                name.len(),
+                buffer.as_mut_ptr() as usize,
+                buffer.len(),
+                Syscalls::GetEnvironmentVariable as usize,
+            )
+        })
+        .ok()?;
+        if length <= buffer.len() {
+            buffer.truncate(length);
+            return String::from_utf8(buffer).ok();
+        }
+        buffer.resize(length, 0);
+    }
+}
+
+/// Sets variable in environment of current task, it is passed to tasks created afterwards
+pub fn setenv(name: &str, value: &str) -> Result<(), EnvironmentError> {
+    to_result(unsafe {
+        syscall4(
+            name.as_ptr() as usize,
+            name.len(),
+            value.as_ptr() as usize,
+            value.len(),
+            Syscalls::SetEnvironmentVariable as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+/// Removes variable from environment of current task
+pub fn unsetenv(name: &str) -> Result<(), EnvironmentError> {
+    to_result(unsafe {
+        syscall2(
+            name.as_ptr() as usize,
+            name.len(),
+            Syscalls::RemoveEnvironmentVariable as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+/// Environment entries (`NAME=value`) placed by kernel on stack of new program right after argv
+///
+/// # Safety
+/// argc and argv have to be the ones that program was started with
+pub unsafe fn initial_environment(argc: usize, argv: *const &[u8]) -> Vec<&'static [u8]> {
+    let mut entries = Vec::new();
+    let mut entry = argv.add(argc);
+    while !(*entry).is_empty() {
+        entries.push(*entry);
+        entry = entry.add(1);
+    }
+    entries
+}
+
+unsafe fn user_str<'a>(pointer: u64, length: u64) -> Option<&'a str> {
+    from_utf8(core::slice::from_raw_parts(
+        pointer as *const u8,
+        length as usize,
+    ))
+    .ok()
+}
+
+fn to_return_value(result: Result<usize, EnvironmentError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+/// Copies as much of value as fits in buffer, returns its whole length
+fn getenv_impl(context: &mut ExceptionContext) -> Result<usize, EnvironmentError> {
+    let name =
+        unsafe { user_str(context.gpr[0], context.gpr[1]) }.ok_or(EnvironmentError::InvalidName)?;
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(context.gpr[2] as *mut u8, context.gpr[3] as usize)
+    };
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let value = current_task
+        .environment
+        .get(name)
+        .ok_or(EnvironmentError::NotFound)?
+        .as_bytes();
+    if value.len() <= buffer.len() {
+        buffer[..value.len()].copy_from_slice(value);
+    }
+    Ok(value.len())
+}
+
+fn setenv_impl(context: &mut ExceptionContext) -> Result<usize, EnvironmentError> {
+    let name =
+        unsafe { user_str(context.gpr[0], context.gpr[1]) }.ok_or(EnvironmentError::InvalidName)?;
+    let value = unsafe { user_str(context.gpr[2], context.gpr[3]) }
+        .ok_or(EnvironmentError::InvalidValue)?;
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    current_task.environment.set(name, value)?;
+    Ok(0)
+}
+
+fn unsetenv_impl(context: &mut ExceptionContext) -> Result<usize, EnvironmentError> {
+    let name =
+        unsafe { user_str(context.gpr[0], context.gpr[1]) }.ok_or(EnvironmentError::InvalidName)?;
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    current_task.environment.remove(name)?;
+    Ok(0)
+}
+
+pub fn handle_getenv(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(getenv_impl(context));
+}
+
+pub fn handle_setenv(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(setenv_impl(context));
+}
+
+pub fn handle_unsetenv(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(unsetenv_impl(context));
+}
@@ -0,0 +1,148 @@
+use crate::interupts::ExceptionContext;
+use crate::memory::memory_controler::{USER_RW_, USER_R_X, USER_R__};
+use crate::scheduler;
+use crate::scheduler::task_memory_manager::TaskMemoryManager;
+use crate::syscall::*;
+use crate::utils::elf::{Elf, ElfError};
+use crate::utils::ONLY_MSB_OF_USIZE;
+use crate::vfs;
+use alloc::vec::Vec;
+
+const PAGE_SIZE: usize = 4096;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum ExecError {
+    /// There is no file with given name in VFS
+    FileNotFound,
+    /// File is opened for write and can't be loaded
+    FileBusy,
+    /// File is not ELF
+    NotExecutable,
+    /// ELF is not static AArch64 executable
+    UnsupportedExecutable,
+    /// ELF headers point outside of the file
+    CorruptedExecutable,
+    /// Segment lies outside of task address range or overlaps other segment
+    InvalidSegment,
+    /// Segment is both writable and executable
+    WritableExecutableSegment,
+    /// Arguments does not fit on task stack
+    ArgumentsTooLong,
+    /// Only user tasks can replace their image
+    NotUserTask,
+}
+
+impl From<ElfError> for ExecError {
+    fn from(error: ElfError) -> Self {
+        match error {
+            ElfError::NotElf => ExecError::NotExecutable,
+            ElfError::Unsupported => ExecError::UnsupportedExecutable,
+            ElfError::Truncated => ExecError::CorruptedExecutable,
+        }
+    }
+}
+
+/// Replaces current task with static ELF executable loaded from file.
+/// Program starts at its entry point with argc in x0 and argv (array of byte slices) in x1,
+/// it has to end with FinishTask syscall. Returns only on failure.
+pub fn exec(filename: &str, args: &[&str]) -> ExecError {
+    let val = unsafe {
+        syscall4(
+            filename.as_ptr() as usize,
+            filename.len(),
+            args as *const [&str] as *const () as usize,
+            args.len(),
+            Syscalls::Exec as usize,
+        )
+    };
+    ExecError::from_usize(val & !ONLY_MSB_OF_USIZE)
+        .unwrap_or_else(|| panic!("Unknown error during exec: {}", val & !ONLY_MSB_OF_USIZE))
+}
+
+fn load_executable(data: &[u8]) -> Result<(TaskMemoryManager, usize), ExecError> {
+    let elf = Elf::parse(data)?;
+    let mut memory_manager = TaskMemoryManager::empty();
+
+    for (index, segment) in elf.segments()?.iter().enumerate() {
+        let attributes = match (segment.is_writable(), segment.is_executable()) {
+            (true, true) => return Err(ExecError::WritableExecutableSegment),
+            (true, false) => USER_RW_,
+            (false, true) => USER_R_X,
+            (false, false) => USER_R__,
+        };
+        let page_offset = segment.virtual_address % PAGE_SIZE;
+        let start = segment.virtual_address - page_offset;
+        let end = segment
+            .virtual_address
+            .checked_add(segment.memory_size)
+            .and_then(|end| end.checked_add(PAGE_SIZE - 1))
+            .ok_or(ExecError::InvalidSegment)?
+            & !(PAGE_SIZE - 1);
+
+        let mut content = Vec::with_capacity(page_offset + segment.data.len());
+        content.resize(page_offset, 0);
+        content.extend_from_slice(segment.data);
+
+        memory_manager
+            .map_new_memory(
+                &format!("Segment {}", index),
+                start..end,
+                &content,
+                attributes,
+            )
+            .map_err(|_| ExecError::InvalidSegment)?;
+    }
+    Ok((memory_manager, elf.entry))
+}
+
+fn exec_impl(context: &mut ExceptionContext) -> Result<(), ExecError> {
+    let filename = unsafe {
+        let bytes =
+            core::slice::from_raw_parts(context.gpr[0] as *const u8, context.gpr[1] as usize);
+        core::str::from_utf8_unchecked(bytes)
+    };
+    let args: &[&[u8]] = unsafe {
+        core::slice::from_raw_parts(context.gpr[2] as *const &[u8], context.gpr[3] as usize)
+    };
+
+    let task = unsafe { &mut *scheduler::get_current_task_context() };
+    if task.is_kernel() {
+        return Err(ExecError::NotUserTask);
+    }
+
+    let filename = crate::syscall::files::resolve_path(filename);
+    let data = vfs::read_whole_file(&filename).map_err(|error| match error {
+        vfs::FileError::FileDoesNotExist => ExecError::FileNotFound,
+        _ => ExecError::FileBusy,
+    })?;
+    let (memory_manager, entry) = load_executable(&data)?;
+
+    // args live in memory of the image that is being replaced
+    let args: Vec<Vec<u8>> = args.iter().map(|arg| arg.to_vec()).collect();
+    let args: Vec<&[u8]> = args.iter().map(|arg| &arg[..]).collect();
+
+    let stack_pointer = task
+        .replace_image(memory_manager, &args)
+        .map_err(|_| ExecError::ArgumentsTooLong)?;
+
+    for mut file in task.file_descriptor_table.lock().close_on_exec() {
+        vfs::close(&mut file).ok();
+    }
+
+    context.gpr = [0; 30];
+    context.gpr[0] = args.len() as u64;
+    context.gpr[1] = stack_pointer as u64;
+    context.lr = 0;
+    context.sp_el0 = stack_pointer as u64;
+    context.elr_el1 = entry as u64;
+    // fresh EL0 state with all interrupts unmasked
+    context.spsr_el1 = 0;
+    Ok(())
+}
+
+pub fn handle_exec(context: &mut ExceptionContext) {
+    if let Err(error) = exec_impl(context) {
+        context.gpr[0] = (ONLY_MSB_OF_USIZE | error as usize) as u64;
+    }
+}
@@ -1,4 +1,4 @@
-use super::resolve_fd;
+use super::is_standard_stream_fd;
 use crate::interupts::ExceptionContext;
 use crate::syscall::*;
 use crate::vfs;
@@ -26,9 +26,10 @@ pub fn close(fd: usize) -> Result<(), vfs::FileError> {
 }
 
 pub fn handle_close(context: &mut ExceptionContext) {
-    let fd = resolve_fd(context.gpr[0] as usize);
+    let fd = context.gpr[0] as usize;
 
-    if fd < 4 {
+    // standard descriptors can be closed only if they were redirected to VFS file
+    if fd < 4 && is_standard_stream_fd(fd) {
         context.gpr[0] =
             (ONLY_MSB_OF_USIZE | vfs::FileError::CannotCloseSpecialFile as usize) as u64;
         return;
@@ -36,7 +37,7 @@ pub fn handle_close(context: &mut ExceptionContext) {
 
     unsafe {
         let current_task = crate::scheduler::get_current_task_context();
-        let fd_table = &mut (*current_task).file_descriptor_table;
+        let mut fd_table = (*current_task).file_descriptor_table.lock();
 
         if !fd_table.exists(fd) {
             context.gpr[0] =
@@ -44,11 +45,13 @@ pub fn handle_close(context: &mut ExceptionContext) {
             return;
         }
 
-        let mut opened_file = fd_table.delete_file(fd).unwrap();
-        let ret = vfs::close(&mut opened_file);
-        if ret.is_err() {
-            context.gpr[0] = (ONLY_MSB_OF_USIZE | ret.err().unwrap() as usize) as u64;
-            return;
+        // file stays opened while other descriptors (e.g. of forked task) reference it
+        if let Some(mut opened_file) = fd_table.delete_file(fd) {
+            let ret = vfs::close(&mut opened_file);
+            if ret.is_err() {
+                context.gpr[0] = (ONLY_MSB_OF_USIZE | ret.err().unwrap() as usize) as u64;
+                return;
+            }
         }
         context.gpr[0] = 0;
     }
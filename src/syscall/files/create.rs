@@ -50,7 +50,7 @@ pub fn handle_create(context: &mut ExceptionContext) {
     }
     let filename = string.unwrap();
 
-    let val = vfs::create_file(&filename);
+    let val = vfs::create_file(&super::resolve_path(filename));
 
     if val.is_err() {
         context.gpr[0] = (ONLY_MSB_OF_USIZE | val.err().unwrap() as usize) as u64;
@@ -0,0 +1,86 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use crate::vfs;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::from_utf8;
+use num_traits::FromPrimitive;
+
+/// Changes directory that relative paths of current task are resolved against.
+/// Directory exists if any file lies in it.
+pub fn chdir(path: &str) -> Result<(), vfs::FileError> {
+    let val = unsafe {
+        syscall2(
+            path.as_ptr() as usize,
+            path.len(),
+            Syscalls::ChangeDirectory as usize,
+        )
+    };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            vfs::FileError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during directory change: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(())
+    }
+}
+
+/// Absolute path of current directory of current task
+pub fn getcwd() -> String {
+    let mut buffer = Vec::<u8>::new();
+    loop {
+        let length = unsafe {
+            syscall2(
+                buffer.as_mut_ptr() as usize,
+                buffer.len(),
+                Syscalls::GetCurrentDirectory as usize,
+            )
+        };
+        if length <= buffer.len() {
+            buffer.truncate(length);
+            return String::from_utf8(buffer).expect("Current directory is not valid UTF-8");
+        }
+        buffer.resize(length, 0);
+    }
+}
+
+pub fn handle_chdir(context: &mut ExceptionContext) {
+    let path = unsafe {
+        core::slice::from_raw_parts(context.gpr[0] as *const u8, context.gpr[1] as usize)
+    };
+    let path = match from_utf8(path) {
+        Ok(path) => path,
+        Err(_) => {
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | vfs::FileError::FileDoesNotExist as usize) as u64;
+            return;
+        }
+    };
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    let directory = vfs::join_path(&current_task.current_directory, path);
+    if !vfs::directory_exists(&directory) {
+        context.gpr[0] = (ONLY_MSB_OF_USIZE | vfs::FileError::FileDoesNotExist as usize) as u64;
+        return;
+    }
+    current_task.current_directory = directory;
+    context.gpr[0] = 0;
+}
+
+/// Copies as much of current directory as fits in buffer, returns its whole length
+pub fn handle_getcwd(context: &mut ExceptionContext) {
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(context.gpr[0] as *mut u8, context.gpr[1] as usize)
+    };
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let directory = current_task.current_directory.as_bytes();
+    if directory.len() <= buffer.len() {
+        buffer[..directory.len()].copy_from_slice(directory);
+    }
+    context.gpr[0] = directory.len() as u64;
+}
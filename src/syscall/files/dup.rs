@@ -0,0 +1,83 @@
+use crate::interupts::ExceptionContext;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use crate::vfs;
+use num_traits::FromPrimitive;
+
+fn to_result(val: usize) -> Result<usize, vfs::FileError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            vfs::FileError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during descriptor duplication: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Creates new descriptor with lowest free number, referring to the same object as fd
+pub fn dup(fd: usize) -> Result<usize, vfs::FileError> {
+    to_result(unsafe { syscall1(fd, Syscalls::DuplicateFd as usize) })
+}
+
+/// Makes new_fd refer to the same object as fd, file previously referred by new_fd is closed
+pub fn dup2(fd: usize, new_fd: usize) -> Result<usize, vfs::FileError> {
+    to_result(unsafe { syscall2(fd, new_fd, Syscalls::DuplicateFdTo as usize) })
+}
+
+/// Marks descriptor to be closed when task replaces its program with exec
+pub fn set_close_on_exec(fd: usize, close_on_exec: bool) -> Result<(), vfs::FileError> {
+    to_result(unsafe {
+        syscall2(
+            fd,
+            close_on_exec as usize,
+            Syscalls::SetCloseOnExec as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+pub fn handle_dup(context: &mut ExceptionContext) {
+    let fd = context.gpr[0] as usize;
+    let current_task = crate::scheduler::get_current_task_context();
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
+
+    context.gpr[0] = match fd_table.duplicate(fd) {
+        Ok(new_fd) => new_fd as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    };
+}
+
+pub fn handle_dup2(context: &mut ExceptionContext) {
+    let fd = context.gpr[0] as usize;
+    let new_fd = context.gpr[1] as usize;
+    let current_task = crate::scheduler::get_current_task_context();
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
+
+    context.gpr[0] = match fd_table.duplicate_to(fd, new_fd) {
+        Ok(replaced_file) => {
+            if let Some(mut replaced_file) = replaced_file {
+                vfs::close(&mut replaced_file).ok();
+            }
+            new_fd as u64
+        }
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    };
+}
+
+pub fn handle_set_close_on_exec(context: &mut ExceptionContext) {
+    let fd = context.gpr[0] as usize;
+    let close_on_exec = context.gpr[1] != 0;
+    let current_task = crate::scheduler::get_current_task_context();
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
+
+    context.gpr[0] = if fd_table.set_close_on_exec(fd, close_on_exec) {
+        0
+    } else {
+        (ONLY_MSB_OF_USIZE | vfs::FileError::BadFileDescriptor as usize) as u64
+    };
+}
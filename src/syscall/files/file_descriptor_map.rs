@@ -1,9 +1,73 @@
+use crate::scheduler::resource_limits::RLIMIT_INFINITY;
+use crate::sync::nulllock::{NullLock, NullLockGuard};
+use crate::syscall::ports::SharedPort;
 use crate::vfs::*;
 use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use super::pipe::{PipeReadEnd, PipeWriteEnd};
+use super::{PIPEIN, PIPEOUT, STDIN, STDOUT};
+
+/// Opened file shared by descriptors of forked tasks, they share also cursor position
+pub type SharedFile = Arc<NullLock<OpenedFile>>;
+
+/// Descriptor table used by all threads of a task
+pub type SharedFileDescriptorMap = Arc<NullLock<FileDescriptiorMap>>;
+
+/// Object that file descriptor refers to
+#[derive(Clone)]
+pub enum FileDescription {
+    /// UART input
+    Stdin,
+    /// UART output
+    Stdout,
+    /// Pipe queue of task set with SetPipeReadOnPID
+    PipeIn,
+    /// Pipe queue of the task itself
+    PipeOut,
+    /// File opened in VFS
+    File(SharedFile),
+    /// Read end of pipe created with pipe syscall
+    PipeReader(Arc<PipeReadEnd>),
+    /// Write end of pipe created with pipe syscall
+    PipeWriter(Arc<PipeWriteEnd>),
+    /// Message queue created with CreatePort syscall
+    Port(SharedPort),
+}
+
+impl FileDescription {
+    /// Everything except VFS files, which can't be seeked
+    pub fn is_special(&self) -> bool {
+        if let FileDescription::File(_) = self {
+            false
+        } else {
+            true
+        }
+    }
+
+    /// UART and task pipe queue streams, that tasks get on standard descriptors
+    pub fn is_standard_stream(&self) -> bool {
+        match self {
+            FileDescription::Stdin
+            | FileDescription::Stdout
+            | FileDescription::PipeIn
+            | FileDescription::PipeOut => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FileDescriptor {
+    description: FileDescription,
+    close_on_exec: bool,
+}
 
 pub struct FileDescriptiorMap {
-    map: BTreeMap<usize, OpenedFile>,
-    next_fd: usize,
+    map: BTreeMap<usize, FileDescriptor>,
+    /// Count of descriptors above which new ones are refused, soft OpenFiles limit of task
+    descriptor_limit: u64,
 }
 
 impl Default for FileDescriptiorMap {
@@ -12,37 +76,187 @@ impl Default for FileDescriptiorMap {
     }
 }
 
+/// Returns file if given descriptor was its last reference
+fn last_reference(descriptor: FileDescriptor) -> Option<OpenedFile> {
+    match descriptor.description {
+        FileDescription::File(file) => Arc::try_unwrap(file).ok().map(NullLock::into_inner),
+        _ => None,
+    }
+}
+
+/// Closes file of description that is not referenced by any descriptor
+pub fn release_description(description: FileDescription) {
+    if let Some(mut file) = last_reference(FileDescriptor {
+        description,
+        close_on_exec: false,
+    }) {
+        close(&mut file).ok();
+    }
+}
+
 impl FileDescriptiorMap {
+    /// Table with special descriptors on their standard numbers
     pub fn new() -> Self {
-        FileDescriptiorMap {
+        let mut table = FileDescriptiorMap {
             map: BTreeMap::new(),
-            next_fd: 4,
+            descriptor_limit: RLIMIT_INFINITY,
+        };
+        table.set_description(STDIN, FileDescription::Stdin);
+        table.set_description(STDOUT, FileDescription::Stdout);
+        table.set_description(PIPEIN, FileDescription::PipeIn);
+        table.set_description(PIPEOUT, FileDescription::PipeOut);
+        table
+    }
+
+    /// Copy of the table for forked task, referencing the same opened files
+    pub fn fork(&self) -> Self {
+        FileDescriptiorMap {
+            map: self.map.clone(),
+            descriptor_limit: self.descriptor_limit,
+        }
+    }
+
+    /// Copy of the table for task starting new program, without close on exec descriptors
+    pub fn inherit(&self) -> Self {
+        FileDescriptiorMap {
+            map: self
+                .map
+                .iter()
+                .filter(|(_, descriptor)| !descriptor.close_on_exec)
+                .map(|(fd, descriptor)| (*fd, descriptor.clone()))
+                .collect(),
+            descriptor_limit: self.descriptor_limit,
         }
     }
 
-    pub fn add_file(&mut self, file: OpenedFile) -> usize {
-        let ret = self.next_fd;
-        self.map.insert(ret, file);
-        self.next_fd = match self.map.keys().max() {
-            Some(val) => val + 1,
-            None => 4,
+    /// Removes close on exec descriptors, returns files that are no longer referenced
+    pub fn close_on_exec(&mut self) -> Vec<OpenedFile> {
+        let closed: Vec<usize> = self
+            .map
+            .iter()
+            .filter(|(_, descriptor)| descriptor.close_on_exec)
+            .map(|(fd, _)| *fd)
+            .collect();
+        closed
+            .into_iter()
+            .filter_map(|fd| self.delete_file(fd))
+            .collect()
+    }
+
+    /// Removes all descriptors, returns files that are no longer referenced
+    pub fn close_all(&mut self) -> Vec<OpenedFile> {
+        let map = core::mem::replace(&mut self.map, BTreeMap::new());
+        map.into_iter()
+            .filter_map(|(_, descriptor)| last_reference(descriptor))
+            .collect()
+    }
+
+    fn lowest_free_fd(&self) -> usize {
+        (0..).find(|fd| !self.map.contains_key(fd)).unwrap()
+    }
+
+    pub fn set_descriptor_limit(&mut self, limit: u64) {
+        self.descriptor_limit = limit;
+    }
+
+    fn is_full(&self) -> bool {
+        self.map.len() as u64 >= self.descriptor_limit
+    }
+
+    /// Places file on lowest free descriptor, file is closed if descriptor limit is reached
+    pub fn add_file(&mut self, mut file: OpenedFile) -> Result<usize, FileError> {
+        if self.is_full() {
+            close(&mut file).ok();
+            return Err(FileError::TooManyOpenFiles);
+        }
+        self.add_description(FileDescription::File(Arc::new(NullLock::new(file))))
+    }
+
+    /// Places description on lowest free descriptor
+    pub fn add_description(&mut self, description: FileDescription) -> Result<usize, FileError> {
+        if self.is_full() {
+            return Err(FileError::TooManyOpenFiles);
+        }
+        let fd = self.lowest_free_fd();
+        self.set_description(fd, description);
+        Ok(fd)
+    }
+
+    /// Points descriptor to given object, returns file that was referenced only by replaced
+    /// descriptor
+    pub fn set_description(
+        &mut self,
+        fd: usize,
+        description: FileDescription,
+    ) -> Option<OpenedFile> {
+        let descriptor = FileDescriptor {
+            description,
+            close_on_exec: false,
         };
-        ret
+        self.map.insert(fd, descriptor).and_then(last_reference)
     }
 
-    pub fn get_file_mut(&mut self, fd: usize) -> Option<&mut OpenedFile> {
-        self.map.get_mut(&fd)
+    pub fn get_description(&self, fd: usize) -> Option<FileDescription> {
+        self.map
+            .get(&fd)
+            .map(|descriptor| descriptor.description.clone())
     }
 
-    pub fn get_file(&mut self, fd: usize) -> Option<&OpenedFile> {
-        self.map.get(&fd)
+    pub fn get_file_mut(&mut self, fd: usize) -> Option<NullLockGuard<OpenedFile>> {
+        self.get_file(fd)
+    }
+
+    pub fn get_file(&mut self, fd: usize) -> Option<NullLockGuard<OpenedFile>> {
+        match self.map.get(&fd).map(|descriptor| &descriptor.description) {
+            Some(FileDescription::File(file)) => Some(file.lock()),
+            _ => None,
+        }
     }
 
     pub fn exists(&mut self, fd: usize) -> bool {
         self.map.get(&fd).is_some()
     }
 
+    /// Copies descriptor to lowest free number
+    pub fn duplicate(&mut self, fd: usize) -> Result<usize, FileError> {
+        let description = self
+            .get_description(fd)
+            .ok_or(FileError::BadFileDescriptor)?;
+        self.add_description(description)
+    }
+
+    /// Makes new_fd refer to the same object as fd. Returns file that was referenced only by
+    /// replaced descriptor.
+    pub fn duplicate_to(
+        &mut self,
+        fd: usize,
+        new_fd: usize,
+    ) -> Result<Option<OpenedFile>, FileError> {
+        let description = self
+            .get_description(fd)
+            .ok_or(FileError::BadFileDescriptor)?;
+        if fd == new_fd {
+            return Ok(None);
+        }
+        if !self.map.contains_key(&new_fd) && self.is_full() {
+            return Err(FileError::TooManyOpenFiles);
+        }
+        Ok(self.set_description(new_fd, description))
+    }
+
+    /// Returns false if descriptor does not exist
+    pub fn set_close_on_exec(&mut self, fd: usize, close_on_exec: bool) -> bool {
+        match self.map.get_mut(&fd) {
+            Some(descriptor) => {
+                descriptor.close_on_exec = close_on_exec;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes descriptor, returns opened file if no other descriptor references it
     pub fn delete_file(&mut self, fd: usize) -> Option<OpenedFile> {
-        self.map.remove(&fd)
+        self.map.remove(&fd).and_then(last_reference)
     }
 }
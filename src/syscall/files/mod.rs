@@ -1,13 +1,18 @@
 pub mod close;
 pub mod create;
 pub mod delete;
+pub mod directory;
+pub mod dup;
 pub mod file_descriptor_map;
 pub mod open;
+pub mod pipe;
+pub mod poll;
 pub mod read;
 pub mod seek;
 pub mod write;
 
-pub const PIPE_QUEUE_GRANULATION: usize = 64;
+/// Size of buffer of each pipe, writers are suspended when it is full
+pub const PIPE_CAPACITY: usize = 4096;
 
 pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
@@ -16,11 +21,14 @@ pub const PIPEOUT: usize = 3;
 
 use crate::interupts::ExceptionContext;
 use crate::scheduler;
-use crate::scheduler::task_context::TaskContext;
 use crate::syscall::asynchronous;
+use crate::syscall::asynchronous::async_syscall::AsyncSyscallError;
 use crate::syscall::asynchronous::files::{AsyncFileDescriptor, AsyncOpenedFile};
+use crate::syscall::asynchronous::registered_buffers::RegisteredBuffer;
 use crate::utils::circullar_buffer::CircullarBuffer;
 use crate::vfs::{FileError, SeekType};
+use alloc::string::String;
+use file_descriptor_map::FileDescription;
 
 pub fn handle_set_pipe_read_on_pid(e: &mut ExceptionContext) {
     let pid = e.gpr[0] as usize;
@@ -30,19 +38,49 @@ pub fn handle_set_pipe_read_on_pid(e: &mut ExceptionContext) {
     }
 }
 
-pub fn resolve_fd(fd: usize) -> usize {
-    let current_task: &mut TaskContext = unsafe { &mut *(scheduler::get_current_task_context()) };
-    match current_task.mapped_fds.get(&fd) {
-        Some(mapped_fd) => *mapped_fd,
-        None => fd,
+/// Object that descriptor of current task refers to
+pub fn get_description(fd: usize) -> Option<FileDescription> {
+    let current_task = scheduler::get_current_task_context();
+    unsafe {
+        (*current_task)
+            .file_descriptor_table
+            .lock()
+            .get_description(fd)
     }
 }
 
+/// Name of VFS file for path, which is relative to current directory of current task
+/// unless it starts with `/`
+pub fn resolve_path(path: &str) -> String {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let path = crate::vfs::join_path(&current_task.current_directory, path);
+    String::from(crate::vfs::file_name(&path))
+}
+
+/// Checks if descriptor of current task refers to UART or pipe instead of VFS file
+pub fn is_special_fd(fd: usize) -> bool {
+    get_description(fd).map_or(false, |description| description.is_special())
+}
+
+/// Checks if descriptor of current task refers to UART or task pipe queue
+pub fn is_standard_stream_fd(fd: usize) -> bool {
+    get_description(fd).map_or(false, |description| description.is_standard_stream())
+}
+
 pub struct File {
     fd: usize,
 }
 
 impl File {
+    /// Creates pipe, returns its (read end, write end)
+    pub fn pipe() -> Result<(Self, Self), FileError> {
+        let (read_fd, write_fd) = pipe::pipe()?;
+        Ok((File { fd: read_fd }, File { fd: write_fd }))
+    }
+    /// Size of buffer of pipe this descriptor refers to
+    pub fn pipe_capacity(&self) -> Result<usize, FileError> {
+        pipe::pipe_capacity(self.fd)
+    }
     pub fn open(filename: &str, with_write: bool) -> Result<Self, FileError> {
         let fd = open::open(filename, with_write)?;
         Ok(File { fd })
@@ -52,8 +90,8 @@ impl File {
         with_write: bool,
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> AsyncOpenedFile {
-        asynchronous::files::open::open(filename, with_write, id, submission_buffer)
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
+        asynchronous::files::open::open(filename, with_write, 0, id, submission_buffer)
     }
     pub fn read(&self, length: usize, buffer: &mut [u8]) -> Result<usize, FileError> {
         read::read(self.fd, length, buffer as *mut [u8] as *mut u8)
@@ -64,11 +102,12 @@ impl File {
         buffer: &mut [u8],
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> AsyncOpenedFile {
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
         asynchronous::files::read::read(
             &AsyncFileDescriptor::FileDescriptor(self.fd),
             length,
             buffer as *mut [u8] as *mut u8,
+            0,
             id,
             submission_buffer,
         )
@@ -81,10 +120,47 @@ impl File {
         message: &'static [u8],
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> AsyncOpenedFile {
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
         asynchronous::files::write::write(
             &AsyncFileDescriptor::FileDescriptor(self.fd),
             message,
+            0,
+            id,
+            submission_buffer,
+        )
+    }
+    pub fn async_read_fixed(
+        &self,
+        buffer: &RegisteredBuffer,
+        offset: usize,
+        length: usize,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
+        asynchronous::files::read::read_fixed(
+            &AsyncFileDescriptor::FileDescriptor(self.fd),
+            buffer,
+            offset,
+            length,
+            0,
+            id,
+            submission_buffer,
+        )
+    }
+    pub fn async_write_fixed(
+        &self,
+        buffer: &RegisteredBuffer,
+        offset: usize,
+        length: usize,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
+        asynchronous::files::write::write_fixed(
+            &AsyncFileDescriptor::FileDescriptor(self.fd),
+            buffer,
+            offset,
+            length,
+            0,
             id,
             submission_buffer,
         )
@@ -98,11 +174,12 @@ impl File {
         seek_type: SeekType,
         id: usize,
         submission_buffer: &mut CircullarBuffer,
-    ) -> AsyncOpenedFile {
+    ) -> Result<AsyncOpenedFile, AsyncSyscallError> {
         asynchronous::files::seek::seek(
             &AsyncFileDescriptor::FileDescriptor(self.fd),
             value,
             seek_type,
+            0,
             id,
             submission_buffer,
         )
@@ -110,9 +187,14 @@ impl File {
     pub fn close(&self) -> Result<(), FileError> {
         close::close(self.fd)
     }
-    pub fn async_close(&self, id: usize, submission_buffer: &mut CircullarBuffer) {
+    pub fn async_close(
+        &self,
+        id: usize,
+        submission_buffer: &mut CircullarBuffer,
+    ) -> Result<(), AsyncSyscallError> {
         asynchronous::files::close::close(
             &AsyncFileDescriptor::FileDescriptor(self.fd),
+            0,
             id,
             submission_buffer,
         )
@@ -120,6 +202,19 @@ impl File {
     pub fn get_fd(&self) -> usize {
         self.fd
     }
+    /// New descriptor referring to the same file, it shares cursor with this one
+    pub fn duplicate(&self) -> Result<Self, FileError> {
+        let fd = dup::dup(self.fd)?;
+        Ok(File { fd })
+    }
+    /// Makes given descriptor refer to this file, e.g. to redirect STDOUT
+    pub fn redirect(&self, fd: usize) -> Result<(), FileError> {
+        dup::dup2(self.fd, fd).map(|_| ())
+    }
+    /// Closes descriptor when task replaces its program with exec
+    pub fn set_close_on_exec(&self, close_on_exec: bool) -> Result<(), FileError> {
+        dup::set_close_on_exec(self.fd, close_on_exec)
+    }
 
     pub fn get_stdin() -> Self {
         File { fd: STDIN }
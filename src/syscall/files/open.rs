@@ -52,7 +52,7 @@ pub fn handle_open(context: &mut ExceptionContext) {
     }
     let filename = string.unwrap();
 
-    let opened_file = vfs::open(&filename, with_write);
+    let opened_file = vfs::open(&super::resolve_path(filename), with_write);
 
     if opened_file.is_err() {
         context.gpr[0] = (ONLY_MSB_OF_USIZE | opened_file.err().unwrap() as usize) as u64;
@@ -63,7 +63,14 @@ pub fn handle_open(context: &mut ExceptionContext) {
 
     let current_task = crate::scheduler::get_current_task_context();
 
-    unsafe {
-        context.gpr[0] = (*current_task).file_descriptor_table.add_file(opened_file) as u64;
-    }
+    let fd = unsafe {
+        (*current_task)
+            .file_descriptor_table
+            .lock()
+            .add_file(opened_file)
+    };
+    context.gpr[0] = match fd {
+        Ok(fd) => fd as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    };
 }
@@ -0,0 +1,235 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::signals::Signal;
+use crate::scheduler::task_context::WaitCondition;
+use crate::sync::nulllock::NullLock;
+use crate::syscall::*;
+use crate::utils::ring_buffer::RingBuffer;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use crate::vfs;
+use alloc::sync::Arc;
+use num_traits::FromPrimitive;
+
+use super::file_descriptor_map::FileDescription;
+use super::get_description;
+use super::poll::{PollObject, POLL_ERROR, POLL_HANG_UP, POLL_IN, POLL_OUT};
+use super::PIPE_CAPACITY;
+
+/// Kernel buffer connecting write ends of pipe with its read ends
+pub struct Pipe {
+    buffer: RingBuffer,
+    write_end_closed: bool,
+    read_end_closed: bool,
+}
+
+type SharedPipe = Arc<NullLock<Pipe>>;
+
+pub(super) fn pipe_id(pipe: &SharedPipe) -> usize {
+    Arc::as_ptr(pipe) as usize
+}
+
+fn wake_waiting_tasks(pipe: &SharedPipe) {
+    let id = pipe_id(pipe);
+    scheduler::wake_all_if(|condition| match condition {
+        WaitCondition::Pipe(pipe) => *pipe == id,
+        _ => false,
+    });
+    scheduler::wake_pollers(PollObject::Pipe(id));
+}
+
+/// Read end of pipe, shared by all descriptors referring to it.
+/// Pipe is closed for readers when last descriptor is closed.
+pub struct PipeReadEnd {
+    pipe: SharedPipe,
+}
+
+/// Write end of pipe, shared by all descriptors referring to it.
+/// Readers get end of file when last descriptor is closed.
+pub struct PipeWriteEnd {
+    pipe: SharedPipe,
+}
+
+impl Drop for PipeReadEnd {
+    fn drop(&mut self) {
+        self.pipe.lock().read_end_closed = true;
+        wake_waiting_tasks(&self.pipe);
+    }
+}
+
+impl Drop for PipeWriteEnd {
+    fn drop(&mut self) {
+        self.pipe.lock().write_end_closed = true;
+        wake_waiting_tasks(&self.pipe);
+    }
+}
+
+impl PipeReadEnd {
+    /// Suspends task until pipe has data, returns count of read bytes.
+    /// Fails with ReadOnClosedFile when pipe is empty and all write ends are closed.
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize, vfs::FileError> {
+        loop {
+            {
+                let mut pipe = self.pipe.lock();
+                if !pipe.buffer.is_empty() {
+                    let count = pipe.buffer.pop_slice(buffer);
+                    drop(pipe);
+                    scheduler::account_pipe_read(count);
+                    // writers may wait for free space
+                    wake_waiting_tasks(&self.pipe);
+                    return Ok(count);
+                }
+                if pipe.write_end_closed {
+                    return Err(vfs::FileError::ReadOnClosedFile);
+                }
+            }
+            scheduler::suspend_current_task(WaitCondition::Pipe(pipe_id(&self.pipe)))
+                .map_err(|_| vfs::FileError::Interrupted)?;
+        }
+    }
+
+    /// Poll events that are currently signaled for this end
+    pub fn poll_events(&self) -> u32 {
+        let pipe = self.pipe.lock();
+        let mut events = 0;
+        if !pipe.buffer.is_empty() {
+            events |= POLL_IN;
+        }
+        if pipe.write_end_closed {
+            events |= POLL_HANG_UP;
+        }
+        events
+    }
+}
+
+impl PipeWriteEnd {
+    /// Appends data to pipe, suspends task while pipe is full. Fails with BrokenPipe and
+    /// raises SIGPIPE in current task when all read ends are closed.
+    pub fn write(&self, data: &[u8]) -> Result<(), vfs::FileError> {
+        let mut written = 0;
+        loop {
+            written += self.write_available(&data[written..])?;
+            if written == data.len() {
+                return Ok(());
+            }
+            scheduler::suspend_current_task(WaitCondition::Pipe(pipe_id(&self.pipe)))
+                .map_err(|_| vfs::FileError::Interrupted)?;
+        }
+    }
+
+    /// Appends as much of data as fits in pipe without suspending task, returns count of
+    /// appended bytes. Fails like write when all read ends are closed.
+    pub fn write_available(&self, data: &[u8]) -> Result<usize, vfs::FileError> {
+        let count = {
+            let mut pipe = self.pipe.lock();
+            if pipe.read_end_closed {
+                drop(pipe);
+                let pid = scheduler::get_current_task_pid();
+                scheduler::send_signal(pid, Signal::BrokenPipe).ok();
+                return Err(vfs::FileError::BrokenPipe);
+            }
+            pipe.buffer.push_slice(data)
+        };
+        scheduler::account_pipe_write(count);
+        // readers may wait for data
+        wake_waiting_tasks(&self.pipe);
+        Ok(count)
+    }
+
+    /// Poll events that are currently signaled for this end
+    pub fn poll_events(&self) -> u32 {
+        let pipe = self.pipe.lock();
+        let mut events = 0;
+        if !pipe.buffer.is_full() {
+            events |= POLL_OUT;
+        }
+        if pipe.read_end_closed {
+            events |= POLL_ERROR;
+        }
+        events
+    }
+}
+
+/// Creates pipe, returns pair of descriptors (read end, write end)
+pub fn pipe() -> Result<(usize, usize), vfs::FileError> {
+    let mut fds = [0usize; 2];
+    let val = unsafe { syscall1(fds.as_mut_ptr() as usize, Syscalls::CreatePipe as usize) };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            vfs::FileError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during pipe creation: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok((fds[0], fds[1]))
+    }
+}
+
+pub fn handle_pipe(context: &mut ExceptionContext) {
+    let fds = context.gpr[0] as *mut [usize; 2];
+
+    let pipe = Arc::new(NullLock::new(Pipe {
+        buffer: RingBuffer::new(PIPE_CAPACITY),
+        write_end_closed: false,
+        read_end_closed: false,
+    }));
+    let read_end = FileDescription::PipeReader(Arc::new(PipeReadEnd { pipe: pipe.clone() }));
+    let write_end = FileDescription::PipeWriter(Arc::new(PipeWriteEnd { pipe }));
+
+    let current_task = scheduler::get_current_task_context();
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
+    let read_fd = match fd_table.add_description(read_end) {
+        Ok(fd) => fd,
+        Err(error) => {
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | error as usize) as u64;
+            return;
+        }
+    };
+    let write_fd = match fd_table.add_description(write_end) {
+        Ok(fd) => fd,
+        Err(error) => {
+            fd_table.delete_file(read_fd);
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | error as usize) as u64;
+            return;
+        }
+    };
+    unsafe { *fds = [read_fd, write_fd] };
+    context.gpr[0] = 0;
+}
+
+/// Size of buffer of pipe that descriptor refers to, writers block when it is full
+pub fn pipe_capacity(fd: usize) -> Result<usize, vfs::FileError> {
+    let val = unsafe { syscall1(fd, Syscalls::PipeCapacity as usize) };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            vfs::FileError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during pipe capacity query: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+pub fn handle_pipe_capacity(context: &mut ExceptionContext) {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let capacity = match get_description(context.gpr[0] as usize) {
+        Some(FileDescription::PipeReader(end)) => Some(end.pipe.lock().buffer.capacity()),
+        Some(FileDescription::PipeWriter(end)) => Some(end.pipe.lock().buffer.capacity()),
+        Some(FileDescription::PipeOut) => Some(current_task.pipe_queue_capacity()),
+        Some(FileDescription::PipeIn) => current_task
+            .pipe_from
+            .and_then(|pid| scheduler::get_task_context(pid).ok())
+            .map(|writer| unsafe { (*writer).pipe_queue_capacity() }),
+        _ => None,
+    };
+    context.gpr[0] = match capacity {
+        Some(capacity) => capacity as u64,
+        None => (ONLY_MSB_OF_USIZE | vfs::FileError::BadFileDescriptor as usize) as u64,
+    };
+}
@@ -0,0 +1,186 @@
+use super::file_descriptor_map::FileDescription;
+use super::get_description;
+use super::pipe;
+use crate::drivers::arm_timer::ArmTimer;
+use crate::drivers::traits::time::Timer;
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::*;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use crate::vfs;
+use core::time::Duration;
+use num_traits::FromPrimitive;
+
+/// Descriptor has data to read
+pub const POLL_IN: u32 = 1 << 0;
+/// Descriptor can be written to without suspending the task
+pub const POLL_OUT: u32 = 1 << 1;
+/// Writing end of the pipe was closed, reported even if not requested
+pub const POLL_HANG_UP: u32 = 1 << 2;
+/// Reading end of the pipe was closed, reported even if not requested
+pub const POLL_ERROR: u32 = 1 << 3;
+/// Descriptor is not opened, reported even if not requested
+pub const POLL_INVALID: u32 = 1 << 4;
+
+/// Kernel object whose change of state can make polled descriptors ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollObject {
+    Stdin,
+    /// Pipe queue of task with given pid
+    PipeQueue(usize),
+    /// Pipe with given id
+    Pipe(usize),
+    /// Port with given id
+    Port(usize),
+}
+
+const ALWAYS_REPORTED: u32 = POLL_HANG_UP | POLL_ERROR | POLL_INVALID;
+
+/// Timeout value passed to kernel when poll should wait without limit
+const NO_TIMEOUT: usize = usize::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+/// Descriptor polled for events, kernel fills returned_events
+pub struct PollFd {
+    pub fd: usize,
+    pub events: u32,
+    pub returned_events: u32,
+}
+
+impl PollFd {
+    pub fn new(fd: usize, events: u32) -> Self {
+        PollFd {
+            fd,
+            events,
+            returned_events: 0,
+        }
+    }
+}
+
+/// Suspends task until any of descriptors is ready for requested events or timeout passes.
+/// Returns count of descriptors with any returned events, 0 on timeout. Fails with Interrupted
+/// when signal handler has to run.
+pub fn poll(fds: &mut [PollFd], timeout: Option<Duration>) -> Result<usize, vfs::FileError> {
+    let timeout = timeout.map_or(NO_TIMEOUT, |timeout| timeout.as_millis() as usize);
+    let val = unsafe {
+        syscall3(
+            fds.as_mut_ptr() as usize,
+            fds.len(),
+            timeout,
+            Syscalls::Poll as usize,
+        )
+    };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(vfs::FileError::from_usize(val & !ONLY_MSB_OF_USIZE)
+            .unwrap_or_else(|| panic!("Unknown error during poll: {}", val & !ONLY_MSB_OF_USIZE)))
+    } else {
+        Ok(val)
+    }
+}
+
+/// Events that are currently signaled for descriptor of current task
+pub(crate) fn poll_events(fd: usize) -> u32 {
+    match get_description(fd) {
+        Some(FileDescription::Stdin) => {
+            if crate::io::INPUT_BUFFER.lock().is_empty() {
+                0
+            } else {
+                POLL_IN
+            }
+        }
+        Some(FileDescription::Stdout) => POLL_OUT,
+        // VFS files never suspend their readers and writers
+        Some(FileDescription::File(_)) => POLL_IN | POLL_OUT,
+        Some(FileDescription::PipeIn) => pipe_queue_reader_events(),
+        Some(FileDescription::PipeOut) => {
+            let current_task = unsafe { &*scheduler::get_current_task_context() };
+            if current_task.is_pipe_reader_closed() {
+                POLL_ERROR
+            } else if current_task.is_pipe_queue_full() {
+                0
+            } else {
+                POLL_OUT
+            }
+        }
+        Some(FileDescription::PipeReader(pipe)) => pipe.poll_events(),
+        Some(FileDescription::PipeWriter(pipe)) => pipe.poll_events(),
+        Some(FileDescription::Port(port)) => ports::poll_events(&port),
+        None => POLL_INVALID,
+    }
+}
+
+/// Object that descriptor of current task waits on, None if it never suspends poll
+fn poll_object(fd: usize) -> Option<PollObject> {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    match get_description(fd) {
+        Some(FileDescription::Stdin) => Some(PollObject::Stdin),
+        Some(FileDescription::PipeIn) => current_task.pipe_from.map(PollObject::PipeQueue),
+        Some(FileDescription::PipeOut) => {
+            Some(PollObject::PipeQueue(scheduler::get_current_task_pid()))
+        }
+        Some(FileDescription::PipeReader(pipe)) | Some(FileDescription::PipeWriter(pipe)) => {
+            Some(PollObject::Pipe(pipe::pipe_id(&pipe)))
+        }
+        Some(FileDescription::Port(port)) => Some(PollObject::Port(ports::port_id(&port))),
+        _ => None,
+    }
+}
+
+fn pipe_queue_reader_events() -> u32 {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let writer = match current_task
+        .pipe_from
+        .and_then(|pid| scheduler::get_task_context(pid).ok())
+    {
+        Some(writer) => unsafe { &*writer },
+        None => return POLL_HANG_UP,
+    };
+    match (writer.get_state(), writer.is_pipe_queue_empty()) {
+        (TaskStates::Dead, _) => POLL_HANG_UP,
+        (TaskStates::Zombie, true) => POLL_HANG_UP,
+        (TaskStates::Zombie, false) => POLL_IN | POLL_HANG_UP,
+        (_, true) => 0,
+        (_, false) => POLL_IN,
+    }
+}
+
+pub fn handle_poll(context: &mut ExceptionContext) {
+    let fds = context.gpr[0] as *mut PollFd;
+    let count = context.gpr[1] as usize;
+    let timeout = context.gpr[2] as usize;
+
+    let deadline = match timeout {
+        NO_TIMEOUT => None,
+        timeout => Some(ArmTimer {}.get_time() + Duration::from_millis(timeout as u64)),
+    };
+
+    let mut interrupted = false;
+    loop {
+        let fds = unsafe { core::slice::from_raw_parts_mut(fds, count) };
+        let mut ready = 0;
+        for poll_fd in fds.iter_mut() {
+            let events = poll_events(poll_fd.fd);
+            poll_fd.returned_events = events & (poll_fd.events | ALWAYS_REPORTED);
+            if poll_fd.returned_events != 0 {
+                ready += 1;
+            }
+        }
+        let timed_out = deadline.map_or(false, |deadline| ArmTimer {}.get_time() >= deadline);
+        if ready > 0 || timed_out {
+            context.gpr[0] = ready as u64;
+            return;
+        }
+        if interrupted {
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | vfs::FileError::Interrupted as usize) as u64;
+            return;
+        }
+        let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+        current_task.polled = fds
+            .iter()
+            .filter_map(|poll_fd| poll_object(poll_fd.fd))
+            .collect();
+        interrupted = scheduler::suspend_current_task(WaitCondition::Poll(deadline)).is_err();
+    }
+}
@@ -1,4 +1,6 @@
-use super::resolve_fd;
+use super::file_descriptor_map::FileDescription;
+use super::get_description;
+use super::poll::PollObject;
 use crate::interupts::ExceptionContext;
 use crate::scheduler;
 use crate::scheduler::task_context::*;
@@ -26,7 +28,7 @@ pub fn read(fd: usize, length: usize, buffer: *mut u8) -> Result<usize, vfs::Fil
     }
 }
 
-pub fn read_from_pipe_handler(length: usize, mut buffer: *mut u8) -> u64 {
+pub fn read_from_pipe_handler(length: usize, buffer: *mut u8) -> u64 {
     let current_task = crate::scheduler::get_current_task_context();
     if let Some(pid) = unsafe { (*current_task).pipe_from } {
         if let Ok(task_ptr) = scheduler::get_task_context(pid) {
@@ -39,37 +41,21 @@ pub fn read_from_pipe_handler(length: usize, mut buffer: *mut u8) -> u64 {
                     return (ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize) as u64;
                 }
             }
-            let mut data_to_go = length;
-            loop {
-                if data_to_go > 0 {
-                    if let Some(element) = task.get_item_from_pipe_queue() {
-                        if element.len() <= data_to_go {
-                            unsafe {
-                                core::ptr::copy_nonoverlapping(
-                                    &element[..] as *const [u8] as *const u8,
-                                    buffer,
-                                    element.len(),
-                                );
-                                buffer = buffer.add(element.len());
-                                data_to_go -= element.len()
-                            }
-                        } else {
-                            unsafe {
-                                core::ptr::copy_nonoverlapping(
-                                    &element[..] as *const [u8] as *const u8,
-                                    buffer,
-                                    data_to_go,
-                                );
-                                task.push_front_item_to_pipe_queue(element[data_to_go..].to_vec());
-                            }
-                        }
+            let buffer = unsafe { core::slice::from_raw_parts_mut(buffer, length) };
+            let count = task.read_from_pipe_queue(buffer);
+            scheduler::account_pipe_read(count);
+            if count > 0 {
+                // writer may wait for free space in its queue
+                scheduler::wake_task_if(pid, |condition| {
+                    if let WaitCondition::PipeQueue = condition {
+                        true
                     } else {
-                        return (length - data_to_go) as u64;
+                        false
                     }
-                } else {
-                    return length as u64;
-                }
+                });
+                scheduler::wake_pollers(PollObject::PipeQueue(pid));
             }
+            return count as u64;
         }
     }
     return (ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize) as u64;
@@ -77,13 +63,13 @@ pub fn read_from_pipe_handler(length: usize, mut buffer: *mut u8) -> u64 {
 
 pub fn read_from_vfs_handler(fd: usize, length: usize, buffer: *mut u8) -> u64 {
     let current_task = crate::scheduler::get_current_task_context();
-    let fd_table = unsafe { &mut (*current_task).file_descriptor_table };
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
 
     if !fd_table.exists(fd) {
         return (ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize) as u64;
     }
-    let opened_file = fd_table.get_file_mut(fd).unwrap();
-    match vfs::read(opened_file, length) {
+    let mut opened_file = fd_table.get_file_mut(fd).unwrap();
+    match vfs::read(&mut opened_file, length) {
         Ok(data) => {
             unsafe {
                 core::ptr::copy_nonoverlapping(data.data, buffer, data.len);
@@ -109,28 +95,32 @@ pub fn read_from_stdin_handler(length: usize, buffer: *mut u8) -> u64 {
 }
 
 pub fn handle_read_syscall(context: &mut ExceptionContext) {
-    let fd = resolve_fd(context.gpr[0] as usize);
+    let fd = context.gpr[0] as usize;
     let length = context.gpr[1] as usize;
-    let mut buffer = context.gpr[2] as *mut u8;
-    // Special file descriptors:
-    // 0: STDIN (UART)
-    // 1: STDOUT (UART)
-    // 2: PIPEIN
-    // 3: PIPEOUT
+    let buffer = context.gpr[2] as *mut u8;
     context.gpr[0] = handle_read(fd, length, buffer);
 }
 
 pub fn handle_read(fd: usize, length: usize, buffer: *mut u8) -> u64 {
-    // Special file descriptors:
-    // 0: STDIN (UART)
-    // 1: STDOUT (UART)
-    // 2: PIPEIN
-    // 3: PIPEOUT
-    match resolve_fd(fd) {
-        0 => read_from_stdin_handler(length, buffer) as u64,
-        1 => (ONLY_MSB_OF_USIZE | vfs::FileError::CannotReadWriteOnlyFile as usize) as u64,
-        2 => read_from_pipe_handler(length, buffer),
-        3 => (ONLY_MSB_OF_USIZE | vfs::FileError::CannotReadWriteOnlyFile as usize) as u64,
-        _ => read_from_vfs_handler(fd, length, buffer),
+    // Descriptors 0 - 3 initially refer to STDIN (UART), STDOUT (UART), PIPEIN and PIPEOUT,
+    // but any descriptor can be redirected with dup2
+    match get_description(fd) {
+        Some(FileDescription::Stdin) => read_from_stdin_handler(length, buffer) as u64,
+        Some(FileDescription::Stdout) | Some(FileDescription::PipeOut) => {
+            (ONLY_MSB_OF_USIZE | vfs::FileError::CannotReadWriteOnlyFile as usize) as u64
+        }
+        Some(FileDescription::PipeIn) => read_from_pipe_handler(length, buffer),
+        Some(FileDescription::File(_)) => read_from_vfs_handler(fd, length, buffer),
+        Some(FileDescription::PipeReader(pipe)) => {
+            let buffer = unsafe { core::slice::from_raw_parts_mut(buffer, length) };
+            match pipe.read(buffer) {
+                Ok(count) => count as u64,
+                Err(err) => (ONLY_MSB_OF_USIZE | err as usize) as u64,
+            }
+        }
+        Some(FileDescription::PipeWriter(_)) | Some(FileDescription::Port(_)) => {
+            (ONLY_MSB_OF_USIZE | vfs::FileError::CannotReadWriteOnlyFile as usize) as u64
+        }
+        None => (ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize) as u64,
     }
 }
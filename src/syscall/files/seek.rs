@@ -1,4 +1,4 @@
-use super::resolve_fd;
+use super::is_special_fd;
 use crate::interupts::ExceptionContext;
 use crate::syscall::*;
 use crate::vfs;
@@ -32,25 +32,25 @@ pub fn seek(fd: usize, value: isize, seek_type: vfs::SeekType) -> Result<usize,
 
 pub fn vfs_seek_handler(fd: usize, difference: isize, seek_type: vfs::SeekType) -> u64 {
     let current_task = crate::scheduler::get_current_task_context();
-    let fd_table = unsafe { &mut (*current_task).file_descriptor_table };
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
 
     if !fd_table.exists(fd) {
         return (ONLY_MSB_OF_USIZE | vfs::FileError::ReadOnClosedFile as usize) as u64;
     }
-    let opened_file = fd_table.get_file_mut(fd).unwrap();
-    match vfs::seek(opened_file, difference, seek_type) {
+    let mut opened_file = fd_table.get_file_mut(fd).unwrap();
+    match vfs::seek(&mut opened_file, difference, seek_type) {
         Ok(val) => val as u64,
         Err(err) => (ONLY_MSB_OF_USIZE | err as usize) as u64,
     }
 }
 
 pub fn handle_seek(context: &mut ExceptionContext) {
-    let fd = resolve_fd(context.gpr[0] as usize);
+    let fd = context.gpr[0] as usize;
     let difference = context.gpr[1] as isize;
     let seek_type = vfs::SeekType::from_u64(context.gpr[2])
         .unwrap_or_else(|| panic!("Wrong type of SeekType sent: {}", context.gpr[2]));
 
-    if fd < 4 {
+    if is_special_fd(fd) {
         context.gpr[0] =
             (ONLY_MSB_OF_USIZE | vfs::FileError::CannotSeekSpecialFile as usize) as u64;
         return;
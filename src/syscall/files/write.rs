@@ -1,11 +1,14 @@
-use super::resolve_fd;
+use super::file_descriptor_map::FileDescription;
+use super::get_description;
+use super::poll::PollObject;
 use crate::interupts::ExceptionContext;
 use crate::syscall::*;
 use crate::vfs;
 use core::slice;
 use num_traits::FromPrimitive;
 
-use super::PIPE_QUEUE_GRANULATION;
+use crate::scheduler;
+use crate::scheduler::signals::Signal;
 use crate::scheduler::task_context::*;
 use crate::utils::ONLY_MSB_OF_USIZE;
 
@@ -34,41 +37,68 @@ pub fn write(fd: usize, bytes: &[u8]) -> Result<(), vfs::FileError> {
     }
 }
 
+/// Writes data to pipe queue of current task, suspends it while the queue is full
 pub fn pipe_write_handler(data: &[u8]) -> u64 {
-    let current_task = crate::scheduler::get_current_task_context();
-    let current_task: &mut TaskContext = unsafe { &mut (*current_task) };
-    let mut data_to_go = data.len();
-    let mut data_sent = 0;
+    let mut written = 0;
     loop {
-        if data_to_go > 0 {
-            if data_to_go >= PIPE_QUEUE_GRANULATION {
-                current_task.push_back_item_to_pipe_queue(
-                    data[data_sent..(data_sent + PIPE_QUEUE_GRANULATION)].to_vec(),
-                );
-                data_sent += PIPE_QUEUE_GRANULATION;
-                data_to_go -= PIPE_QUEUE_GRANULATION;
-            } else {
-                current_task.push_back_item_to_pipe_queue(data[data_sent..].to_vec());
-                return 0;
-            }
-        } else {
+        match pipe_queue_write_available(&data[written..]) {
+            Ok(count) => written += count,
+            Err(value) => return value,
+        }
+        if written == data.len() {
             return 0;
         }
+        if scheduler::suspend_current_task(WaitCondition::PipeQueue).is_err() {
+            return (ONLY_MSB_OF_USIZE | vfs::FileError::Interrupted as usize) as u64;
+        }
+    }
+}
+
+/// Writes as much of data as fits in pipe queue of current task, returns count of written
+/// bytes, or returned value of failed write when the queue is full and its reader is closed
+fn pipe_queue_write_available(data: &[u8]) -> Result<usize, u64> {
+    let current_task: &mut TaskContext = unsafe { &mut *scheduler::get_current_task_context() };
+    let count = current_task.write_to_pipe_queue(data);
+    scheduler::account_pipe_write(count);
+    // readers may wait in poll for data
+    scheduler::wake_pollers(PollObject::PipeQueue(scheduler::get_current_task_pid()));
+    if count < data.len() && current_task.is_pipe_reader_closed() {
+        scheduler::send_signal(scheduler::get_current_task_pid(), Signal::BrokenPipe).ok();
+        return Err((ONLY_MSB_OF_USIZE | vfs::FileError::BrokenPipe as usize) as u64);
+    }
+    Ok(count)
+}
+
+/// Writes as much of data as fits without suspending task, returns count of written bytes, or
+/// returned value of failed write. Only pipes and pipe queues can take less than all of data.
+pub fn write_available(fd: usize, data: &[u8]) -> Result<usize, u64> {
+    match get_description(fd) {
+        Some(FileDescription::PipeOut) => pipe_queue_write_available(data),
+        Some(FileDescription::PipeWriter(pipe)) => pipe
+            .write_available(data)
+            .map_err(|err| (ONLY_MSB_OF_USIZE | err as usize) as u64),
+        _ => match handle_write(fd, data) {
+            0 => Ok(data.len()),
+            value => Err(value),
+        },
     }
 }
 
 pub fn vfs_write_handler(data: &[u8], fd: usize) -> u64 {
     let current_task = crate::scheduler::get_current_task_context();
-    let fd_table = unsafe { &mut (*current_task).file_descriptor_table };
-    let opened_file = fd_table.get_file_mut(fd).unwrap();
-    match vfs::write(opened_file, data) {
+    let mut fd_table = unsafe { (*current_task).file_descriptor_table.lock() };
+    let opened_file = match fd_table.get_file_mut(fd) {
+        Some(opened_file) => opened_file,
+        None => return (ONLY_MSB_OF_USIZE | vfs::FileError::BadFileDescriptor as usize) as u64,
+    };
+    match vfs::write(&opened_file, data) {
         Ok(_) => 0,
         Err(err) => (ONLY_MSB_OF_USIZE | err as usize) as u64,
     }
 }
 
 pub fn handle_write_syscall(context: &mut ExceptionContext) {
-    let fd = resolve_fd(context.gpr[0] as usize);
+    let fd = context.gpr[0] as usize;
     let ptr = context.gpr[1] as *const u8;
     let len = context.gpr[2] as usize;
 
@@ -78,20 +108,26 @@ pub fn handle_write_syscall(context: &mut ExceptionContext) {
 }
 
 pub fn handle_write(fd: usize, data: &[u8]) -> u64 {
-    // Special file descriptors:
-    // 0: STDIN (UART)
-    // 1: STDOUT (UART)
-    // 2: PIPEIN
-    // 3: PIPEOUT
-    match resolve_fd(fd) {
-        0 => (ONLY_MSB_OF_USIZE | vfs::FileError::ModifyingWithoutWritePermission as usize) as u64,
-        1 => {
+    // Descriptors 0 - 3 initially refer to STDIN (UART), STDOUT (UART), PIPEIN and PIPEOUT,
+    // but any descriptor can be redirected with dup2
+    match get_description(fd) {
+        Some(FileDescription::Stdin)
+        | Some(FileDescription::PipeIn)
+        | Some(FileDescription::PipeReader(_))
+        | Some(FileDescription::Port(_)) => {
+            (ONLY_MSB_OF_USIZE | vfs::FileError::ModifyingWithoutWritePermission as usize) as u64
+        }
+        Some(FileDescription::Stdout) => {
             let string = unsafe { core::str::from_utf8_unchecked(data) };
             crate::print!("{}", string);
             0
         }
-        2 => (ONLY_MSB_OF_USIZE | vfs::FileError::ModifyingWithoutWritePermission as usize) as u64,
-        3 => pipe_write_handler(data),
-        _ => vfs_write_handler(data, fd),
+        Some(FileDescription::PipeOut) => pipe_write_handler(data),
+        Some(FileDescription::File(_)) => vfs_write_handler(data, fd),
+        Some(FileDescription::PipeWriter(pipe)) => match pipe.write(data) {
+            Ok(()) => 0,
+            Err(err) => (ONLY_MSB_OF_USIZE | err as usize) as u64,
+        },
+        None => (ONLY_MSB_OF_USIZE | vfs::FileError::BadFileDescriptor as usize) as u64,
     }
 }
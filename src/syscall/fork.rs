@@ -0,0 +1,59 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::TaskError;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum ForkError {
+    /// Kernel tasks can't be forked
+    NotUserTask,
+    /// Stack or address space of child could not be created
+    OutOfMemory,
+    /// Limit of tasks, or of children of current task, has been reached
+    TaskLimitReached,
+}
+
+pub enum ForkResult {
+    /// Returned in parent task, contains pid of created child
+    Parent(u64),
+    /// Returned in created task
+    Child,
+}
+
+/// Duplicates current task. Private memory is copied on write, opened files are shared.
+pub fn fork() -> Result<ForkResult, ForkError> {
+    let val = unsafe { syscall0(Syscalls::Fork as usize) };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            ForkError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!("Unknown error during fork: {}", val & !ONLY_MSB_OF_USIZE)
+            }),
+        )
+    } else if val == 0 {
+        // task 0 is never created by fork, so 0 is free to mark the child
+        Ok(ForkResult::Child)
+    } else {
+        Ok(ForkResult::Parent(val as u64))
+    }
+}
+
+pub fn handle_fork(context: &mut ExceptionContext) {
+    let task = unsafe { &mut *scheduler::get_current_task_context() };
+    let result = scheduler::check_children_limit()
+        .and_then(|_| task.fork(context))
+        .and_then(|mut child| {
+            child.ppid = Some(scheduler::get_current_task_pid());
+            scheduler::add_task(child)
+        })
+        .map_err(|error| match error {
+            TaskError::InvalidTaskReference => ForkError::NotUserTask,
+            TaskError::TaskLimitReached => ForkError::TaskLimitReached,
+            _ => ForkError::OutOfMemory,
+        });
+    context.gpr[0] = match result {
+        Ok(pid) => pid,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    };
+}
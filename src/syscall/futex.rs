@@ -0,0 +1,168 @@
+use crate::device_driver;
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::WaitCondition;
+use crate::scheduler::task_memory_manager::FutexKey;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicU32;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum FutexError {
+    /// Address is not mapped or not aligned to 4 bytes
+    InvalidAddress,
+    /// Word did not contain expected value
+    WouldBlock,
+    /// Task was resumed to run signal handler before syscall could complete
+    Interrupted,
+}
+
+// pids of tasks suspended on futex words, in order of arrival
+device_driver!(
+    unsynchronized FUTEX_QUEUES: BTreeMap<FutexKey, VecDeque<usize>> = BTreeMap::new()
+);
+
+fn to_result(val: usize) -> Result<usize, FutexError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            FutexError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during futex operation: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Suspends task until futex_wake is called on the same word, if it still contains expected
+/// value. Task may also be resumed spuriously, so callers should recheck their condition.
+pub fn futex_wait(word: &AtomicU32, expected: u32) -> Result<(), FutexError> {
+    to_result(unsafe {
+        syscall2(
+            word as *const AtomicU32 as usize,
+            expected as usize,
+            Syscalls::FutexWait as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+/// Resumes up to count tasks waiting on word, returns how many were resumed
+pub fn futex_wake(word: &AtomicU32, count: usize) -> Result<usize, FutexError> {
+    to_result(unsafe {
+        syscall2(
+            word as *const AtomicU32 as usize,
+            count,
+            Syscalls::FutexWake as usize,
+        )
+    })
+}
+
+fn to_return_value(result: Result<usize, FutexError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+fn futex_key(address: usize) -> Result<FutexKey, FutexError> {
+    if address % core::mem::size_of::<u32>() != 0 {
+        return Err(FutexError::InvalidAddress);
+    }
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    current_task
+        .memory_manager
+        .lock()
+        .futex_key(address)
+        .ok_or(FutexError::InvalidAddress)
+}
+
+/// Removes task from queue of futex word, returns whether it was queued
+fn remove_waiter(
+    queues: &mut BTreeMap<FutexKey, VecDeque<usize>>,
+    key: FutexKey,
+    pid: usize,
+) -> bool {
+    let queue = match queues.get_mut(&key) {
+        Some(queue) => queue,
+        None => return false,
+    };
+    let index = queue.iter().position(|waiter| *waiter == pid);
+    if let Some(index) = index {
+        queue.remove(index);
+    }
+    if queue.is_empty() {
+        queues.remove(&key);
+    }
+    index.is_some()
+}
+
+/// Removes finished task from all futex queues, so wakes are not lost on it
+pub fn forget_waiter(pid: usize) {
+    let mut queues = FUTEX_QUEUES.lock();
+    let keys: Vec<FutexKey> = queues.keys().copied().collect();
+    for key in keys {
+        remove_waiter(&mut queues, key, pid);
+    }
+}
+
+fn wait_impl(context: &mut ExceptionContext) -> Result<usize, FutexError> {
+    let address = context.gpr[0] as usize;
+    let expected = context.gpr[1] as u32;
+    let key = futex_key(address)?;
+    // syscalls are not interrupted, so value can't change until task is queued
+    if unsafe { core::ptr::read_volatile(address as *const u32) } != expected {
+        return Err(FutexError::WouldBlock);
+    }
+    let pid = scheduler::get_current_task_pid();
+    FUTEX_QUEUES
+        .lock()
+        .entry(key)
+        .or_insert_with(VecDeque::new)
+        .push_back(pid);
+    let interrupted = scheduler::suspend_current_task(WaitCondition::Futex(key)).is_err();
+    // task is still queued if it was resumed by something else than futex_wake
+    let woken = !remove_waiter(&mut FUTEX_QUEUES.lock(), key, pid);
+    if interrupted && !woken {
+        return Err(FutexError::Interrupted);
+    }
+    Ok(0)
+}
+
+fn wake_impl(context: &mut ExceptionContext) -> Result<usize, FutexError> {
+    let key = futex_key(context.gpr[0] as usize)?;
+    let count = context.gpr[1] as usize;
+    let mut queues = FUTEX_QUEUES.lock();
+    let mut woken = 0;
+    if let Some(queue) = queues.get_mut(&key) {
+        while woken < count {
+            let pid = match queue.pop_front() {
+                Some(pid) => pid,
+                None => break,
+            };
+            scheduler::wake_task_if(pid, |condition| match condition {
+                WaitCondition::Futex(waited) => *waited == key,
+                _ => false,
+            });
+            woken += 1;
+        }
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+    Ok(woken)
+}
+
+pub fn handle_futex_wait(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(wait_impl(context));
+}
+
+pub fn handle_futex_wake(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(wake_impl(context));
+}
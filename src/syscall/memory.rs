@@ -0,0 +1,140 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::resource_limits::Resource;
+use crate::scheduler::task_memory_manager::{MappingError, PAGE_SIZE};
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum MemoryError {
+    /// Break outside of heap range, or address and size not matching anonymous mapping
+    InvalidRange,
+    /// Memory would exceed HeapBytes limit of task
+    LimitReached,
+    /// There is no free memory or address range left
+    OutOfMemory,
+    /// Unmapped range holds buffer registered for async syscalls
+    InUse,
+}
+
+impl From<MappingError> for MemoryError {
+    fn from(error: MappingError) -> Self {
+        match error {
+            MappingError::InvalidRange | MappingError::Overlap => MemoryError::InvalidRange,
+            MappingError::OutOfMemory => MemoryError::OutOfMemory,
+        }
+    }
+}
+
+fn to_result(val: usize) -> Result<usize, MemoryError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            MemoryError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during memory mapping: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Moves end of heap of current task by increment, returns previous end.
+/// Heap is shared with threads of the task and copied on fork.
+pub fn sbrk(increment: isize) -> Result<usize, MemoryError> {
+    to_result(unsafe { syscall1(increment as usize, Syscalls::Sbrk as usize) })
+}
+
+/// Maps zeroed memory of given size, rounded up to whole pages
+pub fn mmap(size: usize) -> Result<*mut u8, MemoryError> {
+    to_result(unsafe { syscall1(size, Syscalls::MapMemory as usize) })
+        .map(|address| address as *mut u8)
+}
+
+/// Unmaps whole mapping created by mmap with the same size
+pub fn munmap(address: *mut u8, size: usize) -> Result<(), MemoryError> {
+    to_result(unsafe { syscall2(address as usize, size, Syscalls::UnmapMemory as usize) })
+        .map(|_| ())
+}
+
+fn to_return_value(result: Result<usize, MemoryError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+fn page_up(size: usize) -> Option<usize> {
+    Some(size.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1))
+}
+
+/// Fails if task would use more than HeapBytes limit after allocating additional bytes
+fn check_heap_limit(heap_size: usize, additional: usize) -> Result<(), MemoryError> {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let limit = current_task.limits.soft(Resource::HeapBytes);
+    match heap_size.checked_add(additional) {
+        Some(size) if size as u64 <= limit => Ok(()),
+        _ => Err(MemoryError::LimitReached),
+    }
+}
+
+fn sbrk_impl(context: &mut ExceptionContext) -> Result<usize, MemoryError> {
+    let increment = context.gpr[0] as isize;
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let mut memory_manager = current_task.memory_manager.lock();
+    let old_break = memory_manager.heap_break();
+    if increment > 0 {
+        check_heap_limit(memory_manager.heap_size(), increment as usize)?;
+    }
+    let new_break = (old_break as isize)
+        .checked_add(increment)
+        .ok_or(MemoryError::InvalidRange)?;
+    if increment < 0
+        && scheduler::is_registered_buffer(
+            &current_task.memory_manager,
+            &(new_break as usize..old_break),
+        )
+    {
+        return Err(MemoryError::InUse);
+    }
+    memory_manager.set_heap_break(new_break as usize)?;
+    Ok(old_break)
+}
+
+fn mmap_impl(context: &mut ExceptionContext) -> Result<usize, MemoryError> {
+    let size = page_up(context.gpr[0] as usize).ok_or(MemoryError::InvalidRange)?;
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let mut memory_manager = current_task.memory_manager.lock();
+    check_heap_limit(memory_manager.heap_size(), size)?;
+    Ok(memory_manager.map_anonymous(size)?)
+}
+
+fn munmap_impl(context: &mut ExceptionContext) -> Result<usize, MemoryError> {
+    let address = context.gpr[0] as usize;
+    let size = page_up(context.gpr[1] as usize).ok_or(MemoryError::InvalidRange)?;
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let range = address..address.saturating_add(size);
+    if scheduler::is_registered_buffer(&current_task.memory_manager, &range) {
+        return Err(MemoryError::InUse);
+    }
+    current_task
+        .memory_manager
+        .lock()
+        .unmap_anonymous(address, size)?;
+    Ok(0)
+}
+
+pub fn handle_sbrk(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(sbrk_impl(context));
+}
+
+pub fn handle_mmap(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(mmap_impl(context));
+}
+
+pub fn handle_munmap(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(munmap_impl(context));
+}
@@ -0,0 +1,643 @@
+use crate::device_driver;
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::WaitCondition;
+use crate::sync::nulllock::NullLock;
+use crate::syscall::files::file_descriptor_map::{release_description, FileDescription};
+use crate::syscall::files::get_description;
+use crate::syscall::files::poll::{PollObject, POLL_IN, POLL_OUT};
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::str::from_utf8;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Size of data that single message can carry
+pub const MESSAGE_PAYLOAD_SIZE: usize = 128;
+/// Count of descriptors that single message can transfer
+pub const MESSAGE_MAX_FDS: usize = 4;
+/// Largest count of messages that port can queue
+pub const MAX_PORT_CAPACITY: usize = 64;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum PortError {
+    /// Name is not valid UTF-8
+    InvalidName,
+    /// Port with given name is still opened by some task
+    AlreadyExists,
+    /// No task has port with given name opened
+    NotFound,
+    /// Capacity is 0 or greater than MAX_PORT_CAPACITY
+    InvalidCapacity,
+    /// Length or descriptor count of message is too big
+    InvalidMessage,
+    /// Descriptor does not exist or does not refer to port
+    BadFileDescriptor,
+    /// Transferred descriptors would exceed OpenFiles limit of receiver
+    TooManyOpenFiles,
+    /// Message was dropped or its receiver finished without replying
+    NoReply,
+    /// There is no message waiting for reply with given id
+    InvalidReply,
+    /// Message transfers descriptor of the port it is sent to, or of port that holds it in its
+    /// queued messages
+    CyclicTransfer,
+    /// Task was resumed to run signal handler before syscall could complete
+    Interrupted,
+}
+
+/// Message passed through port. Kernel fills sender and reply_id on receive and replaces
+/// transferred descriptors with their numbers in receiving task.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Message {
+    /// Meaning of message, defined by protocol of port
+    pub tag: usize,
+    /// PID of sending task
+    pub sender: usize,
+    /// Id to pass to reply, 0 if sender does not wait for reply
+    pub reply_id: usize,
+    /// Count of used bytes of payload
+    pub length: usize,
+    pub payload: [u8; MESSAGE_PAYLOAD_SIZE],
+    /// Count of used descriptors in fds
+    pub fd_count: usize,
+    pub fds: [usize; MESSAGE_MAX_FDS],
+}
+
+impl Message {
+    /// Message with given tag and payload, which is truncated to MESSAGE_PAYLOAD_SIZE
+    pub fn new(tag: usize, data: &[u8]) -> Self {
+        let length = core::cmp::min(data.len(), MESSAGE_PAYLOAD_SIZE);
+        let mut payload = [0; MESSAGE_PAYLOAD_SIZE];
+        payload[..length].copy_from_slice(&data[..length]);
+        Message {
+            tag,
+            sender: 0,
+            reply_id: 0,
+            length,
+            payload,
+            fd_count: 0,
+            fds: [0; MESSAGE_MAX_FDS],
+        }
+    }
+
+    /// Adds descriptor that is duplicated to receiving task, returns false if message is full
+    pub fn push_fd(&mut self, fd: usize) -> bool {
+        if self.fd_count == MESSAGE_MAX_FDS {
+            return false;
+        }
+        self.fds[self.fd_count] = fd;
+        self.fd_count += 1;
+        true
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.payload[..core::cmp::min(self.length, MESSAGE_PAYLOAD_SIZE)]
+    }
+
+    pub fn fds(&self) -> &[usize] {
+        &self.fds[..core::cmp::min(self.fd_count, MESSAGE_MAX_FDS)]
+    }
+}
+
+/// Message held by kernel, with objects of transferred descriptors
+struct Envelope {
+    message: Message,
+    descriptions: Vec<FileDescription>,
+}
+
+impl Drop for Envelope {
+    fn drop(&mut self) {
+        for description in self.descriptions.drain(..) {
+            release_description(description);
+        }
+    }
+}
+
+impl Envelope {
+    /// Copies message from current task, taking objects of its descriptors
+    fn take(message: &Message) -> Result<Self, PortError> {
+        if message.length > MESSAGE_PAYLOAD_SIZE || message.fd_count > MESSAGE_MAX_FDS {
+            return Err(PortError::InvalidMessage);
+        }
+        let descriptions = message
+            .fds()
+            .iter()
+            .map(|fd| get_description(*fd).ok_or(PortError::BadFileDescriptor))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Envelope {
+            message: *message,
+            descriptions,
+        })
+    }
+
+    /// Ports whose descriptors are transferred with message
+    fn ports(&self) -> impl Iterator<Item = &SharedPort> {
+        self.descriptions.iter().filter_map(|description| {
+            if let FileDescription::Port(port) = description {
+                Some(port)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Places transferred objects on descriptors of current task and writes message to user.
+    /// Envelope is returned untouched when descriptor limit is reached.
+    fn deliver(mut self, message: *mut Message) -> Result<(), (Self, PortError)> {
+        let current_task = unsafe { &*scheduler::get_current_task_context() };
+        let mut fd_table = current_task.file_descriptor_table.lock();
+        let mut fds = [0; MESSAGE_MAX_FDS];
+        for (index, description) in self.descriptions.iter().enumerate() {
+            match fd_table.add_description(description.clone()) {
+                Ok(fd) => fds[index] = fd,
+                Err(_) => {
+                    for fd in &fds[..index] {
+                        fd_table.delete_file(*fd);
+                    }
+                    return Err((self, PortError::TooManyOpenFiles));
+                }
+            }
+        }
+        drop(fd_table);
+        // objects are referenced by new descriptors now
+        self.descriptions.clear();
+        self.message.fds = fds;
+        unsafe { *message = self.message };
+        Ok(())
+    }
+}
+
+/// State of message sent with expectation of reply
+enum PendingReply {
+    /// Message is queued, or was received by task with given pid
+    Waiting(Option<usize>),
+    Replied(Envelope),
+    Failed,
+}
+
+struct PendingReplies {
+    replies: BTreeMap<u64, (usize, PendingReply)>,
+}
+
+// reply ids start at 1, as 0 means that sender does not wait for reply
+static NEXT_REPLY_ID: AtomicU64 = AtomicU64::new(1);
+
+// messages waiting for reply, by reply id, with pid of their sender
+device_driver!(
+    unsynchronized PENDING_REPLIES: PendingReplies = PendingReplies {
+        replies: BTreeMap::new(),
+    }
+);
+
+// senders of failed replies, collected instead of woken while scheduler releases ports
+device_driver!(
+    unsynchronized FAILED_REPLY_SENDERS: Option<Vec<(u64, usize)>> = None
+);
+
+/// Whether task suspended on condition waits for reply with given id
+pub fn waits_for_reply(condition: &WaitCondition, id: u64) -> bool {
+    match condition {
+        WaitCondition::PortReply(reply) => *reply == id,
+        _ => false,
+    }
+}
+
+fn wake_reply_waiter(id: u64, sender: usize) {
+    scheduler::wake_task_if(sender, |condition| waits_for_reply(condition, id));
+}
+
+/// Sender of message with given id will get NoReply
+fn fail_reply(id: u64) {
+    let mut pending = PENDING_REPLIES.lock();
+    let sender = match pending.replies.get_mut(&id) {
+        Some((sender, state)) => {
+            *state = PendingReply::Failed;
+            *sender
+        }
+        None => return,
+    };
+    drop(pending);
+    if let Some(senders) = FAILED_REPLY_SENDERS.lock().as_mut() {
+        senders.push((id, sender));
+        return;
+    }
+    wake_reply_waiter(id, sender);
+}
+
+/// Makes replies failed by dropped messages collect their senders instead of waking them,
+/// until take_failed_replies. Scheduler can't be locked again while it releases ports.
+pub fn collect_failed_replies() {
+    *FAILED_REPLY_SENDERS.lock() = Some(Vec::new());
+}
+
+/// Stops collecting failed replies, returns their ids with pids of senders to wake
+pub fn take_failed_replies() -> Vec<(u64, usize)> {
+    FAILED_REPLY_SENDERS.lock().take().unwrap_or_default()
+}
+
+/// Forgets replies awaited by finished task and fails those it was supposed to send.
+/// Returns their ids with pids of senders to wake.
+pub fn fail_replies_of(pid: usize) -> Vec<(u64, usize)> {
+    let mut pending = PENDING_REPLIES.lock();
+    let ids: Vec<u64> = pending.replies.keys().copied().collect();
+    let mut failed = Vec::new();
+    for id in ids {
+        let (sender, state) = pending.replies.get_mut(&id).unwrap();
+        if *sender == pid {
+            pending.replies.remove(&id);
+        } else if let PendingReply::Waiting(Some(replier)) = state {
+            if *replier == pid {
+                *state = PendingReply::Failed;
+                failed.push((id, *sender));
+            }
+        }
+    }
+    failed
+}
+
+/// Queued message, its sender gets NoReply if it is dropped without being received
+struct QueuedMessage {
+    envelope: Option<Envelope>,
+    reply_id: Option<u64>,
+}
+
+impl Drop for QueuedMessage {
+    fn drop(&mut self) {
+        if let Some(id) = self.reply_id {
+            fail_reply(id);
+        }
+    }
+}
+
+/// Bounded queue of messages, shared by all descriptors referring to it.
+/// Queued messages are dropped when last descriptor is closed.
+pub struct Port {
+    queue: VecDeque<QueuedMessage>,
+    capacity: usize,
+}
+
+pub type SharedPort = Arc<NullLock<Port>>;
+
+pub(crate) fn port_id(port: &SharedPort) -> usize {
+    Arc::as_ptr(port) as usize
+}
+
+fn wake_waiting_tasks(port: &SharedPort) {
+    let id = port_id(port);
+    scheduler::wake_all_if(|condition| match condition {
+        WaitCondition::Port(port) => *port == id,
+        _ => false,
+    });
+    scheduler::wake_pollers(PollObject::Port(id));
+}
+
+/// Whether target port is one of given ports or is held by messages queued in them, directly or
+/// through further transferred ports
+fn holds_port(ports: Vec<SharedPort>, target: &SharedPort) -> bool {
+    let mut unvisited = ports;
+    let mut visited = Vec::new();
+    while let Some(port) = unvisited.pop() {
+        if Arc::ptr_eq(&port, target) {
+            return true;
+        }
+        if visited.contains(&port_id(&port)) {
+            continue;
+        }
+        visited.push(port_id(&port));
+        let queue = &port.lock().queue;
+        for queued in queue.iter() {
+            if let Some(envelope) = &queued.envelope {
+                unvisited.extend(envelope.ports().cloned());
+            }
+        }
+    }
+    false
+}
+
+/// Poll events that are currently signaled for port
+pub fn poll_events(port: &SharedPort) -> u32 {
+    let port = port.lock();
+    let mut events = 0;
+    if !port.queue.is_empty() {
+        events |= POLL_IN;
+    }
+    if port.queue.len() < port.capacity {
+        events |= POLL_OUT;
+    }
+    events
+}
+
+// ports are owned by descriptors referring to them, name is forgotten when all of them close
+device_driver!(
+    unsynchronized NAMED_PORTS: BTreeMap<String, Weak<NullLock<Port>>> = BTreeMap::new()
+);
+
+fn to_result(val: usize) -> Result<usize, PortError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            PortError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during port operation: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Creates port queuing up to capacity messages, returns its descriptor.
+/// Port with non empty name can be opened by other tasks with open_port.
+pub fn create_port(name: &str, capacity: usize) -> Result<usize, PortError> {
+    to_result(unsafe {
+        syscall3(
+            name.as_ptr() as usize,
+            name.len(),
+            capacity,
+            Syscalls::CreatePort as usize,
+        )
+    })
+}
+
+/// Opens port created with given name, returns its descriptor
+pub fn open_port(name: &str) -> Result<usize, PortError> {
+    to_result(unsafe {
+        syscall2(
+            name.as_ptr() as usize,
+            name.len(),
+            Syscalls::OpenPort as usize,
+        )
+    })
+}
+
+/// Queues message, suspends task while port is full. Descriptors listed in message are
+/// duplicated to receiver.
+pub fn send(fd: usize, message: &Message) -> Result<(), PortError> {
+    to_result(unsafe {
+        syscall3(
+            fd,
+            message as *const Message as usize,
+            0,
+            Syscalls::SendMessage as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+/// Queues message and suspends task until receiver replies to it
+pub fn call(fd: usize, message: &Message) -> Result<Message, PortError> {
+    let mut reply = Message::new(0, &[]);
+    to_result(unsafe {
+        syscall3(
+            fd,
+            message as *const Message as usize,
+            &mut reply as *mut Message as usize,
+            Syscalls::SendMessage as usize,
+        )
+    })?;
+    Ok(reply)
+}
+
+/// Takes oldest message from port, suspends task while port is empty
+pub fn receive(fd: usize) -> Result<Message, PortError> {
+    let mut message = Message::new(0, &[]);
+    to_result(unsafe {
+        syscall2(
+            fd,
+            &mut message as *mut Message as usize,
+            Syscalls::ReceiveMessage as usize,
+        )
+    })?;
+    Ok(message)
+}
+
+/// Resumes sender of received message, passing reply to it
+pub fn reply(reply_id: usize, message: &Message) -> Result<(), PortError> {
+    to_result(unsafe {
+        syscall2(
+            reply_id,
+            message as *const Message as usize,
+            Syscalls::ReplyMessage as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+fn to_return_value(result: Result<usize, PortError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+unsafe fn user_str<'a>(pointer: u64, length: u64) -> Result<&'a str, PortError> {
+    from_utf8(core::slice::from_raw_parts(
+        pointer as *const u8,
+        length as usize,
+    ))
+    .map_err(|_| PortError::InvalidName)
+}
+
+fn add_port(port: SharedPort) -> Result<usize, PortError> {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    current_task
+        .file_descriptor_table
+        .lock()
+        .add_description(FileDescription::Port(port))
+        .map_err(|_| PortError::TooManyOpenFiles)
+}
+
+fn get_port(fd: u64) -> Result<SharedPort, PortError> {
+    match get_description(fd as usize) {
+        Some(FileDescription::Port(port)) => Ok(port),
+        _ => Err(PortError::BadFileDescriptor),
+    }
+}
+
+fn create_impl(context: &mut ExceptionContext) -> Result<usize, PortError> {
+    let name = unsafe { user_str(context.gpr[0], context.gpr[1]) }?;
+    let capacity = context.gpr[2] as usize;
+    if capacity == 0 || capacity > MAX_PORT_CAPACITY {
+        return Err(PortError::InvalidCapacity);
+    }
+    let mut ports = NAMED_PORTS.lock();
+    if !name.is_empty()
+        && ports
+            .get(name)
+            .map_or(false, |port| port.strong_count() > 0)
+    {
+        return Err(PortError::AlreadyExists);
+    }
+    let port = Arc::new(NullLock::new(Port {
+        queue: VecDeque::with_capacity(capacity),
+        capacity,
+    }));
+    if !name.is_empty() {
+        ports.insert(String::from(name), Arc::downgrade(&port));
+    }
+    drop(ports);
+    add_port(port)
+}
+
+fn open_impl(context: &mut ExceptionContext) -> Result<usize, PortError> {
+    let name = unsafe { user_str(context.gpr[0], context.gpr[1]) }?;
+    let mut ports = NAMED_PORTS.lock();
+    let port = ports.get(name).and_then(Weak::upgrade);
+    if port.is_none() {
+        ports.remove(name);
+    }
+    drop(ports);
+    add_port(port.ok_or(PortError::NotFound)?)
+}
+
+fn wait_for_reply(id: u64, reply: *mut Message) -> Result<usize, PortError> {
+    let mut interrupted = false;
+    loop {
+        let mut pending = PENDING_REPLIES.lock();
+        let waiting = if let Some((_, PendingReply::Waiting(_))) = pending.replies.get(&id) {
+            true
+        } else {
+            false
+        };
+        if waiting {
+            if interrupted {
+                // replier gets InvalidReply
+                pending.replies.remove(&id);
+                return Err(PortError::Interrupted);
+            }
+            drop(pending);
+            interrupted = scheduler::suspend_current_task(WaitCondition::PortReply(id)).is_err();
+            continue;
+        }
+        return match pending.replies.remove(&id) {
+            Some((_, PendingReply::Replied(envelope))) => {
+                drop(pending);
+                // reply is lost if it can't be delivered
+                envelope
+                    .deliver(reply)
+                    .map(|_| 0)
+                    .map_err(|(_, error)| error)
+            }
+            _ => Err(PortError::NoReply),
+        };
+    }
+}
+
+fn send_impl(context: &mut ExceptionContext) -> Result<usize, PortError> {
+    let port = get_port(context.gpr[0])?;
+    let mut envelope = Envelope::take(unsafe { &*(context.gpr[1] as *const Message) })?;
+    let reply = context.gpr[2] as *mut Message;
+    let pid = scheduler::get_current_task_pid();
+    envelope.message.sender = pid;
+    loop {
+        // ports holding each other in their queues would never be freed, queues may change while
+        // task waits for free space
+        if holds_port(envelope.ports().cloned().collect(), &port) {
+            return Err(PortError::CyclicTransfer);
+        }
+        {
+            let mut queue = port.lock();
+            if queue.queue.len() < queue.capacity {
+                let reply_id = if reply.is_null() {
+                    None
+                } else {
+                    let id = NEXT_REPLY_ID.fetch_add(1, Ordering::Relaxed);
+                    PENDING_REPLIES
+                        .lock()
+                        .replies
+                        .insert(id, (pid, PendingReply::Waiting(None)));
+                    Some(id)
+                };
+                envelope.message.reply_id = reply_id.unwrap_or(0) as usize;
+                queue.queue.push_back(QueuedMessage {
+                    envelope: Some(envelope),
+                    reply_id,
+                });
+                drop(queue);
+                // receivers may wait for message
+                wake_waiting_tasks(&port);
+                return match reply_id {
+                    Some(id) => wait_for_reply(id, reply),
+                    None => Ok(0),
+                };
+            }
+        }
+        scheduler::suspend_current_task(WaitCondition::Port(port_id(&port)))
+            .map_err(|_| PortError::Interrupted)?;
+    }
+}
+
+fn receive_impl(context: &mut ExceptionContext) -> Result<usize, PortError> {
+    let port = get_port(context.gpr[0])?;
+    let message = context.gpr[1] as *mut Message;
+    loop {
+        let queued = port.lock().queue.pop_front();
+        if let Some(mut queued) = queued {
+            let envelope = queued.envelope.take().unwrap();
+            if let Err((envelope, error)) = envelope.deliver(message) {
+                queued.envelope = Some(envelope);
+                port.lock().queue.push_front(queued);
+                return Err(error);
+            }
+            if let Some(id) = queued.reply_id.take() {
+                let pid = scheduler::get_current_task_pid();
+                if let Some((_, state)) = PENDING_REPLIES.lock().replies.get_mut(&id) {
+                    *state = PendingReply::Waiting(Some(pid));
+                }
+            }
+            // senders may wait for free space
+            wake_waiting_tasks(&port);
+            return Ok(0);
+        }
+        scheduler::suspend_current_task(WaitCondition::Port(port_id(&port)))
+            .map_err(|_| PortError::Interrupted)?;
+    }
+}
+
+fn reply_impl(context: &mut ExceptionContext) -> Result<usize, PortError> {
+    let id = context.gpr[0];
+    let pid = scheduler::get_current_task_pid();
+    let mut envelope = Envelope::take(unsafe { &*(context.gpr[1] as *const Message) })?;
+    envelope.message.sender = pid;
+    let mut pending = PENDING_REPLIES.lock();
+    let (sender, state) = pending
+        .replies
+        .get_mut(&id)
+        .ok_or(PortError::InvalidReply)?;
+    match state {
+        PendingReply::Waiting(Some(replier)) if *replier == pid => {}
+        _ => return Err(PortError::InvalidReply),
+    }
+    let sender = *sender;
+    *state = PendingReply::Replied(envelope);
+    drop(pending);
+    wake_reply_waiter(id, sender);
+    Ok(0)
+}
+
+pub fn handle_create_port(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(create_impl(context));
+}
+
+pub fn handle_open_port(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(open_impl(context));
+}
+
+pub fn handle_send_message(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(send_impl(context));
+}
+
+pub fn handle_receive_message(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(receive_impl(context));
+}
+
+pub fn handle_reply_message(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(reply_impl(context));
+}
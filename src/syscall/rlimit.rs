@@ -0,0 +1,92 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+pub use crate::scheduler::resource_limits::{Resource, ResourceLimit, RLIMIT_INFINITY};
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum RlimitError {
+    /// There is no resource with given id
+    InvalidResource,
+    /// Soft limit can't be greater than hard limit
+    SoftAboveHard,
+    /// Hard limit can only be lowered
+    HardLimitRaised,
+}
+
+fn to_result(val: usize) -> Result<usize, RlimitError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            RlimitError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during resource limit access: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+pub fn getrlimit(resource: Resource) -> Result<ResourceLimit, RlimitError> {
+    let mut limit = ResourceLimit::new(0, 0);
+    to_result(unsafe {
+        syscall2(
+            resource as usize,
+            &mut limit as *mut ResourceLimit as usize,
+            Syscalls::GetResourceLimit as usize,
+        )
+    })?;
+    Ok(limit)
+}
+
+/// Sets limit of current task, it is inherited by tasks created afterwards
+pub fn setrlimit(resource: Resource, limit: ResourceLimit) -> Result<(), RlimitError> {
+    to_result(unsafe {
+        syscall2(
+            resource as usize,
+            &limit as *const ResourceLimit as usize,
+            Syscalls::SetResourceLimit as usize,
+        )
+    })
+    .map(|_| ())
+}
+
+fn to_return_value(result: Result<(), RlimitError>) -> u64 {
+    match result {
+        Ok(()) => 0,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+pub fn handle_getrlimit(context: &mut ExceptionContext) {
+    let limit = context.gpr[1] as *mut ResourceLimit;
+    context.gpr[0] = to_return_value(
+        Resource::from_u64(context.gpr[0])
+            .ok_or(RlimitError::InvalidResource)
+            .map(|resource| {
+                let current_task = unsafe { &*scheduler::get_current_task_context() };
+                unsafe { *limit = current_task.limits.get(resource) };
+            }),
+    );
+}
+
+fn setrlimit_impl(context: &mut ExceptionContext) -> Result<(), RlimitError> {
+    let resource = Resource::from_u64(context.gpr[0]).ok_or(RlimitError::InvalidResource)?;
+    let limit = unsafe { *(context.gpr[1] as *const ResourceLimit) };
+    let current_task = unsafe { &mut *scheduler::get_current_task_context() };
+    current_task.limits.set(resource, limit)?;
+    if let Resource::OpenFiles = resource {
+        current_task
+            .file_descriptor_table
+            .lock()
+            .set_descriptor_limit(limit.soft);
+    }
+    Ok(())
+}
+
+pub fn handle_setrlimit(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(setrlimit_impl(context));
+}
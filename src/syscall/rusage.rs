@@ -0,0 +1,62 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+pub use crate::scheduler::resource_usage::ResourceUsage;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug, Clone, Copy)]
+pub enum UsageTarget {
+    /// Current task
+    CurrentTask,
+    /// All reaped children of current task and their reaped descendants
+    Children,
+}
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum UsageError {
+    /// There is no target with given id
+    InvalidTarget,
+}
+
+/// Resources used by current task or its reaped children
+pub fn getrusage(target: UsageTarget) -> Result<ResourceUsage, UsageError> {
+    let mut usage = ResourceUsage::default();
+    let val = unsafe {
+        syscall2(
+            target as usize,
+            &mut usage as *mut ResourceUsage as usize,
+            Syscalls::GetResourceUsage as usize,
+        )
+    };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            UsageError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during getrusage: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(usage)
+    }
+}
+
+pub fn handle_getrusage(context: &mut ExceptionContext) {
+    let target = UsageTarget::from_u64(context.gpr[0]);
+    let usage = context.gpr[1] as *mut ResourceUsage;
+    let (task_usage, children_usage) = scheduler::get_current_task_usage();
+    context.gpr[0] = match target {
+        Some(UsageTarget::CurrentTask) => {
+            unsafe { *usage = task_usage };
+            0
+        }
+        Some(UsageTarget::Children) => {
+            unsafe { *usage = children_usage };
+            0
+        }
+        None => (ONLY_MSB_OF_USIZE | UsageError::InvalidTarget as usize) as u64,
+    };
+}
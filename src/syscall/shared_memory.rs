@@ -0,0 +1,139 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::shared_memory::{self, SharedMemory};
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::sync::Arc;
+use core::str::from_utf8;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum SharedMemoryError {
+    /// Name is empty or is not valid UTF-8
+    InvalidName,
+    /// Object with given name is attached by some task
+    AlreadyExists,
+    /// No task has object with given name attached
+    NotFound,
+    /// Size is 0 or too big
+    InvalidSize,
+    /// No object is attached at given address
+    NotAttached,
+    OutOfMemory,
+    /// Object holds buffer registered for async syscalls
+    InUse,
+}
+
+fn to_result(val: usize) -> Result<usize, SharedMemoryError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            SharedMemoryError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during shared memory access: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Creates named shared memory object of given size and attaches it to current task.
+/// Object exists as long as any task has it attached, attachments are kept across fork.
+pub fn shm_create(name: &str, size: usize) -> Result<*mut u8, SharedMemoryError> {
+    to_result(unsafe {
+        syscall3(
+            name.as_ptr() as usize,
+            name.len(),
+            size,
+            Syscalls::CreateSharedMemory as usize,
+        )
+    })
+    .map(|address| address as *mut u8)
+}
+
+/// Attaches existing shared memory object, returns its address and size
+pub fn shm_attach(name: &str) -> Result<(*mut u8, usize), SharedMemoryError> {
+    let mut size = 0usize;
+    let address = to_result(unsafe {
+        syscall3(
+            name.as_ptr() as usize,
+            name.len(),
+            &mut size as *mut usize as usize,
+            Syscalls::AttachSharedMemory as usize,
+        )
+    })?;
+    Ok((address as *mut u8, size))
+}
+
+/// Detaches shared memory object attached at given address, its memory is freed when no task
+/// has it attached
+pub fn shm_detach(address: *mut u8) -> Result<(), SharedMemoryError> {
+    to_result(unsafe { syscall1(address as usize, Syscalls::DetachSharedMemory as usize) })
+        .map(|_| ())
+}
+
+fn to_return_value(result: Result<usize, SharedMemoryError>) -> u64 {
+    match result {
+        Ok(val) => val as u64,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    }
+}
+
+unsafe fn user_str<'a>(pointer: u64, length: u64) -> Result<&'a str, SharedMemoryError> {
+    from_utf8(core::slice::from_raw_parts(
+        pointer as *const u8,
+        length as usize,
+    ))
+    .map_err(|_| SharedMemoryError::InvalidName)
+}
+
+fn attach(memory: Arc<SharedMemory>) -> Result<usize, SharedMemoryError> {
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    current_task
+        .memory_manager
+        .lock()
+        .map_shared(memory)
+        .map_err(|_| SharedMemoryError::OutOfMemory)
+}
+
+fn create_impl(context: &mut ExceptionContext) -> Result<usize, SharedMemoryError> {
+    let name = unsafe { user_str(context.gpr[0], context.gpr[1]) }?;
+    attach(shared_memory::create(name, context.gpr[2] as usize)?)
+}
+
+fn attach_impl(context: &mut ExceptionContext) -> Result<usize, SharedMemoryError> {
+    let name = unsafe { user_str(context.gpr[0], context.gpr[1]) }?;
+    let memory = shared_memory::find(name)?;
+    unsafe { *(context.gpr[2] as *mut usize) = memory.size() };
+    attach(memory)
+}
+
+fn detach_impl(context: &mut ExceptionContext) -> Result<usize, SharedMemoryError> {
+    let address = context.gpr[0] as usize;
+    let current_task = unsafe { &*scheduler::get_current_task_context() };
+    let mut memory_manager = current_task.memory_manager.lock();
+    let range = memory_manager
+        .shared_range(address)
+        .ok_or(SharedMemoryError::NotAttached)?;
+    if scheduler::is_registered_buffer(&current_task.memory_manager, &range) {
+        return Err(SharedMemoryError::InUse);
+    }
+    memory_manager
+        .unmap_shared(address)
+        .map_err(|_| SharedMemoryError::NotAttached)?;
+    Ok(0)
+}
+
+pub fn handle_shm_create(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(create_impl(context));
+}
+
+pub fn handle_shm_attach(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(attach_impl(context));
+}
+
+pub fn handle_shm_detach(context: &mut ExceptionContext) {
+    context.gpr[0] = to_return_value(detach_impl(context));
+}
@@ -0,0 +1,130 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::signals::{MaskOperation, Signal, SignalHandler};
+use crate::scheduler::signals::{SIGNAL_DEFAULT, SIGNAL_IGNORE};
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum SignalError {
+    /// Number does not correspond to any signal
+    InvalidSignal,
+    /// Target task does not exist or has already finished
+    NoSuchTask,
+    /// Kill and Stop can't have handler nor be ignored
+    CannotBeCaught,
+}
+
+/// What happens when signal is delivered to task
+pub enum SignalAction {
+    Default,
+    Ignore,
+    Handle(extern "C" fn(usize)),
+}
+
+fn result_from_usize(val: usize) -> Result<usize, SignalError> {
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            SignalError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during signal syscall: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok(val)
+    }
+}
+
+/// Handler returns here, SignalReturn restores context from before the signal
+#[inline(never)]
+extern "C" fn signal_return_trampoline() {
+    unsafe {
+        syscall0(Syscalls::SignalReturn as usize);
+    }
+}
+
+/// Sends signal to task with given pid
+pub fn kill(pid: u64, signal: Signal) -> Result<(), SignalError> {
+    let val = unsafe { syscall2(pid as usize, signal as usize, Syscalls::Kill as usize) };
+    result_from_usize(val).map(|_| ())
+}
+
+/// Sets action taken by current task on delivery of the signal
+pub fn set_signal_action(signal: Signal, action: SignalAction) -> Result<(), SignalError> {
+    let handler = match action {
+        SignalAction::Default => SIGNAL_DEFAULT,
+        SignalAction::Ignore => SIGNAL_IGNORE,
+        SignalAction::Handle(function) => function as *const () as usize,
+    };
+    let val = unsafe {
+        syscall3(
+            signal as usize,
+            handler,
+            signal_return_trampoline as *const () as usize,
+            Syscalls::SignalAction as usize,
+        )
+    };
+    result_from_usize(val).map(|_| ())
+}
+
+/// Changes set of blocked signals (bit n for signal n), returns previous set
+pub fn set_signal_mask(operation: MaskOperation, set: u32) -> u32 {
+    unsafe { syscall2(operation as usize, set as usize, Syscalls::SignalMask as usize) as u32 }
+}
+
+pub fn handle_kill(context: &mut ExceptionContext) {
+    let pid = context.gpr[0] as usize;
+    let signal = match Signal::from_u64(context.gpr[1]) {
+        Some(signal) => signal,
+        None => {
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | SignalError::InvalidSignal as usize) as u64;
+            return;
+        }
+    };
+    context.gpr[0] = match scheduler::send_signal(pid, signal) {
+        Ok(()) => 0,
+        Err(_) => (ONLY_MSB_OF_USIZE | SignalError::NoSuchTask as usize) as u64,
+    };
+}
+
+pub fn handle_signal_action(context: &mut ExceptionContext) {
+    let signal = match Signal::from_u64(context.gpr[0]) {
+        Some(signal) => signal,
+        None => {
+            context.gpr[0] = (ONLY_MSB_OF_USIZE | SignalError::InvalidSignal as usize) as u64;
+            return;
+        }
+    };
+    if !signal.can_be_caught() {
+        context.gpr[0] = (ONLY_MSB_OF_USIZE | SignalError::CannotBeCaught as usize) as u64;
+        return;
+    }
+    let handler = match context.gpr[1] as usize {
+        SIGNAL_DEFAULT => SignalHandler::Default,
+        SIGNAL_IGNORE => SignalHandler::Ignore,
+        address => SignalHandler::Function {
+            address,
+            trampoline: context.gpr[2] as usize,
+        },
+    };
+    let task = unsafe { &mut *scheduler::get_current_task_context() };
+    task.signals.set_handler(signal, handler);
+    context.gpr[0] = 0;
+}
+
+pub fn handle_signal_mask(context: &mut ExceptionContext) {
+    let task = unsafe { &mut *scheduler::get_current_task_context() };
+    // unknown operation leaves mask untouched
+    let (operation, set) = match MaskOperation::from_u64(context.gpr[0]) {
+        Some(operation) => (operation, context.gpr[1] as u32),
+        None => (MaskOperation::Block, 0),
+    };
+    context.gpr[0] = task.signals.update_mask(operation, set) as u64;
+}
+
+pub fn handle_signal_return(context: &mut ExceptionContext) {
+    scheduler::signals::return_from_signal(context);
+}
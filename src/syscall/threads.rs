@@ -0,0 +1,183 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::{TaskError, WaitCondition};
+use crate::scheduler::ChildKind;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+use alloc::boxed::Box;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum ThreadError {
+    /// Kernel tasks can't create threads
+    NotUserTask,
+    /// Stack of new thread could not be allocated
+    OutOfMemory,
+    /// Limit of tasks, or of children of current task, has been reached
+    TaskLimitReached,
+    /// Thread does not exist, was not created by current task or is detached
+    NoSuchThread,
+    /// Task was resumed to run signal handler before syscall could complete
+    Interrupted,
+}
+
+type ThreadFunction = Box<dyn FnOnce() -> u32 + Send>;
+
+/// Thread of current task, sharing its memory and opened files
+pub struct Thread {
+    tid: u64,
+}
+
+/// Runs in new thread, takes ownership of closure passed to spawn
+extern "C" fn thread_start(function: usize) -> u32 {
+    let function = unsafe { Box::from_raw(function as *mut ThreadFunction) };
+    let return_value = function();
+    finish_task(return_value);
+    return_value
+}
+
+fn thread_error(val: usize) -> ThreadError {
+    ThreadError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+        panic!(
+            "Unknown error during thread operation: {}",
+            val & !ONLY_MSB_OF_USIZE
+        )
+    })
+}
+
+impl Thread {
+    /// Runs function in new thread with thread pointer (TPIDR_EL0) set to 0
+    pub fn spawn<F>(function: F) -> Result<Thread, ThreadError>
+    where
+        F: FnOnce() -> u32 + Send + 'static,
+    {
+        Self::spawn_with_tls(function, 0)
+    }
+
+    /// Runs function in new thread with thread pointer (TPIDR_EL0) set to tls
+    pub fn spawn_with_tls<F>(function: F, tls: usize) -> Result<Thread, ThreadError>
+    where
+        F: FnOnce() -> u32 + Send + 'static,
+    {
+        let function: Box<ThreadFunction> = Box::new(Box::new(function));
+        let function = Box::into_raw(function);
+        let val = unsafe {
+            syscall3(
+                thread_start as *const () as usize,
+                function as usize,
+                tls,
+                Syscalls::CreateThread as usize,
+            )
+        };
+        if val & ONLY_MSB_OF_USIZE > 0 {
+            // thread was not started, so closure is still owned here
+            drop(unsafe { Box::from_raw(function) });
+            Err(thread_error(val))
+        } else {
+            Ok(Thread { tid: val as u64 })
+        }
+    }
+
+    pub fn tid(&self) -> u64 {
+        self.tid
+    }
+
+    /// Suspends current thread until this one finishes, returns its return value
+    pub fn join(self) -> Result<u32, ThreadError> {
+        let mut return_value: u32 = 0;
+        let val = unsafe {
+            syscall2(
+                self.tid as usize,
+                &mut return_value as *mut u32 as usize,
+                Syscalls::JoinThread as usize,
+            )
+        };
+        if val & ONLY_MSB_OF_USIZE > 0 {
+            Err(thread_error(val))
+        } else {
+            Ok(return_value)
+        }
+    }
+
+    /// Lets thread run independently, its resources are released as soon as it finishes
+    pub fn detach(self) -> Result<(), ThreadError> {
+        let val = unsafe { syscall1(self.tid as usize, Syscalls::DetachThread as usize) };
+        if val & ONLY_MSB_OF_USIZE > 0 {
+            Err(thread_error(val))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Ends current thread with given return value
+pub fn exit_thread(return_value: u32) {
+    finish_task(return_value);
+}
+
+/// Thread pointer of current thread, usually base of its thread local storage
+pub fn thread_pointer() -> usize {
+    crate::aarch64::asm::get_el0_thread_pointer() as usize
+}
+
+/// Sets thread pointer of current thread, it is preserved across task switches
+pub fn set_thread_pointer(tls: usize) {
+    crate::aarch64::asm::set_el0_thread_pointer(tls as u64);
+}
+
+pub fn handle_create_thread(context: &mut ExceptionContext) {
+    let entry = context.gpr[0] as usize;
+    let argument = context.gpr[1] as usize;
+    let tls = context.gpr[2] as usize;
+
+    let task = unsafe { &*scheduler::get_current_task_context() };
+    let result = scheduler::check_children_limit()
+        .and_then(|_| task.new_thread(entry, argument, tls))
+        .and_then(|mut thread| {
+            thread.ppid = Some(scheduler::get_current_task_pid());
+            scheduler::add_task(thread)
+        })
+        .map_err(|error| match error {
+            TaskError::InvalidTaskReference => ThreadError::NotUserTask,
+            TaskError::TaskLimitReached => ThreadError::TaskLimitReached,
+            _ => ThreadError::OutOfMemory,
+        });
+    context.gpr[0] = match result {
+        Ok(tid) => tid,
+        Err(error) => (ONLY_MSB_OF_USIZE | error as usize) as u64,
+    };
+}
+
+pub fn handle_join_thread(context: &mut ExceptionContext) {
+    let tid = context.gpr[0] as usize;
+    let return_value = context.gpr[1] as *mut u32;
+
+    loop {
+        match scheduler::reap_child_task(Some(tid), ChildKind::Thread) {
+            Ok(Some((_, value))) => {
+                if !return_value.is_null() {
+                    unsafe { *return_value = value };
+                }
+                context.gpr[0] = 0;
+                return;
+            }
+            Ok(None) => {
+                if scheduler::suspend_current_task(WaitCondition::Child(Some(tid))).is_err() {
+                    context.gpr[0] = (ONLY_MSB_OF_USIZE | ThreadError::Interrupted as usize) as u64;
+                    return;
+                }
+            }
+            Err(_) => {
+                context.gpr[0] = (ONLY_MSB_OF_USIZE | ThreadError::NoSuchThread as usize) as u64;
+                return;
+            }
+        }
+    }
+}
+
+pub fn handle_detach_thread(context: &mut ExceptionContext) {
+    context.gpr[0] = match scheduler::detach_thread(context.gpr[0] as usize) {
+        Ok(()) => 0,
+        Err(_) => (ONLY_MSB_OF_USIZE | ThreadError::NoSuchThread as usize) as u64,
+    };
+}
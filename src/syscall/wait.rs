@@ -0,0 +1,101 @@
+use crate::interupts::ExceptionContext;
+use crate::scheduler;
+use crate::scheduler::task_context::WaitCondition;
+use crate::scheduler::ChildKind;
+use crate::syscall::*;
+use crate::utils::ONLY_MSB_OF_USIZE;
+
+/// Return immediately if no child has finished yet
+pub const WNOHANG: usize = 1;
+
+const ANY_CHILD: usize = !0usize;
+
+#[repr(usize)]
+#[derive(FromPrimitive, ToPrimitive, Debug)]
+pub enum WaitError {
+    /// Task has no (matching) children that could be waited for
+    NoSuchChild,
+    /// Child exists, but has not finished yet (only with WNOHANG)
+    NotFinished,
+    /// Task was resumed to run signal handler before syscall could complete
+    Interrupted,
+}
+
+fn wait_syscall(pid: usize, options: usize) -> Result<(u64, u32), WaitError> {
+    let mut status: u32 = 0;
+    let val = unsafe {
+        syscall3(
+            pid,
+            &mut status as *mut u32 as usize,
+            options,
+            Syscalls::Wait as usize,
+        )
+    };
+    if val & ONLY_MSB_OF_USIZE > 0 {
+        Err(
+            WaitError::from_usize(val & !ONLY_MSB_OF_USIZE).unwrap_or_else(|| {
+                panic!(
+                    "Unknown error during waiting for child: {}",
+                    val & !ONLY_MSB_OF_USIZE
+                )
+            }),
+        )
+    } else {
+        Ok((val as u64, status))
+    }
+}
+
+/// Suspends task until any of its children finishes, returns its pid and return value
+pub fn wait() -> Result<(u64, u32), WaitError> {
+    wait_syscall(ANY_CHILD, 0)
+}
+
+/// Suspends task until child with given pid finishes, returns its pid and return value
+pub fn waitpid(pid: u64) -> Result<(u64, u32), WaitError> {
+    wait_syscall(pid as usize, 0)
+}
+
+/// Checks if child with given pid (or any child if None) has finished, without suspending
+pub fn try_waitpid(pid: Option<u64>) -> Result<Option<(u64, u32)>, WaitError> {
+    let pid = pid.map_or(ANY_CHILD, |pid| pid as usize);
+    match wait_syscall(pid, WNOHANG) {
+        Ok(value) => Ok(Some(value)),
+        Err(WaitError::NotFinished) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn handle_wait(context: &mut ExceptionContext) {
+    let pid = match context.gpr[0] as usize {
+        ANY_CHILD => None,
+        pid => Some(pid),
+    };
+    let status = context.gpr[1] as *mut u32;
+    let no_hang = context.gpr[2] as usize & WNOHANG != 0;
+
+    loop {
+        match scheduler::reap_child_task(pid, ChildKind::Process) {
+            Ok(Some((child_pid, return_value))) => {
+                if !status.is_null() {
+                    unsafe { *status = return_value };
+                }
+                context.gpr[0] = child_pid as u64;
+                return;
+            }
+            Ok(None) if no_hang => {
+                context.gpr[0] = (ONLY_MSB_OF_USIZE | WaitError::NotFinished as usize) as u64;
+                return;
+            }
+            Ok(None) => {
+                if scheduler::suspend_current_task(WaitCondition::Child(pid)).is_err() {
+                    context.gpr[0] = (ONLY_MSB_OF_USIZE | WaitError::Interrupted as usize) as u64;
+                    return;
+                }
+            }
+            Err(_) => {
+                context.gpr[0] = (ONLY_MSB_OF_USIZE | WaitError::NoSuchChild as usize) as u64;
+                return;
+            }
+        }
+    }
+}
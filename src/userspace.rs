@@ -3,9 +3,12 @@ mod print;
 use core::sync::atomic::AtomicU64;
 
 mod neofetch;
+pub mod sync;
+pub mod yutex;
 use crate::{uprint, uprintln};
 use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::sync::atomic::Ordering;
 
 #[no_mangle]
@@ -74,8 +77,8 @@ pub extern "C" fn simple_cat(argc: usize, argv: *const &[u8]) -> u32 {
 #[no_mangle]
 #[inline(never)]
 pub extern "C" fn simple_wc(argc: usize, argv: *const &[u8]) -> u32 {
-    use crate::syscall::files::File;
-    use crate::syscall::*;
+    use crate::syscall::files::poll::{poll, PollFd, POLL_IN};
+    use crate::syscall::files::{File, STDIN};
     use alloc::vec::Vec;
     use core::str::from_utf8;
 
@@ -101,8 +104,8 @@ pub extern "C" fn simple_wc(argc: usize, argv: *const &[u8]) -> u32 {
             Ok(res) => {
                 if res > 0 {
                     result.extend_from_slice(&buffer);
-                } else {
-                    yield_cpu();
+                } else if poll(&mut [PollFd::new(STDIN, POLL_IN)], None).is_err() {
+                    break;
                 }
             }
             Err(_) => break,
@@ -188,6 +191,7 @@ pub extern "C" fn B(_argc: usize, _argv: *const &[u8]) -> u32 {
 }
 
 pub extern "C" fn test_async_files(_argc: usize, _argv: *const &[u8]) -> u32 {
+    use crate::syscall::asynchronous::registered_buffers::with_registered_buffer;
     use crate::syscall::files::File;
     use crate::syscall::*;
     use crate::utils::ONLY_MSB_OF_USIZE;
@@ -197,49 +201,51 @@ pub extern "C" fn test_async_files(_argc: usize, _argv: *const &[u8]) -> u32 {
     let submission_buffer = get_async_submission_buffer();
     let completion_buffer = get_async_completion_buffer();
 
-    let mut str_buffer = [0u8; 20];
-    let mut str_buffer1 = [0u8; 20];
-
-    File::async_open("file1", true, 1, submission_buffer)
-        .then_read(
-            20,
-            &mut str_buffer as *mut [u8] as *mut u8,
-            2,
-            submission_buffer,
-        )
-        .then_seek(-15, vfs::SeekType::FromCurrent, 3, submission_buffer)
-        .then_write(b"<Added>", 4, submission_buffer)
-        .then_seek(2, vfs::SeekType::FromBeginning, 5, submission_buffer)
-        .then_read(
-            20,
-            &mut str_buffer1 as *mut [u8] as *mut u8,
-            6,
-            submission_buffer,
-        )
-        .then_close(7, submission_buffer);
-
-    asynchronous::async_print::async_print("Hello world!", 69, submission_buffer);
-
-    loop {
-        match asynchronous::async_syscall::get_syscall_returned_value(completion_buffer) {
-            Some(val) => {
-                uprintln!(
-                    "Received response for id: {} - {} : {}",
-                    val.id,
-                    val.value,
-                    val.value & !ONLY_MSB_OF_USIZE
-                );
-                if val.id == 7 {
-                    let string = from_utf8(&str_buffer).unwrap();
-                    uprintln!("1st Read_value: {}", string);
-                    let string = from_utf8(&str_buffer1).unwrap();
-                    uprintln!("2nd Read_value: {}", string);
-                    loop {}
+    let mut str_buffer = [0u8; 40];
+    let mut added = *b"<Added>";
+    with_registered_buffer(&mut str_buffer, |str_buffer| {
+        with_registered_buffer(&mut added, |added| {
+            let file = File::async_open("file1", true, 1, submission_buffer).unwrap();
+            file.then_read_fixed(str_buffer, 0, 20, 2, submission_buffer)
+                .and_then(|file| {
+                    file.then_seek(-15, vfs::SeekType::FromCurrent, 3, submission_buffer)
+                })
+                .and_then(|file| file.then_write_fixed(added, 0, added.len(), 4, submission_buffer))
+                .and_then(|file| {
+                    file.then_seek(2, vfs::SeekType::FromBeginning, 5, submission_buffer)
+                })
+                .and_then(|file| file.then_read_fixed(str_buffer, 20, 20, 6, submission_buffer))
+                .and_then(|file| file.then_close(7, submission_buffer))
+                .unwrap();
+
+            asynchronous::async_print::async_print("Hello world!", 69, submission_buffer).unwrap();
+
+            loop {
+                asynchronous::async_enter::async_enter(1, None);
+                if let Some(val) =
+                    asynchronous::async_syscall::get_syscall_returned_value(completion_buffer)
+                {
+                    uprintln!(
+                        "Received response for id: {} - {} : {}",
+                        val.id,
+                        val.value,
+                        val.value & !ONLY_MSB_OF_USIZE
+                    );
+                    if val.id == 7 {
+                        break;
+                    }
                 }
             }
-            None => (),
-        };
-    }
+        })
+    })
+    .and_then(|result| result)
+    .unwrap();
+
+    let string = from_utf8(&str_buffer[..20]).unwrap();
+    uprintln!("1st Read_value: {}", string);
+    let string = from_utf8(&str_buffer[20..]).unwrap();
+    uprintln!("2nd Read_value: {}", string);
+    loop {}
 }
 
 #[no_mangle]
@@ -255,8 +261,140 @@ pub extern "C" fn _loop(_: usize, _: *const &[u8]) -> u32 {
     loop {}
 }
 
+/// Prints realtime clock as UTC date, `date -s seconds` sets it to given Unix time
+pub extern "C" fn date(argc: usize, argv: *const &[u8]) -> u32 {
+    use crate::syscall::clock::{clock_gettime, clock_settime, Clock};
+    use core::str::from_utf8;
+    use core::time::Duration;
+
+    let args = unsafe { core::slice::from_raw_parts(argv, argc) };
+    match args {
+        [] => (),
+        [option, seconds] if *option == b"-s" => {
+            let seconds = match from_utf8(seconds).ok().and_then(|s| s.parse::<u64>().ok()) {
+                Some(seconds) => seconds,
+                None => {
+                    uprintln!("Invalid number of seconds");
+                    return 2;
+                }
+            };
+            if let Err(e) = clock_settime(Clock::Realtime, Duration::from_secs(seconds)) {
+                uprintln!("date: {:?}", e);
+                return 3;
+            }
+        }
+        _ => {
+            uprintln!("Usage: date [-s unix_seconds]");
+            return 1;
+        }
+    }
+    let seconds = match clock_gettime(Clock::Realtime) {
+        Ok(time) => time.as_secs(),
+        Err(e) => {
+            uprintln!("date: {:?}", e);
+            return 4;
+        }
+    };
+    // civil date from days since epoch, by Howard Hinnant's algorithm
+    let days = (seconds / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    uprintln!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+    0
+}
+
+pub extern "C" fn kill(argc: usize, argv: *const &[u8]) -> u32 {
+    use crate::scheduler::signals::Signal;
+    use crate::syscall::signals;
+    use core::str::from_utf8;
+    use num_traits::FromPrimitive;
+
+    let args = unsafe { core::slice::from_raw_parts(argv, argc) };
+    let (signal, pid) = match args {
+        [pid] => (Some(Signal::Terminate), pid),
+        [signal, pid] => (
+            from_utf8(signal)
+                .ok()
+                .filter(|signal| signal.starts_with('-'))
+                .and_then(|signal| signal[1..].parse::<usize>().ok())
+                .and_then(Signal::from_usize),
+            pid,
+        ),
+        _ => {
+            uprintln!("Usage: kill [-signal] pid");
+            return 1;
+        }
+    };
+    let signal = match signal {
+        Some(signal) => signal,
+        None => {
+            uprintln!("Invalid signal");
+            return 2;
+        }
+    };
+    let pid = match from_utf8(pid).ok().and_then(|pid| pid.parse::<u64>().ok()) {
+        Some(pid) => pid,
+        None => {
+            uprintln!("Invalid pid");
+            return 3;
+        }
+    };
+    match signals::kill(pid, signal) {
+        Ok(()) => 0,
+        Err(e) => {
+            uprintln!("kill: {:?}", e);
+            4
+        }
+    }
+}
+
+/// Loads executable given as first argument, used by shell for programs stored in files
+pub extern "C" fn run_executable(argc: usize, argv: *const &[u8]) -> u32 {
+    use crate::syscall::exec::exec;
+    use core::str::from_utf8;
+
+    let args = unsafe { core::slice::from_raw_parts(argv, argc) };
+    let args: Option<Vec<&str>> = args.iter().map(|arg| from_utf8(arg).ok()).collect();
+    let args = match args {
+        Some(args) if !args.is_empty() => args,
+        _ => {
+            uprintln!("Expected valid utf8 executable name");
+            return 1;
+        }
+    };
+    let error = exec(args[0], &args[1..]);
+    uprintln!("{}: {:?}", args[0], error);
+    127
+}
+
 pub extern "C" fn pwd(_: usize, _: *const &[u8]) -> u32 {
-    uprintln!("/");
+    uprintln!("{}", crate::syscall::files::directory::getcwd());
+    0
+}
+/// Prints environment that task was started with
+pub extern "C" fn env(argc: usize, argv: *const &[u8]) -> u32 {
+    for entry in unsafe { crate::syscall::environment::initial_environment(argc, argv) } {
+        uprintln!("{}", core::str::from_utf8(entry).unwrap_or("<invalid utf8>"));
+    }
     0
 }
 pub extern "C" fn clear(_: usize, _: *const &[u8]) -> u32 {
@@ -271,7 +409,7 @@ pub mod shell;
 
 type Program = (&'static str, extern "C" fn(usize, *const &[u8]) -> u32);
 
-const PROGRAMS: [Program; 12] = [
+const PROGRAMS: [Program; 16] = [
     ("ush", ushell),
     ("loop", _loop),
     ("first_task", first_task),
@@ -281,9 +419,12 @@ const PROGRAMS: [Program; 12] = [
     ("true", _true),
     ("false", _false),
     ("pwd", pwd),
+    ("env", env),
     ("clear", clear),
     ("neofetch", neofetch),
     ("hello_world", hello_world),
+    ("kill", kill),
+    ("date", date),
 ];
 
 pub extern "C" fn ushell(argc: usize, argv: *const &[u8]) -> u32 {
@@ -1,14 +1,23 @@
-use crate::syscall::files::File;
-use crate::syscall::{
-    create_task, get_child_return_value, get_pid, set_pipe_read_on_pid, yield_cpu,
-};
+use crate::syscall::clock::{clock_gettime, Clock};
+use crate::syscall::environment::{getenv, setenv, unsetenv};
+use crate::syscall::files::directory::{chdir, getcwd};
+use crate::syscall::files::poll::{poll, PollFd, POLL_IN};
+use crate::syscall::files::{close::close, create::create, dup, File, STDIN, STDOUT};
+use crate::syscall::rlimit::{getrlimit, setrlimit, Resource, RLIMIT_INFINITY};
+use crate::syscall::rusage::{getrusage, ResourceUsage, UsageTarget};
+use crate::scheduler::special_return_vals::KILLED_BY_SIGNAL;
+use crate::syscall::wait::{try_waitpid, waitpid};
+use crate::syscall::{create_task, get_pid, set_pipe_read_on_pid};
 use crate::{euprintln, uprint, uprintln};
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::time::Duration;
 #[derive(Debug)]
 enum ParseError {
     UnknownProgram(String),
     QuoteUnclosed,
+    MissingRedirectionTarget,
+    RedirectionFailed(String),
 }
 
 type ErrorCode = u32;
@@ -19,6 +28,8 @@ const UTF8_ERROR: u32 = 20;
 
 const READ_ERROR: u32 = 30;
 
+const WAIT_ERROR: u32 = 40;
+
 pub(super) fn shell_impl(_args: &[&[u8]]) -> Result<(), ErrorCode> {
     let mut command_line = String::new();
     let stdin: File = File::get_stdin();
@@ -31,7 +42,7 @@ pub(super) fn shell_impl(_args: &[&[u8]]) -> Result<(), ErrorCode> {
     'main_loop: loop {
         let read_count = stdin.read(4096, buffer).map_err(|_| READ_ERROR)?;
         if read_count == 0 {
-            yield_cpu();
+            poll(&mut [PollFd::new(STDIN, POLL_IN)], None).map_err(|_| READ_ERROR)?;
         }
         let read_bytes = &mut buffer[..read_count];
 
@@ -44,6 +55,7 @@ pub(super) fn shell_impl(_args: &[&[u8]]) -> Result<(), ErrorCode> {
             add_new_input(&mut command_line, rest_of_line);
             run_commands(command_line.trim());
             command_line.clear();
+            reap_background_jobs();
             print_prompt();
         }
         if unparsed_string.len() > 0 {
@@ -149,13 +161,30 @@ impl ShellSplit for str {
 fn run_commands(command_line: &str) -> Result<ErrorCode, ParseError> {
     let command_chain = command_line.shell_split("\'\"".chars(), ";".chars())?;
     for base_cmd in command_chain {
+        let (base_cmd, background) = if base_cmd.ends_with('&') {
+            (&base_cmd[..base_cmd.len() - 1], true)
+        } else {
+            (base_cmd, false)
+        };
+        // `time command` reports how long the foreground pipeline took
+        let (base_cmd, timer) = match base_cmd.strip_prefix("time ") {
+            Some(timed_cmd) if !background => (timed_cmd.trim(), Some(PipelineTimer::start())),
+            _ => (base_cmd, None),
+        };
         let indivdual_comands = base_cmd.shell_split("\'\"".chars(), "|".chars())?;
+        let commands_count = indivdual_comands.len();
+        if commands_count == 1 && run_builtin(indivdual_comands[0])? {
+            continue;
+        }
 
         let my_pid = get_pid() as u64;
         let mut input_source_pid = my_pid;
+        let mut children = Vec::<Pid>::new();
 
-        for command in indivdual_comands {
-            let pid = match run_command(command, input_source_pid) {
+        for (index, command) in indivdual_comands.into_iter().enumerate() {
+            // output of background job goes directly to stdout
+            let stdout_to_pipe = !background || index + 1 < commands_count;
+            let pid = match run_command(command, input_source_pid, stdout_to_pipe) {
                 Ok(pid) => pid,
                 Err(parse_error) => {
                     euprintln!("Shell Error: {:?}", parse_error);
@@ -163,8 +192,13 @@ fn run_commands(command_line: &str) -> Result<ErrorCode, ParseError> {
                 }
             };
             input_source_pid = pid;
+            children.push(pid);
         }
-        if input_source_pid != my_pid {
+        if background {
+            for child_pid in children.iter() {
+                uprintln!("[{}]", child_pid);
+            }
+        } else if input_source_pid != my_pid {
             set_pipe_read_on_pid(input_source_pid);
             let end = File::get_pipein();
 
@@ -180,38 +214,307 @@ fn run_commands(command_line: &str) -> Result<ErrorCode, ParseError> {
                 crate::uprint!("{}", unparsed_string);
             }
 
+            for &child_pid in children.iter().filter(|&&pid| pid != input_source_pid) {
+                await_child(child_pid);
+            }
             let ret_val = await_child(input_source_pid);
-            crate::euprintln!("Process Exited with code {}", ret_val);
+            print_exit_status(ret_val);
+        }
+        if let Some(timer) = timer {
+            timer.report();
         }
     }
     Ok(0)
 }
-fn run_command(command: &str, input_source_pid: u64) -> Result<Pid, ParseError> {
+/// Runs commands that change state of the shell itself, returns false for other commands
+fn run_builtin(command: &str) -> Result<bool, ParseError> {
     let words = command.shell_split("\'\"".chars(), " ".chars())?;
+    match words.as_slice() {
+        ["cd"] => {
+            let home = getenv("HOME").unwrap_or_else(|| String::from("/"));
+            if let Err(error) = chdir(&home) {
+                euprintln!("cd: {}: {:?}", home, error);
+            }
+        }
+        ["cd", path] => {
+            if let Err(error) = chdir(path) {
+                euprintln!("cd: {}: {:?}", path, error);
+            }
+        }
+        ["export", assignments @ ..] => {
+            for assignment in assignments {
+                let mut parts = assignment.splitn(2, '=');
+                let name = parts.next().unwrap_or("");
+                let value = parts.next().unwrap_or("");
+                if let Err(error) = setenv(name, value) {
+                    euprintln!("export: {}: {:?}", assignment, error);
+                }
+            }
+        }
+        ["unset", names @ ..] => {
+            for name in names {
+                if let Err(error) = unsetenv(name) {
+                    euprintln!("unset: {}: {:?}", name, error);
+                }
+            }
+        }
+        ["ulimit", options @ ..] => ulimit(options),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Options of `ulimit` builtin selecting resource
+const ULIMIT_RESOURCES: [(&str, Resource, &str); 5] = [
+    ("-u", Resource::Children, "children"),
+    ("-n", Resource::OpenFiles, "open files"),
+    ("-d", Resource::HeapBytes, "heap bytes"),
+    ("-s", Resource::StackSize, "stack bytes"),
+    ("-t", Resource::CpuSeconds, "cpu seconds"),
+];
+
+fn format_limit(limit: u64) -> String {
+    if limit == RLIMIT_INFINITY {
+        String::from("unlimited")
+    } else {
+        format!("{}", limit)
+    }
+}
+
+/// `ulimit [-H|-S] [-a|-u|-n|-d|-s|-t] [limit|unlimited]` shows or sets limits of the shell,
+/// which are inherited by started programs. Both limits are set unless -H or -S is given.
+fn ulimit(options: &[&str]) {
+    let (mut hard, mut soft, mut all) = (false, false, false);
+    let mut selected = None;
+    let mut value = None;
+    for &option in options {
+        match option {
+            "-H" => hard = true,
+            "-S" => soft = true,
+            "-a" => all = true,
+            "unlimited" => value = Some(RLIMIT_INFINITY),
+            _ => match ULIMIT_RESOURCES.iter().find(|(flag, _, _)| *flag == option) {
+                Some(entry) => selected = Some(entry),
+                None => match option.parse() {
+                    Ok(limit) => value = Some(limit),
+                    Err(_) => {
+                        euprintln!("ulimit: invalid option: {}", option);
+                        return;
+                    }
+                },
+            },
+        }
+    }
+
+    if all || (selected.is_none() && value.is_none()) {
+        for &(flag, resource, name) in ULIMIT_RESOURCES.iter() {
+            if let Ok(limit) = getrlimit(resource) {
+                let limit = if hard { limit.hard } else { limit.soft };
+                uprintln!("{:<12} ({}) {}", name, flag, format_limit(limit));
+            }
+        }
+        return;
+    }
+    let &(_, resource, name) = match selected {
+        Some(entry) => entry,
+        None => {
+            euprintln!("ulimit: resource option is required to set limit");
+            return;
+        }
+    };
+    let mut limit = match getrlimit(resource) {
+        Ok(limit) => limit,
+        Err(error) => {
+            euprintln!("ulimit: {}: {:?}", name, error);
+            return;
+        }
+    };
+    match value {
+        None => uprintln!("{}", format_limit(if hard { limit.hard } else { limit.soft })),
+        Some(value) => {
+            if hard || !soft {
+                limit.hard = value;
+            }
+            if soft || !hard {
+                limit.soft = value;
+            }
+            if let Err(error) = setrlimit(resource, limit) {
+                euprintln!("ulimit: {}: {:?}", name, error);
+            }
+        }
+    }
+}
+
+/// Splits `< input` and `> output` out of command words
+fn parse_redirections<'a>(
+    words: &[&'a str],
+) -> Result<(Vec<&'a str>, Option<&'a str>, Option<&'a str>), ParseError> {
+    let mut arguments = Vec::new();
+    let mut input = None;
+    let mut output = None;
+    let mut words = words.iter().filter(|word| !word.is_empty());
+    while let Some(&word) = words.next() {
+        match word {
+            "<" => input = Some(*words.next().ok_or(ParseError::MissingRedirectionTarget)?),
+            ">" => output = Some(*words.next().ok_or(ParseError::MissingRedirectionTarget)?),
+            _ => arguments.push(word),
+        }
+    }
+    Ok((arguments, input, output))
+}
+
+fn open_redirection(filename: &str, with_write: bool) -> Result<File, ParseError> {
+    if with_write {
+        // file may already exist, opening decides if it can be used
+        let _ = create(filename);
+    }
+    File::open(filename, with_write).map_err(|_| ParseError::RedirectionFailed(filename.into()))
+}
+
+/// Points standard descriptors to given files while spawning task, so it inherits them,
+/// then restores descriptors of the shell
+fn spawn_redirected<F>(redirections: &[(usize, &File)], spawn: F) -> Result<Pid, ParseError>
+where
+    F: FnOnce() -> Pid,
+{
+    let mut saved = Vec::new();
+    for &(fd, file) in redirections {
+        let redirected = dup::dup(fd).and_then(|saved_fd| {
+            saved.push((fd, saved_fd));
+            file.redirect(fd)
+        });
+        if redirected.is_err() {
+            restore_descriptors(saved);
+            return Err(ParseError::RedirectionFailed(format!("descriptor {}", fd)));
+        }
+    }
+    let child_pid = spawn();
+    restore_descriptors(saved);
+    Ok(child_pid)
+}
+
+fn restore_descriptors(saved: Vec<(usize, usize)>) {
+    for (fd, saved_fd) in saved.into_iter().rev() {
+        let _ = dup::dup2(saved_fd, fd);
+        let _ = close(saved_fd);
+    }
+}
+
+fn run_command(
+    command: &str,
+    input_source_pid: u64,
+    stdout_to_pipe: bool,
+) -> Result<Pid, ParseError> {
+    let words = command.shell_split("\'\"".chars(), " ".chars())?;
+    let (words, input, output) = parse_redirections(&words)?;
+    if words.is_empty() {
+        return Err(ParseError::UnknownProgram(String::new()));
+    }
     let (head, tail) = words.split_at(1);
     let command_name = head[0];
 
-    // let bytes: Vec<&[u8]> = tail.iter().map(|string| string.as_bytes()).collect();
+    let function = super::PROGRAMS
+        .iter()
+        .find(|&&(name, _)| name == command_name)
+        .map(|&(_, function)| function);
+    if function.is_none() {
+        match File::open(command_name, false) {
+            Ok(file) => {
+                file.close();
+            }
+            Err(_) => return Err(ParseError::UnknownProgram(command_name.into())),
+        }
+    }
+
+    let input_file = match input {
+        Some(filename) => Some(open_redirection(filename, false)?),
+        None => None,
+    };
+    let output_file = match output {
+        Some(filename) => Some(open_redirection(filename, true)?),
+        None => None,
+    };
+
+    let mut redirections = Vec::new();
+    if let Some(file) = &input_file {
+        redirections.push((STDIN, file));
+    }
+    if let Some(file) = &output_file {
+        redirections.push((STDOUT, file));
+    }
+    // redirected streams are not connected to pipes
+    let stdout_to_pipe = stdout_to_pipe && output_file.is_none();
+    let input_source_pid = match input_file {
+        Some(_) => None,
+        None => Some(input_source_pid),
+    };
+
+    let child_pid = spawn_redirected(&redirections, || match function {
+        Some(function) => create_task(function, tail, stdout_to_pipe, input_source_pid),
+        None => create_task(
+            super::run_executable,
+            &words,
+            stdout_to_pipe,
+            input_source_pid,
+        ),
+    });
+
+    // child keeps its own references to redirected files
+    for file in input_file.iter().chain(output_file.iter()) {
+        let _ = file.close();
+    }
+    child_pid
+}
+
+/// Wall time and CPU time of children, measured by `time` builtin
+struct PipelineTimer {
+    wall_start: Duration,
+    children_start: ResourceUsage,
+}
+
+impl PipelineTimer {
+    fn start() -> Self {
+        PipelineTimer {
+            wall_start: clock_gettime(Clock::Monotonic).unwrap_or_default(),
+            children_start: getrusage(UsageTarget::Children).unwrap_or_default(),
+        }
+    }
 
-    for &(name, function) in super::PROGRAMS.iter() {
-        if name == command_name {
-            let child_pid = create_task(function, tail, true, Some(input_source_pid));
-            return Ok(child_pid);
+    fn report(self) {
+        let wall = clock_gettime(Clock::Monotonic).unwrap_or_default() - self.wall_start;
+        let children = getrusage(UsageTarget::Children).unwrap_or_default();
+        let user = children.user_time - self.children_start.user_time;
+        let system = children.system_time - self.children_start.system_time;
+        for (name, time) in [("real", wall), ("user", user), ("sys ", system)].iter() {
+            uprintln!("{} {}.{:03}s", name, time.as_secs(), time.subsec_millis());
         }
     }
-    Err(ParseError::UnknownProgram(command_name.into()))
 }
 
 fn await_child(child_pid: u64) -> u32 {
-    loop {
-        if let Some(ret) = get_child_return_value(child_pid) {
-            return ret;
-        } else {
-            yield_cpu();
-        }
+    match waitpid(child_pid) {
+        Ok((_, ret)) => ret,
+        Err(_) => WAIT_ERROR,
+    }
+}
+
+fn print_exit_status(ret_val: u32) {
+    if ret_val & !0xff == KILLED_BY_SIGNAL {
+        crate::euprintln!("Process killed by signal {}", ret_val & 0xff);
+    } else {
+        crate::euprintln!("Process Exited with code {}", ret_val);
+    }
+}
+
+/// Collects return values of finished background jobs
+fn reap_background_jobs() {
+    while let Ok(Some((pid, ret_val))) = try_waitpid(None) {
+        uprint!("[{}] Done: ", pid);
+        print_exit_status(ret_val);
     }
 }
 
 fn print_prompt() {
-    uprint!("\u{1FA90} default@uranos | \u{1F5C1}  / > ");
+    let user = getenv("USER").unwrap_or_else(|| String::from("default"));
+    uprint!("\u{1FA90} {}@uranos | \u{1F5C1}  {} > ", user, getcwd());
 }
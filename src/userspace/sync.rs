@@ -0,0 +1,17 @@
+//! Blocking synchronisation primitives for userspace tasks and threads. Waiting tasks are
+//! suspended by kernel with futex_wait instead of spinning.
+
+pub mod barrier;
+pub mod condvar;
+pub mod once;
+pub mod rwlock;
+pub mod semaphore;
+
+pub use barrier::Barrier;
+pub use condvar::Condvar;
+pub use once::OnceCell;
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::Semaphore;
+
+/// Count passed to futex_wake to resume all waiters
+const WAKE_ALL: usize = usize::MAX;
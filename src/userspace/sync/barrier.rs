@@ -0,0 +1,39 @@
+use super::WAKE_ALL;
+use crate::syscall::futex::{futex_wait, futex_wake};
+use core::sync::atomic::{AtomicU32, Ordering};
+use uranos_sync::barrier::{released, releases};
+
+/// Lets given count of tasks wait until all of them reach it, can be reused afterwards
+pub struct Barrier {
+    count: u32,
+    arrived: AtomicU32,
+    /// Incremented when all tasks arrive, waiters sleep until it changes
+    generation: AtomicU32,
+}
+
+impl Barrier {
+    pub const fn new(count: u32) -> Self {
+        Barrier {
+            count,
+            arrived: AtomicU32::new(0),
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    /// Suspends task until count tasks call wait, returns true in exactly one of them
+    pub fn wait(&self) -> bool {
+        let generation = self.generation.load(Ordering::Acquire);
+        let arrived_before = self.arrived.fetch_add(1, Ordering::AcqRel);
+        if releases(arrived_before, self.count) {
+            // no task of next generation can arrive before waiters are released
+            self.arrived.store(0, Ordering::Relaxed);
+            self.generation.fetch_add(1, Ordering::Release);
+            futex_wake(&self.generation, WAKE_ALL).ok();
+            return true;
+        }
+        while !released(generation, self.generation.load(Ordering::Acquire)) {
+            futex_wait(&self.generation, generation).ok();
+        }
+        false
+    }
+}
@@ -0,0 +1,64 @@
+use super::WAKE_ALL;
+use crate::syscall::futex::{futex_wait, futex_wake};
+use crate::userspace::yutex::YutexGuard;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Condition variable used together with Yutex
+pub struct Condvar {
+    /// Changed by every notification, so waiters do not miss ones sent after unlocking
+    sequence: AtomicU32,
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Condvar {
+            sequence: AtomicU32::new(0),
+        }
+    }
+
+    /// Unlocks yutex and suspends task until notification, then locks yutex again.
+    /// Task may be resumed spuriously, so condition should be checked in a loop.
+    pub fn wait<'a, T>(&self, guard: YutexGuard<'a, T>) -> YutexGuard<'a, T> {
+        let yutex = YutexGuard::yutex(&guard);
+        let sequence = self.sequence.load(Ordering::Acquire);
+        drop(guard);
+        futex_wait(&self.sequence, sequence).ok();
+        yutex.lock()
+    }
+
+    /// Waits until condition is false, returns guard of yutex locked with condition checked
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: YutexGuard<'a, T>,
+        mut condition: F,
+    ) -> YutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Resumes one waiting task
+    pub fn notify_one(&self) {
+        self.notify(1);
+    }
+
+    /// Resumes all waiting tasks
+    pub fn notify_all(&self) {
+        self.notify(WAKE_ALL);
+    }
+
+    fn notify(&self, count: usize) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        futex_wake(&self.sequence, count).ok();
+    }
+}
@@ -0,0 +1,120 @@
+use super::WAKE_ALL;
+use crate::syscall::futex::{futex_wait, futex_wake};
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use uranos_sync::once::{
+    access, contended, wakes_waiting, Access, CONTENDED, EMPTY, INITIALIZING, POISONED, READY,
+};
+
+/// Cell that is written once, tasks reading it during initialization wait for the value.
+/// Initializer that unwinds poisons the cell, so waiting tasks panic instead of sleeping forever.
+pub struct OnceCell<T> {
+    state: AtomicU32,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Poisons cell when dropped before initialization finishes
+struct Initialization<'a, T> {
+    cell: &'a OnceCell<T>,
+}
+
+impl<'a, T> Drop for Initialization<'a, T> {
+    fn drop(&mut self) {
+        self.cell.leave(POISONED);
+    }
+}
+
+impl<T> OnceCell<T> {
+    pub const fn new() -> Self {
+        OnceCell {
+            state: AtomicU32::new(EMPTY),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Value, if it was already initialized
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == READY {
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Value, initialized with f by first caller. Other callers wait until it finishes.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        let mut f = Some(f);
+        loop {
+            let state = self
+                .state
+                .compare_and_swap(EMPTY, INITIALIZING, Ordering::Acquire);
+            match access(state) {
+                Access::Initialize => {
+                    let initialization = Initialization { cell: self };
+                    let value = (f.take().unwrap())();
+                    core::mem::forget(initialization);
+                    self.finish(value);
+                }
+                Access::Read => return self.get().unwrap(),
+                Access::Wait => self.wait(state),
+                Access::Poisoned => panic!("OnceCell initialization did not finish"),
+            }
+        }
+    }
+
+    /// Initializes cell, returns value back if it was already initialized
+    pub fn set(&self, value: T) -> Result<(), T> {
+        loop {
+            let state = self
+                .state
+                .compare_and_swap(EMPTY, INITIALIZING, Ordering::Acquire);
+            match access(state) {
+                Access::Initialize => {
+                    self.finish(value);
+                    return Ok(());
+                }
+                Access::Read => return Err(value),
+                Access::Wait => self.wait(state),
+                Access::Poisoned => panic!("OnceCell initialization did not finish"),
+            }
+        }
+    }
+
+    fn finish(&self, value: T) {
+        unsafe { *self.value.get() = Some(value) };
+        self.leave(READY);
+    }
+
+    /// Ends initialization with given state, waking tasks waiting for it
+    fn leave(&self, state: u32) {
+        if wakes_waiting(self.state.swap(state, Ordering::Release)) {
+            futex_wake(&self.state, WAKE_ALL).ok();
+        }
+    }
+
+    /// Sleeps until initializing task finishes
+    fn wait(&self, state: u32) {
+        if let Some(new_state) = contended(state) {
+            if self
+                .state
+                .compare_and_swap(state, new_state, Ordering::Relaxed)
+                != state
+            {
+                return;
+            }
+        }
+        futex_wait(&self.state, CONTENDED).ok();
+    }
+}
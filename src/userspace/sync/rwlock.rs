@@ -0,0 +1,131 @@
+use super::WAKE_ALL;
+use crate::syscall::futex::{futex_wait, futex_wake};
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+use core::ops::DerefMut;
+use core::sync::atomic::{AtomicU32, Ordering};
+use uranos_sync::rwlock::{
+    read_locked, read_unlocked, waited_for, wakes_waiting, write_locked, write_unlocked, WRITER,
+};
+
+/// Reader-writer lock, many readers or single writer can hold it at once.
+/// Readers are not stopped by waiting writers, so writers can starve.
+pub struct RwLock<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+unsafe impl<T: Send> Send for RwLock<T> {}
+
+/// RAII shared lock guard for RwLock type
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/// RAII exclusive lock guard for RwLock type
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    fn deref(&'_ self) -> &'_ T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.read_unlock();
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    fn deref(&'_ self) -> &'_ T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&'_ mut self) -> &'_ mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.write_unlock();
+    }
+}
+
+impl<T> RwLock<T> {
+    pub const fn new(data: T) -> Self {
+        RwLock {
+            state: AtomicU32::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Takes shared lock, suspends task while writer holds it
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        self.take(read_locked);
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Takes exclusive lock, suspends task while anyone holds it
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        self.take(write_locked);
+        RwLockWriteGuard { lock: self }
+    }
+
+    fn take<F>(&self, locked: F)
+    where
+        F: Fn(u32) -> Option<u32>,
+    {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            let new_state = match locked(state) {
+                Some(new_state) => new_state,
+                None => {
+                    // Mark lock as waited for, so last holder wakes us when releasing it
+                    let waiting = waited_for(state);
+                    let previous = self
+                        .state
+                        .compare_and_swap(state, waiting, Ordering::Relaxed);
+                    if previous == state {
+                        futex_wait(&self.state, waiting).ok();
+                    }
+                    state = self.state.load(Ordering::Relaxed);
+                    continue;
+                }
+            };
+            let previous = self
+                .state
+                .compare_and_swap(state, new_state, Ordering::Acquire);
+            if previous == state {
+                return;
+            }
+            state = previous;
+        }
+    }
+
+    fn read_unlock(&self) {
+        let state = read_unlocked(self.state.fetch_sub(1, Ordering::Release));
+        if wakes_waiting(state) {
+            self.wake_waiting(state);
+        }
+    }
+
+    fn write_unlock(&self) {
+        let state = write_unlocked(self.state.fetch_and(!WRITER, Ordering::Release));
+        if wakes_waiting(state) {
+            self.wake_waiting(state);
+        }
+    }
+
+    /// Resumes all waiting tasks after lock became free, they race for it again
+    fn wake_waiting(&self, state: u32) {
+        if self.state.compare_and_swap(state, 0, Ordering::Relaxed) == state {
+            futex_wake(&self.state, WAKE_ALL).ok();
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::syscall::futex::{futex_wait, futex_wake};
+use core::sync::atomic::{AtomicU32, Ordering};
+use uranos_sync::semaphore::decremented;
+
+/// Counting semaphore, acquire suspends task while count is 0
+pub struct Semaphore {
+    count: AtomicU32,
+    /// Tasks that may sleep in acquire, release skips syscall when there are none
+    waiters: AtomicU32,
+}
+
+impl Semaphore {
+    pub const fn new(count: u32) -> Self {
+        Semaphore {
+            count: AtomicU32::new(count),
+            waiters: AtomicU32::new(0),
+        }
+    }
+
+    /// Takes permit if one is available
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        while let Some(new_count) = decremented(count) {
+            let previous = self
+                .count
+                .compare_and_swap(count, new_count, Ordering::Acquire);
+            if previous == count {
+                return true;
+            }
+            count = previous;
+        }
+        false
+    }
+
+    /// Takes permit, suspends task until one is released if there are none
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            self.waiters.fetch_add(1, Ordering::SeqCst);
+            futex_wait(&self.count, 0).ok();
+            self.waiters.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns permit, resuming one waiting task
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            futex_wake(&self.count, 1).ok();
+        }
+    }
+
+    /// Count of currently available permits
+    pub fn available(&self) -> u32 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
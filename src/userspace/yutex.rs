@@ -1,12 +1,18 @@
-use crate::aarch64::asm;
+use crate::syscall::futex::{futex_wait, futex_wake};
 use core::cell::UnsafeCell;
 use core::ops::Deref;
 use core::ops::DerefMut;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicU32, Ordering};
 
-/// Spinlock based userspace Mutex type for allowing concurent access to protected data
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked and some task may sleep waiting for it
+const CONTENDED: u32 = 2;
+
+/// Futex based userspace Mutex type for allowing concurent access to protected data.
+/// Tasks waiting for the lock are suspended by kernel instead of spinning.
 pub struct Yutex<T> {
-    lock: AtomicBool,
+    lock: AtomicU32,
     data: UnsafeCell<T>,
 }
 
@@ -15,9 +21,15 @@ unsafe impl<T: Send> Send for Yutex<T> {}
 
 /// RAII Lock guard for Yutex type
 pub struct YutexGuard<'a, T> {
-    lock: &'a AtomicBool,
+    yutex: &'a Yutex<T>,
     data: &'a mut T,
 }
+impl<'a, T> YutexGuard<'a, T> {
+    /// Yutex that is locked by guard, used by condition variables to lock it again
+    pub fn yutex(guard: &Self) -> &'a Yutex<T> {
+        guard.yutex
+    }
+}
 impl<'a, T> Deref for YutexGuard<'a, T> {
     type Target = T;
     fn deref(&'_ self) -> &'_ T {
@@ -31,14 +43,20 @@ impl<'a, T> DerefMut for YutexGuard<'a, T> {
 }
 impl<'a, T> Drop for YutexGuard<'a, T> {
     fn drop(&mut self) {
-        self.lock.store(false, Ordering::Release);
+        release_lock(&self.yutex.lock);
+    }
+}
+
+fn release_lock(lock: &AtomicU32) {
+    if lock.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+        futex_wake(lock, 1).ok();
     }
 }
 impl<T> Yutex<T> {
     ///Crates new Yutex around provided data
     pub const fn new(data: T) -> Self {
         Yutex {
-            lock: AtomicBool::new(false),
+            lock: AtomicU32::new(UNLOCKED),
             data: UnsafeCell::new(data),
         }
     }
@@ -48,7 +66,7 @@ impl<T> Yutex<T> {
     pub fn lock(&self) -> YutexGuard<T> {
         self.take_lock();
         YutexGuard {
-            lock: &self.lock,
+            yutex: self,
             data: unsafe { &mut *self.data.get() },
         }
     }
@@ -63,16 +81,21 @@ impl<T> Yutex<T> {
 
         let result = f(unsafe { &mut *self.data.get() });
 
-        self.lock.store(false, Ordering::Release);
+        release_lock(&self.lock);
         result
     }
 
     fn take_lock(&self) {
-        while self.lock.compare_and_swap(false, true, Ordering::Acquire) {
-            // Wait until the lock seems unlocked
-            while self.lock.load(Ordering::Relaxed) {
-                crate::syscall::yield_cpu();
-            }
+        if self
+            .lock
+            .compare_and_swap(UNLOCKED, LOCKED, Ordering::Acquire)
+            == UNLOCKED
+        {
+            return;
+        }
+        // Mark lock as contended, so owner wakes us when releasing it
+        while self.lock.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            futex_wait(&self.lock, CONTENDED).ok();
         }
     }
 }
@@ -124,6 +124,25 @@ impl CircullarBuffer {
             == self.read_pointer.load(Ordering::Acquire) as u64
     }
 
+    /// Count of values that can be taken with get_value
+    pub fn value_count(&self) -> usize {
+        let mut pointer = self.read_pointer.load(Ordering::Acquire);
+        let write_pointer = self.write_pointer.load(Ordering::Acquire);
+        let end_of_buffer = self.data.as_ptr() as usize + BUFFER_SIZE;
+        let mut count = 0;
+        unsafe {
+            while pointer as usize != write_pointer as usize {
+                let size = *(pointer as *const usize) & !ONLY_MSB_OF_USIZE;
+                pointer = align_ptr_to_usize(pointer.add(size_of::<usize>() + size));
+                if pointer as usize >= end_of_buffer {
+                    pointer = pointer.sub(BUFFER_SIZE);
+                }
+                count += 1;
+            }
+        }
+        count
+    }
+
     pub fn reserve(&self, size: usize) -> Result<ReservedMemory, BufferAddValueError> {
         if size & ONLY_MSB_OF_USIZE != 0 {
             return Err(BufferAddValueError::SizeTooBig);
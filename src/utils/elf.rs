@@ -0,0 +1,125 @@
+use core::convert::TryInto;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+const ELF_TYPE_EXECUTABLE: u16 = 2;
+const ELF_MACHINE_AARCH64: u16 = 183;
+
+const ELF_HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+
+const PROGRAM_TYPE_LOAD: u32 = 1;
+
+pub const SEGMENT_EXECUTABLE: u32 = 1;
+pub const SEGMENT_WRITABLE: u32 = 2;
+pub const SEGMENT_READABLE: u32 = 4;
+
+#[derive(Debug)]
+pub enum ElfError {
+    /// File is too short or does not start with ELF magic
+    NotElf,
+    /// File is not 64 bit little endian AArch64 executable
+    Unsupported,
+    /// Program header table or segment points outside of the file
+    Truncated,
+}
+
+/// Loadable segment of static ELF executable
+#[derive(Debug)]
+pub struct Segment<'a> {
+    pub virtual_address: usize,
+    pub memory_size: usize,
+    pub flags: u32,
+    /// Data from the file, remaining memory_size - data.len() bytes are zeroed
+    pub data: &'a [u8],
+}
+
+impl Segment<'_> {
+    pub fn is_executable(&self) -> bool {
+        self.flags & SEGMENT_EXECUTABLE != 0
+    }
+    pub fn is_writable(&self) -> bool {
+        self.flags & SEGMENT_WRITABLE != 0
+    }
+}
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    pub entry: usize,
+    program_headers_offset: usize,
+    program_headers_count: usize,
+    program_header_size: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+fn read_u64(data: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < ELF_HEADER_SIZE || data[0..4] != ELF_MAGIC {
+            return Err(ElfError::NotElf);
+        }
+        if data[4] != ELF_CLASS_64
+            || data[5] != ELF_DATA_LITTLE_ENDIAN
+            || read_u16(data, 16) != ELF_TYPE_EXECUTABLE
+            || read_u16(data, 18) != ELF_MACHINE_AARCH64
+        {
+            return Err(ElfError::Unsupported);
+        }
+        let elf = Elf {
+            data,
+            entry: read_u64(data, 24),
+            program_headers_offset: read_u64(data, 32),
+            program_header_size: read_u16(data, 54) as usize,
+            program_headers_count: read_u16(data, 56) as usize,
+        };
+        if elf.program_header_size < PROGRAM_HEADER_SIZE {
+            return Err(ElfError::Unsupported);
+        }
+        let table_end = elf
+            .program_headers_count
+            .checked_mul(elf.program_header_size)
+            .and_then(|size| size.checked_add(elf.program_headers_offset));
+        match table_end {
+            Some(end) if end <= data.len() => Ok(elf),
+            _ => Err(ElfError::Truncated),
+        }
+    }
+
+    /// Returns PT_LOAD segments of the executable
+    pub fn segments(&self) -> Result<alloc::vec::Vec<Segment<'a>>, ElfError> {
+        let mut segments = alloc::vec::Vec::new();
+        for index in 0..self.program_headers_count {
+            let header = self.program_headers_offset + index * self.program_header_size;
+            if read_u32(self.data, header) != PROGRAM_TYPE_LOAD {
+                continue;
+            }
+            let flags = read_u32(self.data, header + 4);
+            let file_offset = read_u64(self.data, header + 8);
+            let virtual_address = read_u64(self.data, header + 16);
+            let file_size = read_u64(self.data, header + 32);
+            let memory_size = read_u64(self.data, header + 40);
+
+            let data = file_offset
+                .checked_add(file_size)
+                .filter(|end| *end <= self.data.len() && file_size <= memory_size)
+                .map(|end| &self.data[file_offset..end])
+                .ok_or(ElfError::Truncated)?;
+            segments.push(Segment {
+                virtual_address,
+                memory_size,
+                flags,
+                data,
+            });
+        }
+        Ok(segments)
+    }
+}
@@ -1,6 +1,8 @@
 pub mod binary_info;
 pub mod circullar_buffer;
 pub mod debug;
+pub mod elf;
+pub mod ring_buffer;
 pub mod struct_to_slice;
 
 pub const ONLY_MSB_OF_USIZE: usize = 1 << (core::mem::size_of::<usize>() * 8 - 1);
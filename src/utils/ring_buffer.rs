@@ -0,0 +1,60 @@
+use alloc::vec::Vec;
+
+/// Fixed capacity FIFO of bytes
+pub struct RingBuffer {
+    data: Vec<u8>,
+    head: usize,
+    len: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let mut data = Vec::new();
+        data.resize(capacity, 0);
+        RingBuffer {
+            data,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    /// Appends as much of bytes as fits, returns count of appended bytes
+    pub fn push_slice(&mut self, bytes: &[u8]) -> usize {
+        let count = core::cmp::min(bytes.len(), self.capacity() - self.len);
+        for (index, byte) in bytes[..count].iter().enumerate() {
+            let position = (self.head + self.len + index) % self.capacity();
+            self.data[position] = *byte;
+        }
+        self.len += count;
+        count
+    }
+
+    /// Moves oldest bytes to buffer, returns count of moved bytes
+    pub fn pop_slice(&mut self, buffer: &mut [u8]) -> usize {
+        let count = core::cmp::min(buffer.len(), self.len);
+        for (index, target) in buffer[..count].iter_mut().enumerate() {
+            *target = self.data[(self.head + index) % self.capacity()];
+        }
+        if count > 0 {
+            self.head = (self.head + count) % self.capacity();
+        }
+        self.len -= count;
+        count
+    }
+}
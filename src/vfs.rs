@@ -61,6 +61,64 @@ pub fn read(of: &mut OpenedFile, length: usize) -> Result<ReadData, FileError> {
     })
 }
 
+/// Checks if any file lies in directory given as absolute path, root always exists
+pub fn directory_exists(path: &str) -> bool {
+    let fs = VIRTUAL_FILE_SYSTEM.lock();
+    fs.directory_exists(path)
+}
+
+/// Resolves path relative to directory (both may contain `.` and `..`),
+/// returns absolute path without trailing slash
+pub fn join_path(directory: &str, path: &str) -> String {
+    let mut components = Vec::<&str>::new();
+    let base = if path.starts_with('/') { "" } else { directory };
+    for component in base.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => (),
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    let mut joined = String::new();
+    for component in components.iter() {
+        joined.push('/');
+        joined.push_str(component);
+    }
+    if joined.is_empty() {
+        joined.push('/');
+    }
+    joined
+}
+
+/// Name under which file with given absolute path is stored
+pub fn file_name(path: &str) -> &str {
+    path.trim_start_matches('/')
+}
+
+/// Size and open state of file
+pub fn stat(filename: &str) -> Result<FileStat, FileError> {
+    let fs = VIRTUAL_FILE_SYSTEM.lock();
+    fs.stat(filename)
+}
+
+/// Returns copy of whole file content, used for loading executables
+pub fn read_whole_file(filename: &str) -> Result<Vec<u8>, FileError> {
+    let fs = VIRTUAL_FILE_SYSTEM.lock();
+    fs.read_whole_file(filename)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileStat {
+    /// Length of file content in bytes
+    pub size: usize,
+    /// Count of descriptors that have file opened for reading
+    pub readers: usize,
+    pub opened_for_write: bool,
+}
+
 pub struct ReadData {
     pub data: *const u8,
     pub len: usize,
@@ -81,6 +139,14 @@ pub enum FileError {
     CannotReadWriteOnlyFile,
     CannotSeekSpecialFile,
     CannotCloseSpecialFile,
+    BadFileDescriptor,
+    BrokenPipe,
+    /// Descriptor limit of task was reached
+    TooManyOpenFiles,
+    /// Registered buffer does not exist or is too small for async syscall
+    InvalidBuffer,
+    /// Task was resumed to run signal handler before syscall could complete
+    Interrupted,
 }
 
 #[repr(usize)]
@@ -167,6 +233,11 @@ impl VFS {
         }
     }
 
+    pub fn directory_exists(&self, path: &str) -> bool {
+        let prefix = format!("{}/", file_name(path));
+        prefix == "/" || self.file_map.keys().any(|name| name.starts_with(&prefix))
+    }
+
     pub fn delete_file(&mut self, filename: &str) -> Result<(), FileError> {
         match self.file_map.get(filename) {
             Some(f) => {
@@ -206,6 +277,24 @@ impl VFS {
             cursor: 0,
         })
     }
+    pub fn stat(&self, filename: &str) -> Result<FileStat, FileError> {
+        match self.file_map.get(filename) {
+            Some(f) => Ok(FileStat {
+                size: f.data.len(),
+                readers: f.is_opened_for_read as usize,
+                opened_for_write: f.is_opened_for_write,
+            }),
+            None => Err(FileError::FileDoesNotExist),
+        }
+    }
+    pub fn read_whole_file(&self, filename: &str) -> Result<Vec<u8>, FileError> {
+        match self.file_map.get(filename) {
+            Some(f) if f.is_opened_for_write => Err(FileError::FileAlreadyOpenedForWrite),
+            Some(f) => Ok(f.data.clone()),
+            None => Err(FileError::FileDoesNotExist),
+        }
+    }
+
     pub fn read(&mut self, of: &mut OpenedFile, length: usize) -> Result<&[u8], FileError> {
         let file = match self.file_map.get(&of.filename) {
             Some(f) => {
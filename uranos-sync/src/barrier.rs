@@ -0,0 +1,44 @@
+/// Whether task arriving after given count of others releases all of them
+pub fn releases(arrived_before: u32, count: u32) -> bool {
+    arrived_before + 1 >= count
+}
+
+/// Whether task that arrived in given generation can leave. Generation wraps around, so it is
+/// only compared for equality.
+pub fn released(generation: u32, current_generation: u32) -> bool {
+    generation != current_generation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn last_task_releases_others() {
+        assert!(!releases(0, 3));
+        assert!(!releases(1, 3));
+        assert!(releases(2, 3));
+    }
+
+    #[test]
+    fn single_task_barrier_releases_at_once() {
+        assert!(releases(0, 1));
+        assert!(releases(0, 0));
+    }
+
+    #[test]
+    fn waiters_stay_until_generation_changes() {
+        assert!(!released(5, 5));
+        assert!(released(5, 6));
+    }
+
+    #[test]
+    fn generation_rollover_releases_waiters() {
+        let generation = AtomicU32::new(u32::MAX);
+        let waited = generation.load(Ordering::Acquire);
+        generation.fetch_add(1, Ordering::Release);
+        assert_eq!(generation.load(Ordering::Acquire), 0);
+        assert!(released(waited, generation.load(Ordering::Acquire)));
+    }
+}
@@ -0,0 +1,8 @@
+//! State transitions of userspace synchronization primitives, kept apart from the futex
+//! syscalls using them so they can be tested on host with `cargo test`
+#![cfg_attr(not(test), no_std)]
+
+pub mod barrier;
+pub mod once;
+pub mod rwlock;
+pub mod semaphore;
@@ -0,0 +1,74 @@
+pub const EMPTY: u32 = 0;
+pub const INITIALIZING: u32 = 1;
+/// Initializing and some task sleeps waiting for value
+pub const CONTENDED: u32 = 2;
+pub const READY: u32 = 3;
+/// Initializer did not finish, value will never be set
+pub const POISONED: u32 = 4;
+
+/// What task accessing cell does, after it tried to change its state from EMPTY to INITIALIZING
+#[derive(Debug, PartialEq)]
+pub enum Access {
+    /// Task changed the state, it initializes the value
+    Initialize,
+    /// Value is ready to be read
+    Read,
+    /// Other task initializes the value
+    Wait,
+    Poisoned,
+}
+
+/// Access to cell that was in given state
+pub fn access(state: u32) -> Access {
+    match state {
+        EMPTY => Access::Initialize,
+        READY => Access::Read,
+        POISONED => Access::Poisoned,
+        _ => Access::Wait,
+    }
+}
+
+/// State waiting task marks cell with before it sleeps, None if it is marked already
+pub fn contended(state: u32) -> Option<u32> {
+    if state == INITIALIZING {
+        Some(CONTENDED)
+    } else {
+        None
+    }
+}
+
+/// Whether initializer leaving cell in given state has to wake waiting tasks
+pub fn wakes_waiting(state: u32) -> bool {
+    state == CONTENDED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_task_initializes() {
+        assert_eq!(access(EMPTY), Access::Initialize);
+    }
+
+    #[test]
+    fn others_wait_during_initialization() {
+        assert_eq!(access(INITIALIZING), Access::Wait);
+        assert_eq!(access(CONTENDED), Access::Wait);
+        assert_eq!(contended(INITIALIZING), Some(CONTENDED));
+        assert_eq!(contended(CONTENDED), None);
+    }
+
+    #[test]
+    fn completion_makes_value_readable() {
+        assert_eq!(access(READY), Access::Read);
+        assert!(wakes_waiting(CONTENDED));
+        assert!(!wakes_waiting(INITIALIZING));
+    }
+
+    #[test]
+    fn poisoned_cell_is_never_initialized() {
+        assert_eq!(access(POISONED), Access::Poisoned);
+        assert_eq!(contended(POISONED), None);
+    }
+}
@@ -0,0 +1,104 @@
+/// Lock is held by writer, other bits count readers
+pub const WRITER: u32 = 1 << 30;
+/// Some task sleeps waiting for the lock
+pub const WAITING: u32 = 1 << 31;
+pub const READERS: u32 = WRITER - 1;
+
+/// State after taking read lock, None if writer holds the lock
+pub fn read_locked(state: u32) -> Option<u32> {
+    if state & WRITER != 0 || state & READERS == READERS {
+        None
+    } else {
+        Some(state + 1)
+    }
+}
+
+/// State after taking write lock, None if lock is held by anyone
+pub fn write_locked(state: u32) -> Option<u32> {
+    if state & (WRITER | READERS) != 0 {
+        None
+    } else {
+        Some(state | WRITER)
+    }
+}
+
+/// State after releasing read lock
+pub fn read_unlocked(state: u32) -> u32 {
+    state - 1
+}
+
+/// State after releasing write lock
+pub fn write_unlocked(state: u32) -> u32 {
+    state & !WRITER
+}
+
+/// State after task that could not take the lock marks it as waited for
+pub fn waited_for(state: u32) -> u32 {
+    state | WAITING
+}
+
+/// Whether lock became free while some tasks wait for it, so they have to be woken
+pub fn wakes_waiting(state: u32) -> bool {
+    state == WAITING
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readers_share_lock() {
+        let state = read_locked(0).unwrap();
+        let state = read_locked(state).unwrap();
+        assert_eq!(state & READERS, 2);
+        assert_eq!(write_locked(state), None);
+        let state = read_unlocked(state);
+        let state = read_unlocked(state);
+        assert_eq!(state, 0);
+        assert!(write_locked(state).is_some());
+    }
+
+    #[test]
+    fn writer_excludes_everyone() {
+        let state = write_locked(0).unwrap();
+        assert_eq!(state, WRITER);
+        assert_eq!(read_locked(state), None);
+        assert_eq!(write_locked(state), None);
+        assert_eq!(write_unlocked(state), 0);
+    }
+
+    #[test]
+    fn reader_count_does_not_overflow_into_writer_bit() {
+        assert_eq!(read_locked(READERS), None);
+        assert_eq!(read_locked(READERS - 1), Some(READERS));
+    }
+
+    #[test]
+    fn waiting_writer_is_woken_by_last_reader() {
+        let state = read_locked(read_locked(0).unwrap()).unwrap();
+        assert_eq!(write_locked(state), None);
+        let state = waited_for(state);
+        // readers can still join while writer waits
+        let state = read_locked(state).unwrap();
+        let state = read_unlocked(state);
+        let state = read_unlocked(state);
+        assert!(!wakes_waiting(state));
+        let state = read_unlocked(state);
+        assert!(wakes_waiting(state));
+    }
+
+    #[test]
+    fn waiting_reader_is_woken_by_writer() {
+        let state = write_locked(0).unwrap();
+        assert_eq!(read_locked(state), None);
+        let state = waited_for(state);
+        let state = write_unlocked(state);
+        assert!(wakes_waiting(state));
+    }
+
+    #[test]
+    fn unlocking_without_waiters_wakes_nobody() {
+        assert!(!wakes_waiting(write_unlocked(WRITER)));
+        assert!(!wakes_waiting(read_unlocked(1)));
+    }
+}
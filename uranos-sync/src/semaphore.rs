@@ -0,0 +1,20 @@
+/// Count after taking one permit, None if there are none left
+pub fn decremented(count: u32) -> Option<u32> {
+    count.checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permit_is_taken_while_count_is_positive() {
+        assert_eq!(decremented(2), Some(1));
+        assert_eq!(decremented(1), Some(0));
+    }
+
+    #[test]
+    fn no_permit_is_taken_at_zero() {
+        assert_eq!(decremented(0), None);
+    }
+}